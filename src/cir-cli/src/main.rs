@@ -0,0 +1,220 @@
+use std::path::PathBuf;
+
+use cir::db::{self, SourceDatabase, SourceDatabaseStorage};
+use cir::{FileId, Item, SourceMap, ValueDefId, VariantId};
+use cir_parse::{LowerDatabase, LowerDatabaseStorage, ResolveErrorKind, TypeNs, ValueNs};
+use cir_typecheck::{TypecheckDatabase, TypecheckDatabaseStorage, Variance};
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+
+#[salsa::database(SourceDatabaseStorage, LowerDatabaseStorage, TypecheckDatabaseStorage)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {
+}
+
+fn main() -> anyhow::Result<()> {
+    let usage = "usage: cir [--strict-positivity] [--info <type>] <file>...";
+    let mut strict_positivity = false;
+    let mut info = None;
+    let mut paths = vec![];
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // Reject data types that occur negatively in their own definitions
+            "--strict-positivity" => strict_positivity = true,
+            // Print the definition of a data type with the variances of its parameters
+            "--info" => info = Some(args.next().ok_or_else(|| anyhow::anyhow!(usage))?),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        anyhow::bail!(usage);
+    }
+
+    let mut source_map = SourceMap::default();
+    let prelude = cir_parse::add_prelude(&mut source_map);
+    for path in paths {
+        let source = std::fs::read_to_string(&path)?;
+        source_map.add(path, source);
+    }
+
+    let mut db = Database::default();
+    db::set_source_map(&mut db, &source_map);
+    load_module_files(&mut db, &mut source_map)?;
+
+    let mut diagnostics = vec![];
+    for &file in db.files().iter().filter(|&&file| file != prelude) {
+        check_file(&db, file, strict_positivity, &mut diagnostics);
+    }
+
+    let writer = StandardStream::stderr(ColorChoice::Auto);
+    let config = codespan_reporting::term::Config::default();
+    for diagnostic in &diagnostics {
+        codespan_reporting::term::emit(&mut writer.lock(), &config, &source_map, diagnostic)?;
+    }
+
+    if !diagnostics.is_empty() {
+        std::process::exit(1);
+    }
+
+    if let Some(name) = info {
+        print_info(&db, &name)?;
+    }
+
+    if let Some(main) = find_main(&db) {
+        match cir_eval::Interpreter::new(&db).eval_value_def(main) {
+            Ok(value) => println!("main = {}", value.display(&db)),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The value def named `main` in the root module, if any
+fn find_main(db: &Database) -> Option<ValueDefId> {
+    let def_map = db.def_map();
+    let main = cir::Path::single(cir::Name::new(cir::Span::new(FileId(0), 0, 0), "main"));
+    match def_map.resolve_value_path(def_map.root(), &main) {
+        Ok(ValueNs::ValueDef(def)) => Some(def),
+        _ => None,
+    }
+}
+
+/// Prints the constructors of the data type at `path` (e.g. `M.T`) in the root module, headed by
+/// the variances of its parameters
+fn print_info(db: &Database, path: &str) -> anyhow::Result<()> {
+    let def_map = db.def_map();
+    let path = path
+        .split('.')
+        .map(|name| cir::Name::new(cir::Span::new(FileId(0), 0, 0), name))
+        .collect::<cir::Path>();
+    let data_def = match def_map.resolve_type_path(def_map.root(), &path) {
+        Ok(TypeNs::DataDef(data_def)) => data_def,
+        Ok(_) => anyhow::bail!("`{}` is not a data type", path),
+        Err(err) => anyhow::bail!("{}", err),
+    };
+    let items = db.items(data_def.file);
+    let data_def_data = &items[data_def.def];
+    let variances = db.variances(data_def);
+    println!("{}", format_variances(data_def_data.name.symbol.as_str(), &variances));
+    for (variant, variant_data) in data_def_data.variants.iter() {
+        let ty = db.ctor_ty(VariantId { data_def, variant });
+        println!("  {} : {}", variant_data.name.symbol.as_str(), cir_parse::display_ty(db, &ty));
+    }
+    Ok(())
+}
+
+/// Adds the files of `module M;` declarations that were not given on the command line
+fn load_module_files(db: &mut Database, source_map: &mut SourceMap) -> anyhow::Result<()> {
+    loop {
+        let missing = db
+            .def_map()
+            .errors()
+            .iter()
+            .filter_map(|err| match &err.kind {
+                ResolveErrorKind::MissingModuleFile(_, path) if path.exists() => Some(path.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        for path in missing {
+            let source = std::fs::read_to_string(&path)?;
+            source_map.add(path, source);
+        }
+        db::set_source_map(db, source_map);
+    }
+}
+
+fn check_file(
+    db: &Database,
+    file: FileId,
+    strict_positivity: bool,
+    diagnostics: &mut Vec<Diagnostic<FileId>>,
+) {
+    let (items, errors) = match db.lower_file_with_errors(file) {
+        Ok(lowered) => lowered,
+        Err(err) => {
+            let offset = err.location.offset;
+            diagnostics.push(
+                Diagnostic::error()
+                    .with_message(format!("expected {}", err.expected))
+                    .with_labels(vec![Label::primary(file, offset..offset)]),
+            );
+            return;
+        }
+    };
+
+    if !errors.is_empty() {
+        diagnostics.extend(errors.iter().map(|err| {
+            Diagnostic::error()
+                .with_message(err.to_string())
+                .with_labels(vec![Label::primary(err.span.file, err.span.range())])
+        }));
+        return;
+    }
+
+    if strict_positivity {
+        let errors = db.positivity_errors(file);
+        if !errors.is_empty() {
+            diagnostics.extend(errors.iter().map(|err| {
+                Diagnostic::error()
+                    .with_message(err.to_string())
+                    .with_labels(vec![Label::primary(err.span.file, err.span.range())])
+            }));
+            return;
+        }
+    }
+
+    let errors = db.type_errors(file);
+    if !errors.is_empty() {
+        diagnostics.extend(errors.iter().map(|err| {
+            Diagnostic::error()
+                .with_message(err.display(db).to_string())
+                .with_labels(vec![Label::primary(err.span.file, err.span.range())])
+        }));
+        return;
+    }
+
+    for item in &items.items {
+        if let Item::ValueDef(def) = *item {
+            let ty = db.type_of_value_def(ValueDefId { file, def });
+            println!("{}: {}", items[def].name.symbol.as_str(), cir_parse::display_ty(db, &ty));
+        }
+    }
+}
+
+/// The variances of the parameters of a data type, e.g. `Either: + +`
+fn format_variances(name: &str, variances: &[Variance]) -> String {
+    let mut s = format!("{}:", name);
+    for variance in variances {
+        s.push_str(&format!(" {}", variance));
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_variances() {
+        assert_eq!(format_variances("Bool", &[]), "Bool:");
+        assert_eq!(format_variances("List", &[Variance::Covariant]), "List: +");
+        let variances = [
+            Variance::Contravariant,
+            Variance::Covariant,
+            Variance::Invariant,
+            Variance::Bivariant,
+        ];
+        assert_eq!(format_variances("T", &variances), "T: - + = *");
+    }
+}
@@ -0,0 +1,318 @@
+//! A tree-walking interpreter for well-typed programs.
+//! Evaluation is strict, types are erased except that type abstractions delay evaluation of their
+//! body until they are applied. The elaborated bodies are evaluated, so constraints have become
+//! dictionary arguments.
+
+mod value;
+
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::Arc;
+
+use cir::{BinOp, BodyData, Expr, ExprData, InstanceId, Lit, Prim, UnOp, ValueDefId, VariantId};
+use cir_typecheck::TypecheckDatabase;
+use rustc_hash::FxHashMap;
+
+pub use self::value::{Binding, Closure, Env, Value};
+
+pub type EvalResult<T = Value> = Result<T, EvalError>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    DivisionByZero,
+    /// `charAt` with an index outside of the string
+    IndexOutOfBounds {
+        index: i64,
+        len: usize,
+    },
+    /// No alternative of a `match` matched the scrutinee
+    MatchFailure,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::DivisionByZero => write!(f, "division by zero"),
+            EvalError::IndexOutOfBounds { index, len } =>
+                write!(f, "index {} is out of bounds for a string of length {}", index, len),
+            EvalError::MatchFailure => write!(f, "no alternative matched the scrutinee"),
+        }
+    }
+}
+
+pub struct Interpreter<'db> {
+    db: &'db dyn TypecheckDatabase,
+    /// The values of the top-level definitions that have been evaluated so far
+    globals: RefCell<FxHashMap<ValueDefId, Value>>,
+}
+
+impl<'db> Interpreter<'db> {
+    pub fn new(db: &'db dyn TypecheckDatabase) -> Self {
+        Self { db, globals: Default::default() }
+    }
+
+    pub fn eval_value_def(&self, def: ValueDefId) -> EvalResult {
+        if let Some(value) = self.globals.borrow().get(&def) {
+            return Ok(value.clone());
+        }
+        let body = self.db.elaborated_body(def);
+        let value = self.eval(&body, &Env::default(), body.expr)?;
+        self.globals.borrow_mut().insert(def, value.clone());
+        Ok(value)
+    }
+
+    /// Evaluates the dictionary of an instance, which is a function of its type parameters and
+    /// the dictionaries of its context so it is cheap to evaluate
+    fn eval_instance(&self, instance: InstanceId) -> EvalResult {
+        let body = self.db.elaborated_instance_body(instance);
+        self.eval(&body, &Env::default(), body.expr)
+    }
+
+    fn eval(&self, body: &Arc<BodyData>, env: &Env, expr: Expr) -> EvalResult {
+        match &body[expr] {
+            ExprData::Var(binder) => match env.lookup(*binder) {
+                Binding::Value(value) => Ok(value),
+                Binding::Rec(expr, env) => self.eval(body, &env, expr),
+            },
+            ExprData::Def(def) => self.eval_value_def(*def),
+            ExprData::Ctor(variant) => Ok(self.ctor(*variant)),
+            ExprData::Instance(instance) => self.eval_instance(*instance),
+            ExprData::Method(_) => unreachable!("method in an elaborated body"),
+            ExprData::Lit(lit) => Ok(match lit {
+                &Lit::Bool(b) => Value::Bool(b),
+                &Lit::Int(i) => Value::Int(i),
+                &Lit::Float(f) => Value::Float(f.value()),
+                &Lit::Char(c) => Value::Char(c),
+                Lit::String(s) => Value::String(s.clone()),
+            }),
+            &ExprData::Lambda(binder, expr) => Ok(Value::Closure(Arc::new(Closure {
+                body: Arc::clone(body),
+                env: env.clone(),
+                binder,
+                expr,
+            }))),
+            &ExprData::App(f, x) => {
+                let f = self.eval(body, env, f)?;
+                match &body[x] {
+                    ExprData::Type(_) => self.apply_ty(f),
+                    _ => {
+                        let x = self.eval(body, env, x)?;
+                        self.apply(f, x)
+                    }
+                }
+            }
+            ExprData::Case(scrutinee, alts) => {
+                let scrutinee = self.eval(body, env, *scrutinee)?;
+                for alt in alts {
+                    let (binders, fields) = match (&alt.pat, &scrutinee) {
+                        (cir::Pat::Variant(v, _, binders), Value::Data(variant, fields))
+                            if v == variant =>
+                            (binders, fields),
+                        (cir::Pat::Variant(..), Value::Data(..)) => continue,
+                        (cir::Pat::Tuple(binders), Value::Tuple(fields)) => (binders, fields),
+                        (cir::Pat::Err(..), _) =>
+                            unreachable!("evaluating a pattern that failed to lower"),
+                        (_, value) => unreachable!("ill-typed scrutinee: {:?}", value),
+                    };
+                    let env = binders
+                        .iter()
+                        .zip(fields.iter())
+                        .fold(env.clone(), |env, (&binder, field)| env.bind(binder, field.clone()));
+                    return self.eval(body, &env, alt.body);
+                }
+                Err(EvalError::MatchFailure)
+            }
+            &ExprData::If(c, t, e) => match self.eval(body, env, c)? {
+                Value::Bool(true) => self.eval(body, env, t),
+                Value::Bool(false) => self.eval(body, env, e),
+                value => unreachable!("ill-typed condition: {:?}", value),
+            },
+            &ExprData::Let(binder, e1, e2) => {
+                let value = self.eval(body, env, e1)?;
+                self.eval(body, &env.bind(binder, value), e2)
+            }
+            ExprData::LetRec(bindings, e) => self.eval(body, &env.bind_rec(bindings), *e),
+            &ExprData::BinOp(op, l, r) => self.eval_bin_op(body, env, op, l, r),
+            &ExprData::UnOp(op, expr) => Ok(match (op, self.eval(body, env, expr)?) {
+                (UnOp::Not, Value::Bool(b)) => Value::Bool(!b),
+                (UnOp::Neg, Value::Int(i)) => Value::Int(i.wrapping_neg()),
+                (UnOp::Neg, Value::Float(f)) => Value::Float(-f),
+                (op, value) => unreachable!("ill-typed operand of `{:?}`: {:?}", op, value),
+            }),
+            &ExprData::Prim(prim) => Ok(Value::PartialPrim(prim, vec![])),
+            ExprData::Tuple(exprs) => Ok(Value::Tuple(
+                exprs.iter().map(|&expr| self.eval(body, env, expr)).collect::<EvalResult<_>>()?,
+            )),
+            ExprData::Record(fields) => Ok(Value::Record(
+                fields
+                    .iter()
+                    .map(|(name, expr)| Ok((name.clone(), self.eval(body, env, *expr)?)))
+                    .collect::<EvalResult<_>>()?,
+            )),
+            ExprData::Field(expr, name) => match self.eval(body, env, *expr)? {
+                Value::Record(fields) => {
+                    let (_, value) = fields
+                        .iter()
+                        .find(|(field, _)| field == name)
+                        .expect("ill-typed field access");
+                    Ok(value.clone())
+                }
+                value => unreachable!("field access on a non-record: {:?}", value),
+            },
+            // The hidden type is erased
+            &ExprData::Pack(_, expr, _) => self.eval(body, env, expr),
+            &ExprData::Unpack(_, binder, e1, e2) => {
+                let value = self.eval(body, env, e1)?;
+                self.eval(body, &env.bind(binder, value), e2)
+            }
+            // `fold` and `unfold` only change the type of a value
+            &ExprData::Fold(_, expr) | &ExprData::Unfold(expr) => self.eval(body, env, expr),
+            ExprData::Type(_) => unreachable!("found type in expression position"),
+            ExprData::Err => unreachable!("evaluating an expression that failed to lower"),
+        }
+    }
+
+    fn eval_bin_op(
+        &self,
+        body: &Arc<BodyData>,
+        env: &Env,
+        op: BinOp,
+        l: Expr,
+        r: Expr,
+    ) -> EvalResult {
+        let l = self.eval(body, env, l)?;
+        // `&&` and `||` only evaluate their right operand if necessary
+        match (op, &l) {
+            (BinOp::And, Value::Bool(false)) | (BinOp::Or, Value::Bool(true)) => return Ok(l),
+            (BinOp::And | BinOp::Or, _) => return self.eval(body, env, r),
+            _ => {}
+        }
+        let r = self.eval(body, env, r)?;
+        let value = match (l, r) {
+            (Value::Int(l), Value::Int(r)) => match op {
+                BinOp::Add => Value::Int(l.wrapping_add(r)),
+                BinOp::Sub => Value::Int(l.wrapping_sub(r)),
+                BinOp::Mul => Value::Int(l.wrapping_mul(r)),
+                BinOp::Div | BinOp::Rem if r == 0 => return Err(EvalError::DivisionByZero),
+                // `i64::MIN / -1` wraps to `i64::MIN` (and the remainder is 0)
+                BinOp::Div => Value::Int(l.wrapping_div(r)),
+                BinOp::Rem => Value::Int(l.wrapping_rem(r)),
+                BinOp::Eq => Value::Bool(l == r),
+                BinOp::Ne => Value::Bool(l != r),
+                BinOp::Lt => Value::Bool(l < r),
+                BinOp::Le => Value::Bool(l <= r),
+                BinOp::Gt => Value::Bool(l > r),
+                BinOp::Ge => Value::Bool(l >= r),
+                BinOp::And | BinOp::Or => unreachable!(),
+            },
+            // Floats follow IEEE 754, so division by zero gives an infinity or NaN
+            (Value::Float(l), Value::Float(r)) => match op {
+                BinOp::Add => Value::Float(l + r),
+                BinOp::Sub => Value::Float(l - r),
+                BinOp::Mul => Value::Float(l * r),
+                BinOp::Div => Value::Float(l / r),
+                BinOp::Rem => Value::Float(l % r),
+                BinOp::Eq => Value::Bool(l == r),
+                BinOp::Ne => Value::Bool(l != r),
+                BinOp::Lt => Value::Bool(l < r),
+                BinOp::Le => Value::Bool(l <= r),
+                BinOp::Gt => Value::Bool(l > r),
+                BinOp::Ge => Value::Bool(l >= r),
+                BinOp::And | BinOp::Or => unreachable!(),
+            },
+            (Value::Bool(l), Value::Bool(r)) => match op {
+                BinOp::Eq => Value::Bool(l == r),
+                BinOp::Ne => Value::Bool(l != r),
+                _ => unreachable!("ill-typed operands of `{}`", op),
+            },
+            (Value::Char(l), Value::Char(r)) => match op {
+                BinOp::Eq => Value::Bool(l == r),
+                BinOp::Ne => Value::Bool(l != r),
+                _ => unreachable!("ill-typed operands of `{}`", op),
+            },
+            (Value::String(l), Value::String(r)) => match op {
+                BinOp::Eq => Value::Bool(l == r),
+                BinOp::Ne => Value::Bool(l != r),
+                _ => unreachable!("ill-typed operands of `{}`", op),
+            },
+            (l, r) => unreachable!("ill-typed operands of `{}`: {:?} and {:?}", op, l, r),
+        };
+        Ok(value)
+    }
+
+    pub fn apply(&self, f: Value, arg: Value) -> EvalResult {
+        match f {
+            Value::Closure(closure) => {
+                let env = closure.env.bind(closure.binder, arg);
+                self.eval(&closure.body, &env, closure.expr)
+            }
+            Value::PartialCtor(variant, arity, mut args) => {
+                args.push(arg);
+                Ok(if args.len() == arity {
+                    Value::Data(variant, args.into())
+                } else {
+                    Value::PartialCtor(variant, arity, args)
+                })
+            }
+            Value::PartialPrim(prim, mut args) => {
+                args.push(arg);
+                if args.len() == prim.arity() {
+                    self.eval_prim(prim, &args)
+                } else {
+                    Ok(Value::PartialPrim(prim, args))
+                }
+            }
+            value => unreachable!("applying a non-function: {:?}", value),
+        }
+    }
+
+    /// Evaluates a fully applied primitive, strings are indexed by character
+    fn eval_prim(&self, prim: Prim, args: &[Value]) -> EvalResult {
+        let value = match (prim, args) {
+            (Prim::StringLength, [Value::String(s)]) => Value::Int(s.chars().count() as i64),
+            (Prim::StringConcat, [Value::String(l), Value::String(r)]) =>
+                Value::String(format!("{}{}", l, r).into()),
+            (Prim::CharAt, [Value::String(s), Value::Int(index)]) => {
+                let c = usize::try_from(*index).ok().and_then(|i| s.chars().nth(i));
+                match c {
+                    Some(c) => Value::Char(c),
+                    None => {
+                        let len = s.chars().count();
+                        return Err(EvalError::IndexOutOfBounds { index: *index, len });
+                    }
+                }
+            }
+            // The same as displaying the value
+            (Prim::ShowInt, [Value::Int(i)]) => Value::String(i.to_string().into()),
+            (Prim::ShowFloat, [Value::Float(x)]) => Value::String(format!("{:?}", x).into()),
+            (Prim::ShowChar, [Value::Char(c)]) => Value::String(format!("{:?}", c).into()),
+            (Prim::ShowString, [Value::String(s)]) =>
+                Value::String(format!("{:?}", s.as_str()).into()),
+            (prim, args) => unreachable!("ill-typed arguments of `{:?}`: {:?}", prim, args),
+        };
+        Ok(value)
+    }
+
+    /// Applies `f` to a (erased) type argument
+    fn apply_ty(&self, f: Value) -> EvalResult {
+        match f {
+            Value::Closure(closure) => match closure.body.binders[closure.binder] {
+                cir::BinderData::Ty(_) => self.eval(&closure.body, &closure.env, closure.expr),
+                _ => unreachable!("type application of a value lambda"),
+            },
+            // Constructors are not type abstractions after erasure
+            value => Ok(value),
+        }
+    }
+
+    fn ctor(&self, variant: VariantId) -> Value {
+        let items = self.db.items(variant.data_def.file);
+        match items[variant.data_def.def].variants[variant.variant].fields.len() {
+            0 => Value::Data(variant, Arc::from([])),
+            arity => Value::PartialCtor(variant, arity, vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
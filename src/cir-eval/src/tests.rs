@@ -0,0 +1,388 @@
+use cir::db::{self, SourceDatabaseStorage};
+use cir::{FileId, SourceMap, ValueDefId};
+use cir_parse::{LowerDatabase, LowerDatabaseStorage};
+use cir_typecheck::{TypecheckDatabase, TypecheckDatabaseStorage};
+
+use super::*;
+
+#[salsa::database(SourceDatabaseStorage, LowerDatabaseStorage, TypecheckDatabaseStorage)]
+#[derive(Default)]
+struct TestDatabase {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for TestDatabase {
+}
+
+/// Evaluates the definition `name` in `src` (with the prelude loaded) and displays the result
+fn eval(src: &str, name: &str) -> Result<String, EvalError> {
+    let mut db = TestDatabase::default();
+    let mut source_map = SourceMap::default();
+    cir_parse::add_prelude(&mut source_map);
+    let file = source_map.add("main.cir", src);
+    db::set_source_map(&mut db, &source_map);
+    let def = value_def(&db, file, name);
+    let value = Interpreter::new(&db).eval_value_def(def)?;
+    let display = value.display(&db).to_string();
+    Ok(display)
+}
+
+fn value_def(db: &TestDatabase, file: FileId, name: &str) -> ValueDefId {
+    let (items, errors) = db.lower_file_with_errors(file).unwrap();
+    assert!(errors.is_empty(), "{:?}", errors);
+    let type_errors = db.type_errors(file);
+    assert!(type_errors.is_empty(), "{:?}", type_errors);
+    let (def, _) = items.value_defs.iter().find(|(_, def)| def.name.symbol == name).unwrap();
+    ValueDefId { file, def }
+}
+
+#[test]
+fn test_eval_arithmetic() {
+    assert_eq!(eval("let x: Int = 1 + 2 * 3 - 4;", "x"), Ok("3".to_owned()));
+    assert_eq!(eval("let x: Int = (1 + 2) * 3;", "x"), Ok("9".to_owned()));
+    assert_eq!(eval("let x: Int = 7 / 2 + 7 % 2;", "x"), Ok("4".to_owned()));
+    assert_eq!(eval("let x: Int = -7 / 2;", "x"), Ok("-3".to_owned()));
+    assert_eq!(eval("let x: Int = -7 % 2;", "x"), Ok("-1".to_owned()));
+    assert_eq!(eval("let x: Int = 5 -1;", "x"), Ok("4".to_owned()));
+    assert_eq!(eval("let x: Int = - (2 + 3);", "x"), Ok("-5".to_owned()));
+}
+
+#[test]
+fn test_eval_overflow_wraps() {
+    assert_eq!(eval("let x: Int = 9223372036854775807 + 1;", "x"), Ok(i64::MIN.to_string()));
+    assert_eq!(eval("let x: Int = -9223372036854775807 - 1;", "x"), Ok(i64::MIN.to_string()));
+    assert_eq!(
+        eval("let min: Int = -9223372036854775807 - 1; let x: Int = min / -1;", "x"),
+        Ok(i64::MIN.to_string())
+    );
+    assert_eq!(eval("let x: Int = 4611686018427387904 * 2;", "x"), Ok(i64::MIN.to_string()));
+}
+
+#[test]
+fn test_eval_division_by_zero() {
+    assert_eq!(eval("let x: Int = 1 / 0;", "x"), Err(EvalError::DivisionByZero));
+    assert_eq!(eval("let x: Int = 1 % (2 - 2);", "x"), Err(EvalError::DivisionByZero));
+}
+
+#[test]
+fn test_eval_comparison_and_boolean() {
+    assert_eq!(eval("let x: Bool = 1 < 2 && 2 <= 2 && 3 > 2 && 3 >= 4;", "x"), Ok("false".into()));
+    assert_eq!(eval("let x: Bool = 1 == 2 || 1 != 2;", "x"), Ok("true".into()));
+    assert_eq!(eval("let x: Bool = !(true == false);", "x"), Ok("true".into()));
+    // The right operand is not evaluated
+    assert_eq!(eval("let x: Bool = false && 1 / 0 == 0;", "x"), Ok("false".into()));
+    assert_eq!(eval("let x: Bool = true || 1 / 0 == 0;", "x"), Ok("true".into()));
+}
+
+#[test]
+fn test_eval_float() {
+    assert_eq!(eval("let x: Float = 1.5 + 2.0 * 3.0;", "x"), Ok("7.5".into()));
+    assert_eq!(eval("let x: Float = 1e-3 * -2.0;", "x"), Ok("-0.002".into()));
+    assert_eq!(eval("let x: Float = 7.5 % 2.0;", "x"), Ok("1.5".into()));
+    assert_eq!(eval("let x: Float = 1.0 / 0.0;", "x"), Ok("inf".into()));
+    assert_eq!(eval("let x: Bool = 0.1 + 0.2 != 0.3 && -1.5 < 2.5e0;", "x"), Ok("true".into()));
+}
+
+#[test]
+fn test_eval_string_and_char() {
+    assert_eq!(
+        eval(r#"let x: String = stringConcat "foo" "bar\n";"#, "x"),
+        Ok(r#""foobar\n""#.into())
+    );
+    assert_eq!(eval(r#"let x: Int = stringLength "h\u{e9}llo";"#, "x"), Ok("5".into()));
+    assert_eq!(eval(r#"let x: Char = charAt "h\u{e9}llo" 1;"#, "x"), Ok("'é'".into()));
+    assert_eq!(
+        eval(r#"let x: Bool = "ab" == stringConcat "a" "b" && 'a' != 'b';"#, "x"),
+        Ok("true".into())
+    );
+    assert_eq!(
+        eval(r#"let x: Char = charAt "abc" 3;"#, "x"),
+        Err(EvalError::IndexOutOfBounds { index: 3, len: 3 })
+    );
+    assert_eq!(
+        eval(r#"let x: Char = charAt "abc" (-1);"#, "x"),
+        Err(EvalError::IndexOutOfBounds { index: -1, len: 3 })
+    );
+    // Primitives can be partially applied and shadowed
+    let src = r#"
+        let greet: String -> String = stringConcat "hello ";
+        let x: String = greet "world";
+        let stringLength: Int = 0;
+        let y: Int = stringLength;
+    "#;
+    assert_eq!(eval(src, "x"), Ok(r#""hello world""#.into()));
+    assert_eq!(eval(src, "y"), Ok("0".into()));
+}
+
+#[test]
+fn test_eval_tuple() {
+    let src = r#"
+        let divMod: Int -> Int -> (Int, Int) = \n:Int. \d:Int. (n / d, n % d);
+        let a: (Int, Int) = divMod 7 2;
+        let b: Int = match divMod 7 2 { (q, r) -> q * 10 + r };
+        let c: ((), (Bool, String)) = ((), (true, "x"));
+    "#;
+    assert_eq!(eval(src, "a"), Ok("(3, 1)".into()));
+    assert_eq!(eval(src, "b"), Ok("31".into()));
+    assert_eq!(eval(src, "c"), Ok(r#"((), (true, "x"))"#.into()));
+}
+
+#[test]
+fn test_eval_record() {
+    let src = r#"
+        data Point = Point { x: Int, y: Int };
+        let origin: Point = Point { y = 0, x = 0 };
+        let norm1: Point -> Int = \p:Point. match p { Point r -> r.x + r.y };
+        let a: Int = norm1 (Point { x = 3, y = 4 });
+        let b: { name: String, point: Point } = { point = origin, name = "o" };
+        let c: Int = { inner = { value = 42 } }.inner.value;
+    "#;
+    assert_eq!(eval(src, "a"), Ok("7".into()));
+    assert_eq!(eval(src, "b"), Ok(r#"{ name = "o", point = Point { x = 0, y = 0 } }"#.into()));
+    assert_eq!(eval(src, "c"), Ok("42".into()));
+}
+
+#[test]
+fn test_eval_existential() {
+    let src = r#"
+        let counter: exists c. { new: c, inc: c -> c, get: c -> Int } =
+            pack @Int { new = 0, inc = \n:Int. n + 1, get = \n:Int. n }
+            as exists c. { new: c, inc: c -> c, get: c -> Int };
+        let x: Int = unpack @c k = counter in k.get (k.inc (k.inc k.new));
+    "#;
+    assert_eq!(eval(src, "x"), Ok("2".into()));
+}
+
+#[test]
+fn test_eval_type_operators() {
+    let src = r#"
+        let twice: forall (f : * -> *). (forall a. a -> f a) -> Int -> f (f Int) =
+            \@(f : * -> *). \pure:(forall a. a -> f a). \x:Int. pure @(f Int) (pure @Int x);
+        let x: Maybe (Maybe Int) = twice @Maybe (\@a. \x:a. Just @a x) 1;
+        let y: List (List Int) = twice @List (\@a. \x:a. Cons @a x (Nil @a)) 2;
+    "#;
+    assert_eq!(eval(src, "x"), Ok("Just (Just 1)".into()));
+    assert_eq!(eval(src, "y"), Ok("Cons (Cons 2 Nil) Nil".into()));
+}
+
+#[test]
+fn test_eval_type_synonyms() {
+    let src = r#"
+        type Pair a b = forall r. (a -> b -> r) -> r;
+        let pair: forall a. forall b. a -> b -> Pair a b =
+            \@a. \@b. \x:a. \y:b. \@r. \k:(a -> b -> r). k x y;
+        let swap: Pair Int Bool -> Pair Bool Int =
+            \p:Pair Int Bool. p @(Pair Bool Int) (\x:Int. \y:Bool. pair @Bool @Int y x);
+        let x: (Bool, Int) = swap (pair @Int @Bool 1 true) @(Bool, Int) (\a:Bool. \b:Int. (a, b));
+    "#;
+    assert_eq!(eval(src, "x"), Ok("(true, 1)".into()));
+}
+
+#[test]
+fn test_eval_recursive_types() {
+    let src = r#"
+        type IntList = mu l. Maybe (Int, l);
+        let nil: IntList = fold @IntList (Nothing @(Int, IntList));
+        let cons: Int -> IntList -> IntList =
+            \x:Int. \xs:IntList. fold @IntList (Just @(Int, IntList) (x, xs));
+        let sum: IntList -> Int = \xs:IntList. match unfold xs {
+            Nothing -> 0,
+            Just p -> match p { (x, rest) -> x + sum rest },
+        };
+        let total: Int = sum (cons 1 (cons 2 (cons 3 nil)));
+    "#;
+    assert_eq!(eval(src, "total"), Ok("6".into()));
+}
+
+#[test]
+fn test_eval_mutually_recursive_data() {
+    let src = r#"
+        data Tree = Node Int Forest;
+        data Forest = Leaf | Branch Tree Forest;
+        let size: Tree -> Int = \t:Tree. match t { Node x f -> x + sizeForest f };
+        let sizeForest: Forest -> Int = \f:Forest. match f {
+            Leaf -> 0,
+            Branch t rest -> size t + sizeForest rest,
+        };
+        let x: Int = size (Node 1 (Branch (Node 2 Leaf) (Branch (Node 3 Leaf) Leaf)));
+    "#;
+    assert_eq!(eval(src, "x"), Ok("6".into()));
+}
+
+#[test]
+fn test_eval_typed_evaluator() {
+    let src = r#"
+        data Expr a where
+            IntE : Int -> Expr Int;
+            BoolE : Bool -> Expr Bool;
+            Add : Expr Int -> Expr Int -> Expr Int;
+            IsZero : Expr Int -> Expr Bool;
+            If : Expr Bool -> Expr a -> Expr a -> Expr a;
+            Pair : forall b. forall c. Expr b -> Expr c -> Expr (b, c);
+        let eval: forall a. Expr a -> a = \@a.\e:Expr a. match e {
+            IntE n -> n,
+            BoolE b -> b,
+            Add x y -> eval @Int x + eval @Int y,
+            IsZero x -> eval @Int x == 0,
+            If c t f -> if eval @Bool c then eval @a t else eval @a f,
+            Pair @b @c x y -> (eval @b x, eval @c y),
+        };
+        let e: Expr (Int, Bool) = Pair @Int @Bool
+            (If @Int (IsZero (Add (IntE 1) (IntE (-1)))) (IntE 10) (IntE 20))
+            (BoolE false);
+        let x: (Int, Bool) = eval @(Int, Bool) e;
+    "#;
+    assert_eq!(eval(src, "x"), Ok("(10, false)".into()));
+}
+
+#[test]
+fn test_eval_prelude() {
+    let src = r#"
+        let xs: List Int = Cons @Int 1 (Cons @Int 2 (Cons @Int 3 (Nil @Int)));
+        let doubled: List Int = map @Int @Int (\x:Int. x * 2) xs;
+        let total: Int = sum doubled;
+        let n: Int = length @Int (reverse @Int xs);
+        let m: Maybe (List Int) = tail @Int xs;
+    "#;
+    assert_eq!(eval(src, "doubled"), Ok("Cons 2 (Cons 4 (Cons 6 Nil))".into()));
+    assert_eq!(eval(src, "total"), Ok("12".into()));
+    assert_eq!(eval(src, "n"), Ok("3".into()));
+    assert_eq!(eval(src, "m"), Ok("Just (Cons 2 (Cons 3 Nil))".into()));
+}
+
+#[test]
+fn test_eval_if() {
+    let src = r#"
+        let abs: Int -> Int = \x:Int. if x < 0 then -x else x;
+        let a: Int = abs (-5) + abs 3;
+        // Only the chosen branch is evaluated
+        let b: Int = if true then 1 else 1 / 0;
+        let evens: List Int = filter @Int (\x:Int. x % 2 == 0) (Cons @Int 1 (Cons @Int 2 (Nil @Int)));
+    "#;
+    assert_eq!(eval(src, "a"), Ok("8".into()));
+    assert_eq!(eval(src, "b"), Ok("1".into()));
+    assert_eq!(eval(src, "evens"), Ok("Cons 2 Nil".into()));
+}
+
+#[test]
+fn test_eval_let() {
+    let src = r#"
+        let a: Int = let x = 2 in let f = \y:Int. x * y in let x = 10 in f x + x;
+        let b: Pair Int Bool = let id = \@a. \x:a. x in Pair @Int @Bool (id @Int 1) (id @Bool true);
+    "#;
+    assert_eq!(eval(src, "a"), Ok("30".into()));
+    assert_eq!(eval(src, "b"), Ok("Pair 1 true".into()));
+}
+
+#[test]
+fn test_eval_recursion() {
+    let src = r#"
+        let factorial: Int -> Int = \n:Int. if n == 0 then 1 else n * factorial (n - 1);
+        let a: Int = factorial 10;
+        let b: Bool =
+            let rec even: Int -> Bool = \n:Int. if n == 0 then true else odd (n - 1)
+            and odd: Int -> Bool = \n:Int. if n == 0 then false else even (n - 1)
+            in even 10 && odd 7;
+        let c: List Int =
+            let rec upto: Int -> List Int =
+                \n:Int. if n == 0 then Nil @Int else Cons @Int n (upto (n - 1))
+            in map @Int @Int (\x:Int. x * x) (upto 3);
+    "#;
+    assert_eq!(eval(src, "a"), Ok("3628800".into()));
+    assert_eq!(eval(src, "b"), Ok("true".into()));
+    assert_eq!(eval(src, "c"), Ok("Cons 9 (Cons 4 (Cons 1 Nil))".into()));
+}
+
+#[test]
+fn test_eval_classes() {
+    let src = r#"
+        class Eq a where eq : a -> a -> Bool;
+        class Show a where show : a -> String;
+        instance Eq Int where eq = \x:Int.\y:Int. x == y;
+        instance Show Int where show = \x:Int. if x == 0 then "zero" else "nonzero";
+        instance forall a. Eq a => Eq (List a) where
+            eq = \xs:List a.\ys:List a. match xs {
+                Nil -> match ys { Nil -> true, Cons y ys2 -> false },
+                Cons x xs2 -> match ys {
+                    Nil -> false,
+                    Cons y ys2 -> eq @a x y && eq @(List a) xs2 ys2,
+                },
+            };
+        let elem: forall a. Eq a => a -> List a -> Bool = \@a.\x:a.\xs:List a.
+            foldr @a @Bool (\y:a.\found:Bool. found || eq @a x y) false xs;
+        let xs: List Int = Cons @Int 1 (Cons @Int 2 (Nil @Int));
+        let a: Bool = elem @Int 2 xs;
+        let b: Bool = elem @(List Int) xs (Cons @(List Int) (Nil @Int) (Nil @(List Int)));
+        let c: String = show @Int 0;
+    "#;
+    assert_eq!(eval(src, "a"), Ok("true".into()));
+    assert_eq!(eval(src, "b"), Ok("false".into()));
+    assert_eq!(eval(src, "c"), Ok("\"zero\"".into()));
+}
+
+#[test]
+fn test_eval_deriving() {
+    let src = r#"
+        data Tree a = Leaf | Node (Tree a) a (Tree a) deriving (Eq, Show);
+        let t: Tree Int = Node @Int (Leaf @Int) 1 (Leaf @Int);
+        let a: Bool = eq @(Tree Int) t t;
+        let b: Bool = eq @(Tree Int) t (Node @Int (Leaf @Int) 2 (Leaf @Int));
+        let c: Bool = eq @(Tree Int) t (Leaf @Int);
+        let d: String = show @(Tree Int) t;
+        let e: String = show @(Maybe (List Int)) (Just @(List Int) (Cons @Int (0 - 1) (Nil @Int)));
+        let f: String = show @(Pair String Char) (Pair @String @Char "a b" 'c');
+        let g: String = show @Float 1.0;
+        data Color = Red | Green | Blue deriving (Eq);
+        let h: Bool = eq @Color Green Green;
+        let i: Bool = eq @Color Green Blue;
+    "#;
+    assert_eq!(eval(src, "a"), Ok("true".into()));
+    assert_eq!(eval(src, "b"), Ok("false".into()));
+    assert_eq!(eval(src, "c"), Ok("false".into()));
+    assert_eq!(eval(src, "d"), Ok(r#""Node Leaf 1 Leaf""#.into()));
+    assert_eq!(eval(src, "e"), Ok(r#""Just (Cons (-1) Nil)""#.into()));
+    assert_eq!(eval(src, "f"), Ok(r#""Pair \"a b\" 'c'""#.into()));
+    assert_eq!(eval(src, "g"), Ok(r#""1.0""#.into()));
+    assert_eq!(eval(src, "h"), Ok("true".into()));
+    assert_eq!(eval(src, "i"), Ok("false".into()));
+}
+
+#[test]
+fn test_eval_subsumption() {
+    let src = r#"
+        let usePoly: ((forall a. a -> a) -> Int) -> Int =
+            \h:((forall a. a -> a) -> Int). h (\@a.\x:a.x);
+        let k: forall a. a -> forall b. b -> a = \@a.\x:a.\@b.\y:b.x;
+        let a: List Int = map @Int @Int id (Cons @Int 1 (Nil @Int));
+        let b: Int = usePoly (\g:Int -> Int. g 41);
+        let c: Int = usePoly (if false then \g:Int -> Int. 0 else \g:Int -> Int. g 42);
+        let d: Int = (\f:Int -> (forall b. b -> Int). f 1 @Bool true) k;
+    "#;
+    assert_eq!(eval(src, "a"), Ok("Cons 1 Nil".into()));
+    assert_eq!(eval(src, "b"), Ok("41".into()));
+    assert_eq!(eval(src, "c"), Ok("42".into()));
+    assert_eq!(eval(src, "d"), Ok("1".into()));
+}
+
+#[test]
+fn test_eval_inferred() {
+    let src = r#"
+        let twice = \f.\x. f (f x);
+        let len = \xs. match xs { Nil -> 0, Cons x rest -> 1 + len rest };
+        let a = twice (\x. x * 2) 3;
+        let b = len (map (\s. stringLength s) (Cons "a" (Cons "bc" Nil)));
+        let c = show (twice (\xs. Cons true xs) Nil);
+        let elem = \x.\xs. match xs { Nil -> false, Cons y rest -> eq x y || elem x rest };
+        let d = elem 'b' (Cons 'a' (Cons 'b' Nil));
+        let even = \n. n == 0 || odd (n - 1);
+        let odd = \n. if n == 0 then false else even (n - 1);
+        let e = even 10;
+        let f = let dup = \x. (x, x) in (dup 1, dup "s");
+    "#;
+    assert_eq!(eval(src, "a"), Ok("12".into()));
+    assert_eq!(eval(src, "b"), Ok("2".into()));
+    assert_eq!(eval(src, "c"), Ok(r#""Cons true (Cons true Nil)""#.into()));
+    assert_eq!(eval(src, "d"), Ok("true".into()));
+    assert_eq!(eval(src, "e"), Ok("true".into()));
+    assert_eq!(eval(src, "f"), Ok(r#"((1, 1), ("s", "s"))"#.into()));
+}
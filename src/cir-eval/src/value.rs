@@ -0,0 +1,181 @@
+use std::fmt;
+use std::sync::{Arc, Weak};
+
+use cir::{Binder, BodyData, Expr, Prim, SmolStr, Symbol, VariantId};
+use cir_parse::LowerDatabase;
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    String(SmolStr),
+    /// A fully applied data constructor
+    Data(VariantId, Arc<[Value]>),
+    /// A data constructor with the given number of fields applied to fewer arguments
+    PartialCtor(VariantId, usize, Vec<Value>),
+    Tuple(Arc<[Value]>),
+    /// The fields of a record, sorted by name
+    Record(Arc<[(Symbol, Value)]>),
+    /// A primitive applied to fewer arguments than its arity
+    PartialPrim(Prim, Vec<Value>),
+    Closure(Arc<Closure>),
+}
+
+impl Value {
+    /// Displays the value using the names of the constructors
+    pub fn display<'a>(&'a self, db: &'a dyn LowerDatabase) -> impl fmt::Display + 'a {
+        DisplayValue { db, value: self, nested: false }
+    }
+}
+
+struct DisplayValue<'a> {
+    db: &'a dyn LowerDatabase,
+    value: &'a Value,
+    /// Whether the value is an argument of a constructor, and so needs parentheses
+    nested: bool,
+}
+
+impl fmt::Display for DisplayValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Value::Int(i) => write!(f, "{}", i),
+            // `Debug` always includes a fractional part or exponent, e.g. `3.0`
+            Value::Float(x) => write!(f, "{:?}", x),
+            // Quoted, with the same escapes as literals
+            Value::Char(c) => write!(f, "{:?}", c),
+            Value::String(s) => write!(f, "{:?}", s.as_str()),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Data(variant, fields) => {
+                let items = self.db.items(variant.data_def.file);
+                let name = &items[variant.data_def.def].variants[variant.variant].name;
+                if fields.is_empty() {
+                    return write!(f, "{}", name.symbol.as_str());
+                }
+                if self.nested {
+                    write!(f, "(")?;
+                }
+                write!(f, "{}", name.symbol.as_str())?;
+                for field in fields.iter() {
+                    write!(f, " {}", DisplayValue { db: self.db, value: field, nested: true })?;
+                }
+                if self.nested {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Value::Tuple(values) => {
+                write!(f, "(")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", DisplayValue { db: self.db, value, nested: false })?;
+                }
+                write!(f, ")")
+            }
+            Value::Record(fields) => {
+                write!(f, "{{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    let value = DisplayValue { db: self.db, value, nested: false };
+                    write!(f, " {} = {}", name.as_str(), value)?;
+                }
+                write!(f, " }}")
+            }
+            Value::PartialCtor(..) | Value::PartialPrim(..) | Value::Closure(_) =>
+                write!(f, "<function>"),
+        }
+    }
+}
+
+pub struct Closure {
+    pub body: Arc<BodyData>,
+    pub env: Env,
+    pub binder: Binder,
+    pub expr: Expr,
+}
+
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<closure>")
+    }
+}
+
+/// The values of the binders in scope, a persistent list so closures can cheaply capture it
+#[derive(Debug, Clone, Default)]
+pub struct Env {
+    head: Option<Arc<EnvNode>>,
+}
+
+#[derive(Debug)]
+struct EnvNode {
+    binder: Binder,
+    value: Bound,
+    next: Option<Arc<EnvNode>>,
+}
+
+#[derive(Debug)]
+enum Bound {
+    Value(Value),
+    /// Bound by a `let rec` group, `head` is the node of the last binder of the group.
+    /// Every `Env` that contains this node passes through `head`, so it is alive during lookup.
+    Rec {
+        expr: Expr,
+        head: Weak<EnvNode>,
+    },
+}
+
+/// The result of looking up a binder
+pub enum Binding {
+    Value(Value),
+    /// A binder of a `let rec` group, its value is `expr` evaluated in `env`
+    Rec(Expr, Env),
+}
+
+impl Env {
+    #[must_use]
+    pub fn bind(&self, binder: Binder, value: Value) -> Self {
+        let node = EnvNode { binder, value: Bound::Value(value), next: self.head.clone() };
+        Self { head: Some(Arc::new(node)) }
+    }
+
+    /// Binds a `let rec` group, each binder's expression is evaluated in the returned `Env` (so
+    /// can refer to the group) whenever the binder is looked up
+    #[must_use]
+    pub fn bind_rec(&self, bindings: &[(Binder, Expr)]) -> Self {
+        let (&(last_binder, last_expr), rest) = match bindings.split_last() {
+            Some(split) => split,
+            None => return self.clone(),
+        };
+        let head = Arc::new_cyclic(|head| {
+            let next = rest.iter().fold(self.head.clone(), |next, &(binder, expr)| {
+                let value = Bound::Rec { expr, head: Weak::clone(head) };
+                Some(Arc::new(EnvNode { binder, value, next }))
+            });
+            let value = Bound::Rec { expr: last_expr, head: Weak::clone(head) };
+            EnvNode { binder: last_binder, value, next }
+        });
+        Self { head: Some(head) }
+    }
+
+    pub fn lookup(&self, binder: Binder) -> Binding {
+        let mut node = self.head.as_deref();
+        while let Some(n) = node {
+            if n.binder == binder {
+                return match &n.value {
+                    Bound::Value(value) => Binding::Value(value.clone()),
+                    Bound::Rec { expr, head } => {
+                        let head = head.upgrade().expect("`let rec` group outlived its env");
+                        Binding::Rec(*expr, Env { head: Some(head) })
+                    }
+                };
+            }
+            node = n.next.as_deref();
+        }
+        panic!("unbound binder {:?}", binder)
+    }
+}
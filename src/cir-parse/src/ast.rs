@@ -1,5 +1,5 @@
-use cir::Name;
-use codespan::Span;
+pub use cir::{BinOp, Path, UnOp, Visibility};
+use cir::{Name, Span};
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct SourceFile {
@@ -19,10 +19,38 @@ pub enum Expr {
     Lambda(Binder, Box<Expr>),
     App(Box<Expr>, Box<Expr>),
     Case(Box<Expr>, Alts),
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// `let x: T = e1 in e2`, the type annotation is optional
+    Let(Name, Option<Type>, Box<Expr>, Box<Expr>),
+    /// `let rec f: T = e1 and g: U = e2 in e`, the type annotations are required
+    LetRec(Vec<RecBinding>, Box<Expr>),
+    BinOp(BinOp, Box<Expr>, Box<Expr>),
+    UnOp(UnOp, Box<Expr>),
+    /// `(e1, e2, ...)` or the unit `()`, a parenthesized expression is not a tuple
+    Tuple(Vec<Expr>),
+    /// `{ x = e1, y = e2 }`
+    Record(Vec<(Name, Expr)>),
+    /// `e.x`
+    Field(Box<Expr>, Name),
+    /// `pack @T e as exists a. U`
+    Pack(Type, Box<Expr>, Type),
+    /// `unpack @a x = e1 in e2`
+    Unpack(TyVar, Name, Box<Expr>, Box<Expr>),
+    /// `fold @(mu a. T) e`
+    Fold(Type, Box<Expr>),
+    /// `unfold e`
+    Unfold(Box<Expr>),
     Path(Path),
     Type(Type),
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct RecBinding {
+    pub name: Name,
+    pub ty: Type,
+    pub expr: Expr,
+}
+
 pub type Alts = Vec<Alt>;
 
 #[derive(Debug, PartialEq, Eq)]
@@ -33,13 +61,19 @@ pub struct Alt {
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Pat {
-    Variant(Path),
+    /// A constructor followed by a binder for each of its own type parameters (if it has a
+    /// signature with a `forall`) and for each of its fields, e.g. `Cons x xs` or `Pair @b @c x y`
+    Variant(Path, Vec<TyVar>, Vec<Name>),
+    /// A binder for each component of a tuple, e.g. `(x, y)`
+    Tuple(Vec<Name>),
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Binder {
-    Val(Name, Type),
-    Ty(TyVar),
+    /// `x: T` or `x`, the type annotation is optional
+    Val(Name, Option<Type>),
+    /// `@a` or `@(f : * -> *)`, the kind is `*` unless annotated
+    Ty(TyVar, cir::Kind),
 }
 
 #[derive(Debug, Hash, Clone, PartialEq, Eq)]
@@ -55,8 +89,13 @@ pub struct Literal {
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum LiteralKind {
-    Int(i64),
+    /// Checked to be in the range of `Int` during lowering, after negative literals in
+    /// expressions are folded
+    Int(i128),
+    Float(cir::Float),
     Bool(bool),
+    Char(char),
+    String(cir::SmolStr),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -67,13 +106,25 @@ pub enum Var {
     Ty(TyVar),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Type {
     Var(TyVar),
     Path(Path),
     Scalar(cir::Scalar),
     Fn(Box<Type>, Box<Type>),
-    ForAll(TyVar, Box<Type>),
+    ForAll(TyVar, cir::Kind, Box<Type>),
+    Exists(TyVar, Box<Type>),
+    /// `mu a. T`
+    Mu(TyVar, Box<Type>),
+    /// A type operator `\a. T` or `\(f : * -> *). T`
+    Lambda(TyVar, cir::Kind, Box<Type>),
+    App(Box<Type>, Box<Type>),
+    /// `(T1, T2, ...)` or the unit type `()`
+    Tuple(Vec<Type>),
+    /// `{ x: T1, y: T2 }`
+    Record(Vec<(Name, Type)>),
+    /// `Eq a => T`
+    Constrained(Path, Box<Type>, Box<Type>),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -86,30 +137,95 @@ pub struct Item {
 pub enum ItemKind {
     ValueDef(ValueDef),
     DataDef(DataDef),
+    TypeSynonym(TypeSynonym),
+    Class(Class),
+    Instance(Instance),
+    Module(Module),
+    Import(Import),
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct ValueDef {
+    pub vis: Visibility,
     pub name: Name,
-    pub ty: Type,
+    /// The signature, the type of a definition without one is inferred
+    pub ty: Option<Type>,
     pub expr: Expr,
 }
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct Path {
-    // TODO paths are just a name for now
+pub struct DataDef {
+    pub vis: Visibility,
     pub name: Name,
+    pub binders: Vec<TyVar>,
+    pub variants: Vec<Variant>,
+    /// The classes in `deriving (Eq, Show)`, an instance of each is generated
+    pub deriving: Vec<Path>,
 }
 
+/// `type Pair a b = forall r. (a -> b -> r) -> r`
 #[derive(Debug, PartialEq, Eq)]
-pub struct DataDef {
+pub struct TypeSynonym {
+    pub vis: Visibility,
     pub name: Name,
-    pub binders: Vec<TyVar>,
-    pub variants: Vec<Variant>,
+    pub params: Vec<TyVar>,
+    pub ty: Type,
+}
+
+/// `class Eq a where eq : a -> a -> Bool; ne : a -> a -> Bool`, the methods have the visibility
+/// of the class
+#[derive(Debug, PartialEq, Eq)]
+pub struct Class {
+    pub vis: Visibility,
+    pub name: Name,
+    pub param: (TyVar, cir::Kind),
+    pub methods: Vec<(Name, Type)>,
+}
+
+/// `instance forall a. Eq a => Eq (List a) where eq = e1; ne = e2`, the parameters and the
+/// context are written as in a type
+#[derive(Debug, PartialEq, Eq)]
+pub struct Instance {
+    pub ty: Type,
+    pub methods: Vec<(Name, Expr)>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Variant {
+    /// Constructors are private unless marked `pub`, even if the data definition is public
+    pub vis: Visibility,
+    pub name: Name,
+    pub params: Vec<Spanned<Type>>,
+    /// The signature of a constructor of a `data Expr a where ...` definition
+    pub sig: Option<VariantSig>,
+}
+
+/// The parts of `Pair : forall b c. Expr b -> Expr c -> Expr (b, c)` other than the fields, the
+/// parameters of the data definition are in scope
+#[derive(Debug, PartialEq, Eq)]
+pub struct VariantSig {
+    /// The constructor's own type parameters
+    pub binders: Vec<(TyVar, cir::Kind)>,
+    /// The constructed type
+    pub result: Type,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Module {
     pub name: Name,
-    pub params: Vec<Type>,
+    pub kind: ModuleKind,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ModuleKind {
+    /// `module M { ... }`
+    Inline(Vec<Item>),
+    /// `module M;`, the items are in the file `M.cir` next to the declaring file
+    File,
+}
+
+/// `import M;` imports every item of module `M`, `import M.x;` imports only `x`
+#[derive(Debug, PartialEq, Eq)]
+pub struct Import {
+    pub path: Path,
 }
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use cir::db::SourceDatabase;
+use cir::{FileId, InstanceId, ValueDefId};
+use peg::error::ParseError;
+use peg::str::LineCol;
+
+use crate::ast;
+use crate::cirparser;
+use crate::lower::LowerCtxt;
+use crate::resolve::{self, DefMap, ResolveError, Resolver};
+
+pub type ParseResult<T> = Result<T, ParseError<LineCol>>;
+
+#[salsa::query_group(LowerDatabaseStorage)]
+pub trait LowerDatabase: SourceDatabase {
+    fn parse(&self, file: FileId) -> ParseResult<Arc<ast::SourceFile>>;
+
+    #[salsa::invoke(resolve::def_map)]
+    fn def_map(&self) -> Arc<DefMap>;
+
+    fn lower_file_with_errors(
+        &self,
+        file: FileId,
+    ) -> ParseResult<(Arc<cir::Items>, Arc<[ResolveError]>)>;
+
+    fn lower_file(&self, file: FileId) -> ParseResult<Arc<cir::Items>>;
+
+    /// The lowered items of a file without their bodies, which are given by `value_def_body` and
+    /// `instance_body`. Unlike `lower_file` this is unaffected by edits to the bodies.
+    fn file_signatures(&self, file: FileId) -> ParseResult<Arc<cir::Items>>;
+
+    /// The signatures of the items of a file that is known to parse, e.g. because an ID refers to
+    /// one of its items, see `file_signatures`
+    #[salsa::transparent]
+    fn items(&self, file: FileId) -> Arc<cir::Items>;
+
+    /// The declared type of a `ValueDef`, `None` if it has no signature
+    fn value_def_sig(&self, def: ValueDefId) -> Option<cir::Ty>;
+
+    /// The lowered body of a `ValueDef`.
+    /// Bodies contain no spans so this is unaffected by edits elsewhere in the file.
+    fn value_def_body(&self, def: ValueDefId) -> Arc<cir::BodyData>;
+
+    /// The lowered body of an `Instance`, see `value_def_body`
+    fn instance_body(&self, instance: InstanceId) -> Arc<cir::BodyData>;
+}
+
+fn parse(db: &dyn LowerDatabase, file: FileId) -> ParseResult<Arc<ast::SourceFile>> {
+    let text = db.file_text(file);
+    cirparser::source_file(&text, file).map(Arc::new)
+}
+
+fn lower_file_with_errors(
+    db: &dyn LowerDatabase,
+    file: FileId,
+) -> ParseResult<(Arc<cir::Items>, Arc<[ResolveError]>)> {
+    let source_file = db.parse(file)?;
+    let def_map = db.def_map();
+    let module = def_map.file_module(file);
+    let mut lcx = LowerCtxt::new(Resolver::new(Arc::clone(&def_map), module));
+    let items = lcx.lower_source_file(&source_file);
+    let mut errors =
+        def_map.errors().iter().filter(|err| err.span.file == file).cloned().collect::<Vec<_>>();
+    errors.extend(lcx.errors);
+    Ok((Arc::new(items), Arc::from(errors)))
+}
+
+fn lower_file(db: &dyn LowerDatabase, file: FileId) -> ParseResult<Arc<cir::Items>> {
+    db.lower_file_with_errors(file).map(|(items, _)| items)
+}
+
+fn file_signatures(db: &dyn LowerDatabase, file: FileId) -> ParseResult<Arc<cir::Items>> {
+    let items = db.lower_file(file)?;
+    Ok(Arc::new(cir::Items {
+        items: items.items.clone(),
+        value_defs: items.value_defs.clone(),
+        data_defs: items.data_defs.clone(),
+        type_synonyms: items.type_synonyms.clone(),
+        classes: items.classes.clone(),
+        instances: items.instances.clone(),
+        bodies: Default::default(),
+    }))
+}
+
+fn items(db: &dyn LowerDatabase, file: FileId) -> Arc<cir::Items> {
+    db.file_signatures(file).expect("an ID refers to a file that failed to parse")
+}
+
+/// The lowered items of the file of a body, including the bodies
+fn file_bodies(db: &dyn LowerDatabase, file: FileId) -> Arc<cir::Items> {
+    db.lower_file(file).expect("an ID refers to a file that failed to parse")
+}
+
+fn value_def_sig(db: &dyn LowerDatabase, def: ValueDefId) -> Option<cir::Ty> {
+    let items = db.items(def.file);
+    items[def.def].ty.clone()
+}
+
+fn value_def_body(db: &dyn LowerDatabase, def: ValueDefId) -> Arc<cir::BodyData> {
+    let items = file_bodies(db, def.file);
+    Arc::new(items[items[def.def].body].clone())
+}
+
+fn instance_body(db: &dyn LowerDatabase, instance: InstanceId) -> Arc<cir::BodyData> {
+    let items = file_bodies(db, instance.file);
+    Arc::new(items[items[instance.def].body].clone())
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+
+use cir::{Kind, Ty, TyKind};
+
+use crate::LowerDatabase;
+
+/// Displays a type in the surface syntax, using the names of data types and classes. Bound type
+/// variables are named `a`, `b`, ... by the depth of their binders.
+pub fn display_ty<'a>(db: &'a dyn LowerDatabase, ty: &'a Ty) -> impl fmt::Display + 'a {
+    DisplayTy { db, ty, depth: 0, prec: Prec::Top }
+}
+
+/// The context a type is displayed in, types that bind less tightly need parentheses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    /// The body of a binder or the result of a function type
+    Top,
+    /// The parameter of a function type
+    Fn,
+    /// The argument of a type application
+    Arg,
+}
+
+struct DisplayTy<'a> {
+    db: &'a dyn LowerDatabase,
+    ty: &'a Ty,
+    /// The number of type binders enclosing `ty`
+    depth: u32,
+    prec: Prec,
+}
+
+impl<'a> DisplayTy<'a> {
+    fn nested(&self, ty: &'a Ty, depth: u32, prec: Prec) -> Self {
+        Self { db: self.db, ty, depth, prec }
+    }
+
+    /// Writes `forall (f : * -> *). T` and the like, naming the variable after `self.depth`
+    fn binder(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        keyword: &str,
+        kind: &Kind,
+        ty: &Ty,
+    ) -> fmt::Result {
+        let var = var_name(self.depth);
+        match kind {
+            Kind::Type => write!(f, "{} {}. ", keyword, var)?,
+            kind => write!(f, "{} ({} : {:?}). ", keyword, var, kind)?,
+        }
+        write!(f, "{}", self.nested(ty, self.depth + 1, Prec::Top))
+    }
+}
+
+impl fmt::Display for DisplayTy<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parens = match self.ty.kind() {
+            TyKind::Fn(..)
+            | TyKind::ForAll(..)
+            | TyKind::Exists(_)
+            | TyKind::Mu(_)
+            | TyKind::Lambda(..)
+            | TyKind::Constrained(..) => self.prec > Prec::Top,
+            TyKind::App(..) => self.prec == Prec::Arg,
+            TyKind::Adt(_, args) => !args.is_empty() && self.prec == Prec::Arg,
+            _ => false,
+        };
+        if parens {
+            write!(f, "(")?;
+        }
+        match self.ty.kind() {
+            TyKind::Scalar(scalar) => write!(f, "{:?}", scalar)?,
+            TyKind::Fn(l, r) => write!(
+                f,
+                "{} -> {}",
+                self.nested(l, self.depth, Prec::Fn),
+                self.nested(r, self.depth, Prec::Top)
+            )?,
+            TyKind::Var(var) if var.index() < self.depth =>
+                write!(f, "{}", var_name(self.depth - 1 - var.index()))?,
+            // A variable bound outside the displayed type
+            TyKind::Var(var) => write!(f, "{:?}", var)?,
+            TyKind::ForAll(kind, ty) => self.binder(f, "forall", kind, ty)?,
+            TyKind::Exists(ty) => self.binder(f, "exists", &Kind::Type, ty)?,
+            TyKind::Mu(ty) => self.binder(f, "mu", &Kind::Type, ty)?,
+            TyKind::Lambda(kind, ty) => self.binder(f, "\\", kind, ty)?,
+            TyKind::App(l, r) => write!(
+                f,
+                "{} {}",
+                self.nested(l, self.depth, Prec::Fn),
+                self.nested(r, self.depth, Prec::Arg)
+            )?,
+            TyKind::Adt(adt, args) => {
+                let items = self.db.items(adt.file);
+                write!(f, "{}", items[adt.def].name.symbol.as_str())?;
+                for arg in args {
+                    write!(f, " {}", self.nested(arg, self.depth, Prec::Arg))?;
+                }
+            }
+            TyKind::Tuple(tys) => {
+                write!(f, "(")?;
+                for (i, ty) in tys.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", self.nested(ty, self.depth, Prec::Top))?;
+                }
+                write!(f, ")")?;
+            }
+            TyKind::Record(fields) => {
+                write!(f, "{{")?;
+                for (i, (name, ty)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, " {}: {}", name.as_str(), self.nested(ty, self.depth, Prec::Top))?;
+                }
+                write!(f, " }}")?;
+            }
+            TyKind::Constrained(class, arg, ty) => {
+                let items = self.db.items(class.file);
+                write!(
+                    f,
+                    "{} {} => {}",
+                    items[class.def].name.symbol.as_str(),
+                    self.nested(arg, self.depth, Prec::Arg),
+                    self.nested(ty, self.depth, Prec::Top)
+                )?;
+            }
+            TyKind::Err => write!(f, "{{error}}")?,
+        }
+        if parens {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// `a`, ..., `z`, `a1`, ..., `z1`, `a2`, ...
+fn var_name(level: u32) -> String {
+    let letter = (b'a' + (level % 26) as u8) as char;
+    match level / 26 {
+        0 => letter.to_string(),
+        n => format!("{}{}", letter, n),
+    }
+}
@@ -1,39 +1,91 @@
-mod ast;
+pub mod ast;
+mod db;
+mod display;
 mod lower;
+mod prelude;
+mod resolve;
 
 use ast::*;
-use cir::Name;
-
-use codespan::Span;
+use cir::{FileId, Name, Span};
 
+pub use self::db::{LowerDatabase, LowerDatabaseStorage, ParseResult};
+pub use self::display::display_ty;
 use self::lower::{BodyLowerCtxt, LowerCtxt};
+pub use self::prelude::{add_prelude, PRELUDE_PATH};
+pub use self::resolve::{
+    DefMap, ModuleData, ModuleId, ResolveError, ResolveErrorKind, Resolver, Scope, TypeNs,
+    TypeSynonymSource, ValueNs,
+};
+
+/// The reserved words, which are not names. `true` and `false` are literals.
+const KEYWORDS: &[&str] = &[
+    "and", "as", "class", "data", "deriving", "else", "exists", "false", "fold", "forall", "if",
+    "import", "in", "instance", "let", "match", "module", "mu", "pack", "pub", "rec", "then",
+    "true", "type", "unfold", "unpack", "where",
+];
 
 // FIXME minor hack for testing purposes for now
 pub fn parse_body(s: &str) -> cir::BodyData {
-    let expr: ast::Expr = cirparser::expr(s).unwrap();
+    let expr: ast::Expr = cirparser::expr(s, FileId(0)).unwrap();
     let mut lcx = LowerCtxt::default();
     let body_id = BodyLowerCtxt::new(&mut lcx).lower(&expr);
     lcx.bodies[body_id].clone()
 }
 
 pub fn parse_ty(s: &str) -> cir::Ty {
-    let ty: ast::Type = cirparser::ty(s).unwrap();
+    let ty: ast::Type = cirparser::ty(s, FileId(0)).unwrap();
     let mut lcx = LowerCtxt::default();
     lcx.lower_ty(&ty)
 }
 
+/// A single parenthesized element is just that element, otherwise the elements form a tuple
+fn tuple<T>(mut elems: Vec<T>, mk_tuple: impl FnOnce(Vec<T>) -> T) -> T {
+    match elems.len() {
+        1 => elems.pop().unwrap(),
+        _ => mk_tuple(elems),
+    }
+}
+
 peg::parser! {
-    pub grammar cirparser() for str {
+    pub grammar cirparser(file: FileId) for str {
         rule lower() -> &'input str = s:$(['_' | 'a'..='z'] alphanumeric()?) { s }
         rule upper() -> &'input str = s:$(['A'..='Z'] alphanumeric()?) { s }
         rule alphanumeric() -> &'input str = s:$(['_' | 'a'..='z' | 'A'..='Z' | '0'..='9']+) { s }
-        rule integer() -> i64 = n:$("-"?['0'..='9']+) { n.parse().unwrap() }
+        rule integer() -> i128 = n:$("-"?['0'..='9']+) {? n.parse().or(Err("integer literal")) }
+        rule natural() -> i128 = n:$(['0'..='9']+) {? n.parse().or(Err("integer literal")) }
+        // A float requires a fraction or an exponent (or both), e.g. `3.14`, `1e-3`, `2.5E10`
+        rule float_digits() = ['0'..='9']+ (("." ['0'..='9']+ exponent()?) / exponent())
+        rule exponent() = ['e' | 'E'] ['+' | '-']? ['0'..='9']+
+        rule float() -> cir::Float = f:$("-"? float_digits()) { cir::Float::new(f.parse().unwrap()) }
+        rule unsigned_float() -> cir::Float = f:$(float_digits()) { cir::Float::new(f.parse().unwrap()) }
+        // The end of a scalar type name, which must not be a prefix of a longer name
+        rule word_end() = !['_' | 'a'..='z' | 'A'..='Z' | '0'..='9']
         rule bool() -> bool = b:$("false" / "true") { b.parse().unwrap() }
-        rule _ = [' ' | '\t' | '\n' | '\r']*
+        rule escape() -> char = "\\" c:(
+            "n" { '\n' } / "t" { '\t' } / "r" { '\r' } / "0" { '\0' }
+            / "\\" { '\\' } / "\"" { '"' } / "'" { '\'' }
+            / "u{" h:$(['0'..='9' | 'a'..='f' | 'A'..='F']*<1,6>) "}" {?
+                u32::from_str_radix(h, 16).ok().and_then(char::from_u32).ok_or("unicode scalar value")
+            }
+        ) { c }
+        rule string_char() -> char = escape() / !['"' | '\\' | '\n'] c:$([_]) { c.chars().next().unwrap() }
+        rule string() -> cir::SmolStr = "\"" cs:string_char()* "\"" {
+            cs.into_iter().collect::<String>().into()
+        }
+        rule char_char() -> char = escape() / !['\'' | '\\' | '\n'] c:$([_]) { c.chars().next().unwrap() }
+        rule character() -> char = "'" c:char_char() "'" { c }
+        rule ws() = [' ' | '\t' | '\n' | '\r']
+        rule comment() = "//" (!"\n" [_])*
+        rule _ = (ws() / comment())*
+
+        // The whole word is compared, so that e.g. `instance` is not taken to be `in`
+        rule keyword() = k:$(['a'..='z']+) word_end() {?
+            if KEYWORDS.contains(&k) { Ok(()) } else { Err("keyword") }
+        }
 
         rule spanned<T>(t: rule<T>) -> Spanned<T> = start:position!() node:t() end:position!() {
             Spanned {
-                span: Span::new(start as u32, end as u32),
+                span: Span::new(file, start as u32, end as u32),
                 node,
             }
         }
@@ -45,6 +97,13 @@ peg::parser! {
             }
         }
 
+        pub rule float_literal() -> Literal = f:spanned(<float()>) {
+            Literal {
+                span: f.span,
+                kind: LiteralKind::Float(f.node),
+            }
+        }
+
         pub rule boolean_literal() -> Literal = b:spanned(<bool()>) {
             Literal {
                 span: b.span,
@@ -52,11 +111,41 @@ peg::parser! {
             }
         }
 
-        pub rule literal() -> Literal = _ lit:(integer_literal() / boolean_literal()) _ {
+        pub rule char_literal() -> Literal = c:spanned(<character()>) {
+            Literal {
+                span: c.span,
+                kind: LiteralKind::Char(c.node),
+            }
+        }
+
+        pub rule string_literal() -> Literal = s:spanned(<string()>) {
+            Literal {
+                span: s.span,
+                kind: LiteralKind::String(s.node),
+            }
+        }
+
+        pub rule literal() -> Literal = _ lit:(
+            float_literal() / integer_literal() / boolean_literal() / char_literal() / string_literal()
+        ) _ {
             lit
         }
 
-        pub rule lname() -> Name = s:spanned(<lower()>) {
+        // Integer literals in expressions are unsigned so that `x -1` is a subtraction,
+        // negative literals are negations that are folded during lowering
+        rule expr_literal() -> Literal = f:spanned(<unsigned_float()>) {
+            Literal {
+                span: f.span,
+                kind: LiteralKind::Float(f.node),
+            }
+        } / i:spanned(<natural()>) {
+            Literal {
+                span: i.span,
+                kind: LiteralKind::Int(i.node),
+            }
+        } / boolean_literal() / char_literal() / string_literal()
+
+        pub rule lname() -> Name = !keyword() s:spanned(<lower()>) {
             Name::new(s.span, s.node)
         }
 
@@ -68,80 +157,246 @@ peg::parser! {
             TyVar { name }
         }
 
-        rule lpath() -> Path = name:lname() {
-            Path { name }
+        // A module qualifier, the `.` must be immediately followed by the next segment
+        rule qualifier() -> Name = name:uname() "." &(['_' | 'a'..='z' | 'A'..='Z']) { name }
+
+        // A qualified lowercase path such as `List.map`
+        rule lpath() -> Path = qualifier:qualifier()+ name:lname() {
+            qualifier.into_iter().chain(Some(name)).collect()
         }
 
-        rule upath() -> Path = name:uname() {
-            Path { name }
+        // An (optionally qualified) uppercase path such as `Prelude.Maybe`
+        pub rule upath() -> Path = qualifier:qualifier()* name:uname() {
+            qualifier.into_iter().chain(Some(name)).collect()
         }
 
+        // A path to either an item or a module
+        rule path() -> Path = lpath() / upath()
+
         pub rule var() -> Var = precedence! {
             tyvar:tyvar() { Var::Ty(tyvar) }
             name:lname() { Var::Val { name } }
         }
 
         pub rule binder() -> Binder = precedence! {
-            name:lname() _ ":" _ ty:ty() { Binder::Val(name, ty) }
-            "@" tyvar:tyvar() { Binder::Ty(tyvar) }
+            // Qualified types must be parenthesized in binders to avoid ambiguity
+            // with the `.` that separates the binder from the body (e.g. `\\t:T.Y`)
+            name:lname() _ ":" _ ty:ty_in(false) { Binder::Val(name, Some(ty)) }
+            name:lname() { Binder::Val(name, None) }
+            "@" binder:ty_binder() { Binder::Ty(binder.0, binder.1) }
         }
 
         rule expr_atom() -> Expr = precedence! {
-            "(" expr:expr() ")" { expr }
+            "(" _ ")" { Expr::Tuple(vec![]) }
+            "(" exprs:(expr() ++ (_ "," _)) _ ")" { tuple(exprs, Expr::Tuple) }
+            "{" _ fields:(field_expr() ** (_ "," _)) _ "}" { Expr::Record(fields) }
             "\\" _ binder:binder() _ "." _ expr:expr() { Expr::Lambda(binder, Box::new(expr)) }
-            "@" ty:ty() { Expr::Type(ty) }
+            // Type arguments must be atoms (e.g. `f @(List a)`) so they don't swallow the next argument
+            "@" ty:ty_atom(true) { Expr::Type(ty) }
             "match" _ scrutinee:expr() _ "{" _ alts:alts() _ "}" { Expr::Case(Box::new(scrutinee), alts) }
-            lit:literal() { Expr::Lit(lit) }
+            "if" _ c:expr() _ "then" _ t:expr() _ "else" _ e:expr() {
+                Expr::If(Box::new(c), Box::new(t), Box::new(e))
+            }
+            "let" _ "rec" _ bindings:(rec_binding() ++ (_ "and" _)) _ "in" _ e:expr() {
+                Expr::LetRec(bindings, Box::new(e))
+            }
+            "let" _ name:lname() _ ty:(":" _ ty:ty() _ { ty })? "=" _ e1:expr() _ "in" _ e2:expr() {
+                Expr::Let(name, ty, Box::new(e1), Box::new(e2))
+            }
+            "pack" _ "@" hidden:ty_atom(true) e:expr_arg() _ "as" _ ty:ty() {
+                Expr::Pack(hidden, Box::new(e), ty)
+            }
+            "unpack" _ "@" tyvar:tyvar() _ name:lname() _ "=" _ e1:expr() _ "in" _ e2:expr() {
+                Expr::Unpack(tyvar, name, Box::new(e1), Box::new(e2))
+            }
+            "fold" word_end() _ "@" ty:ty_atom(true) e:expr_arg() { Expr::Fold(ty, Box::new(e)) }
+            "unfold" word_end() e:expr_arg() { Expr::Unfold(Box::new(e)) }
+            lit:expr_literal() { Expr::Lit(lit) }
+            path:lpath() { Expr::Path(path) }
             path:upath() { Expr::Path(path) }
             name:lname() { Expr::Var(Var::Val { name }) }
         }
 
-        rule alts() -> Alts = alts:(alt() ++ ",") { alts }
+        rule field_expr() -> (Name, Expr) = name:lname() _ "=" _ expr:expr() { (name, expr) }
+
+        rule rec_binding() -> RecBinding = name:lname() _ ":" _ ty:ty() _ "=" _ expr:expr() {
+            RecBinding { name, ty, expr }
+        }
+
+        rule alts() -> Alts = alts:(alt() ++ (_ "," _)) (_ ",")? { alts }
 
         rule alt() -> Alt = pat:pat() _ "->" _ body:expr() { Alt { pat, body } }
 
-        pub rule pat() -> Pat = variant_pat()
+        pub rule pat() -> Pat = variant_pat() / tuple_pat()
+
+        rule variant_pat() -> Pat = path:upath() ty_binders:(_ "@" tyvar:tyvar() { tyvar })* binders:(_ name:lname() { name })* {
+            Pat::Variant(path, ty_binders, binders)
+        }
 
-        rule variant_pat() -> Pat = path:upath() {
-            Pat::Variant(path)
-         }
+        rule tuple_pat() -> Pat = "(" _ binders:(lname() ** (_ "," _)) _ ")" {?
+            if binders.len() == 1 { Err("tuple pattern with more than one component") } else { Ok(Pat::Tuple(binders)) }
+        }
 
         pub rule expr() -> Expr = precedence! {
-            f:(@) " " x:@ { Expr::App(Box::new(f), Box::new(x)) }
-            _ atom:expr_atom() { atom }
+            l:(@) _ "||" _ r:@ { Expr::BinOp(BinOp::Or, Box::new(l), Box::new(r)) }
+            --
+            l:(@) _ "&&" _ r:@ { Expr::BinOp(BinOp::And, Box::new(l), Box::new(r)) }
+            --
+            l:(@) _ "==" _ r:@ { Expr::BinOp(BinOp::Eq, Box::new(l), Box::new(r)) }
+            l:(@) _ "!=" _ r:@ { Expr::BinOp(BinOp::Ne, Box::new(l), Box::new(r)) }
+            l:(@) _ "<=" _ r:@ { Expr::BinOp(BinOp::Le, Box::new(l), Box::new(r)) }
+            l:(@) _ "<" _ r:@ { Expr::BinOp(BinOp::Lt, Box::new(l), Box::new(r)) }
+            l:(@) _ ">=" _ r:@ { Expr::BinOp(BinOp::Ge, Box::new(l), Box::new(r)) }
+            l:(@) _ ">" _ r:@ { Expr::BinOp(BinOp::Gt, Box::new(l), Box::new(r)) }
+            --
+            l:(@) _ "+" _ r:@ { Expr::BinOp(BinOp::Add, Box::new(l), Box::new(r)) }
+            l:(@) _ "-" _ r:@ { Expr::BinOp(BinOp::Sub, Box::new(l), Box::new(r)) }
+            --
+            l:(@) _ "*" _ r:@ { Expr::BinOp(BinOp::Mul, Box::new(l), Box::new(r)) }
+            l:(@) _ "/" _ r:@ { Expr::BinOp(BinOp::Div, Box::new(l), Box::new(r)) }
+            l:(@) _ "%" _ r:@ { Expr::BinOp(BinOp::Rem, Box::new(l), Box::new(r)) }
+            --
+            e:expr_unary() { e }
         }
 
+        // Prefix operators are outside the precedence climbing, which would otherwise allow them
+        // in any operand (e.g. parsing `x -1` as an application)
+        rule expr_unary() -> Expr
+            = _ "!" e:expr_unary() { Expr::UnOp(UnOp::Not, Box::new(e)) }
+            / _ "-" e:expr_unary() { Expr::UnOp(UnOp::Neg, Box::new(e)) }
+            / expr_app()
 
-        rule ty_atom() -> Type = precedence! {
-            "Bool"  { Type::Scalar(cir::Scalar::Bool) }
-            "Int" { Type::Scalar(cir::Scalar::Int) }
-            "forall" _ tyvar:tyvar() _ "." _ ty:ty() { Type::ForAll(tyvar, Box::new(ty)) }
-            "(" ty:ty() ")" { ty }
-            path:upath() { Type::Path(path) }
-            name:lname() { Type::Var(TyVar { name }) }
+        rule expr_app() -> Expr = f:expr_arg() args:(ws() x:expr_arg() { x })* {
+            args.into_iter().fold(f, |f, x| Expr::App(Box::new(f), Box::new(x)))
         }
 
-        pub rule ty() -> Type = precedence! {
+        // Field accesses bind tighter than application, e.g. `f r.x` is `f (r.x)`
+        rule expr_arg() -> Expr = _ atom:expr_atom() fields:("." name:lname() { name })* {
+            fields.into_iter().fold(atom, |expr, name| Expr::Field(Box::new(expr), name))
+        }
+
+
+        rule ty_atom(qualified: bool) -> Type
+            = "Bool" word_end() { Type::Scalar(cir::Scalar::Bool) }
+            / "Int" word_end() { Type::Scalar(cir::Scalar::Int) }
+            / "Float" word_end() { Type::Scalar(cir::Scalar::Float) }
+            / "Char" word_end() { Type::Scalar(cir::Scalar::Char) }
+            / "String" word_end() { Type::Scalar(cir::Scalar::String) }
+            / "forall" _ binder:ty_binder() _ "." _ ty:ty() {
+                Type::ForAll(binder.0, binder.1, Box::new(ty))
+            }
+            / "exists" _ tyvar:tyvar() _ "." _ ty:ty() { Type::Exists(tyvar, Box::new(ty)) }
+            / "mu" word_end() _ tyvar:tyvar() _ "." _ ty:ty() { Type::Mu(tyvar, Box::new(ty)) }
+            / "\\" _ binder:ty_binder() _ "." _ ty:ty() {
+                Type::Lambda(binder.0, binder.1, Box::new(ty))
+            }
+            / "(" _ ")" { Type::Tuple(vec![]) }
+            / "(" _ tys:(ty() ++ (_ "," _)) _ ")" { tuple(tys, Type::Tuple) }
+            / "{" _ fields:(field_ty() ** (_ "," _)) _ "}" { Type::Record(fields) }
+            / path:upath() {? if qualified || path.as_single().is_some() {
+                Ok(Type::Path(path))
+            } else {
+                Err("unqualified type")
+            } }
+            / name:uname() { Type::Path(Path::single(name)) }
+            / name:lname() { Type::Var(TyVar { name }) }
+
+        // A type variable binder with an optional kind annotation, e.g. `a` or `(f : * -> *)`
+        rule ty_binder() -> (TyVar, cir::Kind)
+            = "(" _ tyvar:tyvar() _ ":" _ kind:kind() _ ")" { (tyvar, kind) }
+            / tyvar:tyvar() { (tyvar, cir::Kind::Type) }
+
+        pub rule kind() -> cir::Kind = precedence! {
+            l:@ _ "->" _ r:(@) { cir::Kind::Fn(Box::new(l), Box::new(r)) }
+            --
+            "*" { cir::Kind::Type }
+            "(" _ kind:kind() _ ")" { kind }
+        }
+
+        rule field_ty() -> (Name, Type) = name:lname() _ ":" _ ty:ty() { (name, ty) }
+
+        // A type, `qualified` determines whether qualified paths are allowed without parentheses
+        rule ty_in(qualified: bool) -> Type = precedence! {
              l:@ _ "->" _ r:(@) { Type::Fn(Box::new(l), Box::new(r)) }
              --
-            atom:ty_atom() { atom }
+             f:(@) ws() _ x:@ { Type::App(Box::new(f), Box::new(x)) }
+             --
+            atom:ty_atom(qualified) { atom }
         }
 
-        pub rule value_def() -> ValueDef = _ "let" _ name:lname() _ ":"  _ ty:ty() _ "=" _ expr:expr() _ {
-            ValueDef { name, expr, ty }
+        // A constraint is a class applied to a type atom, e.g. `Eq a => a -> a -> Bool`
+        pub rule ty() -> Type
+            = class:upath() _ arg:ty_atom(true) _ "=>" _ ty:ty() {
+                Type::Constrained(class, Box::new(arg), Box::new(ty))
+            }
+            / ty_in(true)
+
+        // A type that is not a function type (unless parenthesized)
+        rule ty_app() -> Type = precedence! {
+             f:(@) ws() _ x:@ { Type::App(Box::new(f), Box::new(x)) }
+             --
+            atom:ty_atom(true) { atom }
+        }
+
+        rule visibility() -> Visibility
+            = "pub" ws()+ { Visibility::Public }
+            / "" { Visibility::Private }
+
+        pub rule value_def() -> ValueDef = _ vis:visibility() "let" _ name:lname() _ ty:(":" _ ty:ty() _ { ty })? "=" _ expr:expr() _ {
+            ValueDef { vis, name, expr, ty }
         }
 
         // data Foo a b = Foo a | Bar b
-        pub rule data_def() -> DataDef = _ "data" _ name:uname() _ binders:(tyvar() ** _) _ "=" _ variants:(variant() ++ (_ "|" _)) {
+        // pub data Foo = pub Foo
+        // data Expr a where IntE : Int -> Expr Int; If : Expr Bool -> Expr a -> Expr a -> Expr a
+        // data List a = Nil | Cons a (List a) deriving (Eq, Show)
+        pub rule data_def() -> DataDef = _ vis:visibility() "data" _ name:uname() _ binders:(tyvar() ** _) _ variants:data_variants()
+            deriving:(_ "deriving" word_end() _ "(" _ classes:(upath() ++ (_ "," _)) _ ")" { classes })? {
             DataDef {
+                vis,
                 name,
                 binders,
                 variants,
+                deriving: deriving.unwrap_or_default(),
             }
         }
 
-        pub rule variant() -> Variant = name:uname() _ params:(ty() ** _) {
-            Variant { name, params }
+        // The last signature is not followed by a `;` as that ends the item
+        rule data_variants() -> Vec<Variant>
+            = "=" _ variants:(variant() ++ (_ "|" _)) { variants }
+            / "where" word_end() _ variants:(variant_sig() ++ (_ ";" _)) { variants }
+
+        // type Pair a b = forall r. (a -> b -> r) -> r
+        pub rule type_synonym() -> TypeSynonym = _ vis:visibility() "type" _ name:uname() _ params:(tyvar() ** _) _ "=" _ ty:ty() {
+            TypeSynonym { vis, name, params, ty }
+        }
+
+        // The fields are type atoms, so `Cons a (List a)` has two fields
+        pub rule variant() -> Variant = vis:visibility() name:uname() _ params:(spanned(<ty_atom(true)>) ** _) {
+            Variant { vis, name, params, sig: None }
+        }
+
+        // The fields are the types to the left of the arrows, so they must be parenthesized if
+        // they are function types
+        pub rule variant_sig() -> Variant = vis:visibility() name:uname() _ ":" _
+            binders:("forall" _ binder:ty_binder() _ "." _ { binder })*
+            params:(param:spanned(<ty_app()>) _ "->" _ { param })* result:ty_app() {
+            Variant { vis, name, params, sig: Some(VariantSig { binders, result }) }
+        }
+
+        // class Eq a where eq : a -> a -> Bool; ne : a -> a -> Bool
+        pub rule class() -> Class = _ vis:visibility() "class" word_end() _ name:uname() _ param:ty_binder() _
+            "where" word_end() _ methods:(method_sig() ++ (_ ";" _)) {
+            Class { vis, name, param, methods }
+        }
+
+        rule method_sig() -> (Name, Type) = name:lname() _ ":" _ ty:ty() { (name, ty) }
+
+        // instance Eq Int where eq = \x:Int.\y:Int. x == y
+        // instance forall a. Eq a => Eq (List a) where eq = ...
+        pub rule instance() -> Instance = _ "instance" word_end() _ ty:ty() _ "where" word_end() _
+            methods:(field_expr() ++ (_ ";" _)) {
+            Instance { ty, methods }
         }
 
         pub rule value_def_item() -> Item = _ def:spanned(<value_def()>) _ {
@@ -158,10 +413,62 @@ peg::parser! {
             }
         }
 
-        pub rule item() -> Item = item:(value_def_item() / data_def_item()) _ ";" _ {
-            item
+        pub rule type_synonym_item() -> Item = _ def:spanned(<type_synonym()>) _ {
+            Item {
+                span: def.span,
+                kind: ItemKind::TypeSynonym(def.node)
+            }
+        }
+
+        rule class_item() -> Item = _ class:spanned(<class()>) _ {
+            Item {
+                span: class.span,
+                kind: ItemKind::Class(class.node)
+            }
+        }
+
+        rule instance_item() -> Item = _ instance:spanned(<instance()>) _ {
+            Item {
+                span: instance.span,
+                kind: ItemKind::Instance(instance.node)
+            }
+        }
+
+        // module M { let x: Int = 0; }
+        // module M;
+        pub rule module() -> Module = _ "module" _ name:uname() _ kind:module_kind() {
+            Module { name, kind }
+        }
+
+        rule module_kind() -> ModuleKind
+            = "{" _ items:item()* _ "}" { ModuleKind::Inline(items) }
+            / ";" { ModuleKind::File }
+
+        // import M;
+        // import M.x;
+        pub rule import() -> Import = _ "import" _ path:path() _ {
+            Import { path }
+        }
+
+        rule module_item() -> Item = _ module:spanned(<module()>) _ {
+            Item {
+                span: module.span,
+                kind: ItemKind::Module(module.node)
+            }
+        }
+
+        rule import_item() -> Item = _ import:spanned(<import()>) _ {
+            Item {
+                span: import.span,
+                kind: ItemKind::Import(import.node)
+            }
         }
 
+        pub rule item() -> Item
+            = item:(value_def_item() / data_def_item() / type_synonym_item() / class_item()
+                / instance_item() / import_item()) _ ";" _ { item }
+            / module_item()
+
         pub rule source_file() -> SourceFile = _ items:item()* {
             SourceFile { items }
         }
@@ -1,8 +1,11 @@
+use cir::subst::Substitute;
 use cir::Debruijn;
 use enum_map::{Enum, EnumMap};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::ast;
+use crate::resolve::{FileItems, ResolveError, ResolveErrorKind, Resolver, TypeNs, ValueNs};
 
 use la_arena::{Arena, Idx};
 
@@ -10,59 +13,482 @@ use la_arena::{Arena, Idx};
 pub(crate) struct LowerCtxt {
     pub(crate) bodies: Arena<cir::BodyData>,
     value_defs: Arena<cir::ValueDefData>,
-    exprs: Arena<cir::ExprData>,
-    foralls: Vec<cir::Name>,
+    data_defs: Arena<cir::DataDefData>,
+    type_synonyms: Arena<cir::TypeSynonymData>,
+    classes: Arena<cir::ClassData>,
+    instances: Arena<cir::InstanceData>,
+    foralls: Vec<cir::Symbol>,
+    /// The type synonyms currently being expanded, used to detect cycles
+    expanding: Vec<cir::TypeSynonymId>,
+    resolver: Resolver,
+    pub(crate) errors: Vec<ResolveError>,
 }
 
 impl LowerCtxt {
-    fn lower_source_file(&mut self, file: &ast::SourceFile) -> cir::Items {
-        let items = file.items.iter().map(|item| self.lower_item(item)).collect();
-        cir::Items { items }
+    pub(crate) fn new(resolver: Resolver) -> Self {
+        Self { resolver, ..Default::default() }
     }
 
-    fn lower_item(&mut self, item: &ast::Item) -> cir::Item {
-        match &item.kind {
-            ast::ItemKind::ValueDef(def) => cir::Item::ValueDef(self.lower_value_def(def)),
-            ast::ItemKind::DataDef(def) => cir::Item::DataDef(self.lower_data_def(def)),
+    pub(crate) fn lower_source_file(&mut self, file: &ast::SourceFile) -> cir::Items {
+        let mut items = vec![];
+        self.lower_items(&file.items, &mut items);
+        cir::Items {
+            items,
+            value_defs: std::mem::take(&mut self.value_defs),
+            data_defs: std::mem::take(&mut self.data_defs),
+            type_synonyms: std::mem::take(&mut self.type_synonyms),
+            classes: std::mem::take(&mut self.classes),
+            instances: std::mem::take(&mut self.instances),
+            bodies: std::mem::take(&mut self.bodies),
+        }
+    }
+
+    /// Lowers `items` and the items of any inline modules (in pre-order) into a flat list
+    fn lower_items(&mut self, items: &[ast::Item], acc: &mut Vec<cir::Item>) {
+        for item in items {
+            match &item.kind {
+                ast::ItemKind::ValueDef(def) =>
+                    acc.push(cir::Item::ValueDef(self.lower_value_def(def))),
+                ast::ItemKind::DataDef(def) => {
+                    let data_def = self.lower_data_def(def);
+                    acc.push(cir::Item::DataDef(data_def));
+                    for class in &def.deriving {
+                        acc.extend(self.derive(def, data_def, class).map(cir::Item::Instance));
+                    }
+                }
+                ast::ItemKind::TypeSynonym(def) =>
+                    acc.push(cir::Item::TypeSynonym(self.lower_type_synonym(def))),
+                ast::ItemKind::Class(class) => acc.push(cir::Item::Class(self.lower_class(class))),
+                ast::ItemKind::Instance(instance) =>
+                    acc.extend(self.lower_instance(item.span, instance).map(cir::Item::Instance)),
+                ast::ItemKind::Module(ast::Module {
+                    name,
+                    kind: ast::ModuleKind::Inline(items),
+                }) => self.in_module(name, |lcx| lcx.lower_items(items, acc)),
+                // File modules are lowered with their own file and imports are handled by the `DefMap`
+                ast::ItemKind::Module(_) | ast::ItemKind::Import(_) => {}
+            }
         }
     }
 
+    fn in_module<R>(&mut self, name: &cir::Name, f: impl FnOnce(&mut Self) -> R) -> R {
+        let child = self.resolver.child(name).unwrap_or_else(|| self.resolver.clone());
+        let parent = std::mem::replace(&mut self.resolver, child);
+        let r = f(self);
+        self.resolver = parent;
+        r
+    }
+
     fn lower_data_def(&mut self, data_def: &ast::DataDef) -> cir::DataDef {
-        todo!()
+        let ast::DataDef { vis: _, name, binders, variants, deriving: _ } = data_def;
+        let variants = self.in_foralls(binders, |lcx| {
+            variants.iter().map(|variant| lcx.lower_variant(name, binders, variant)).collect()
+        });
+        let data_def = cir::DataDefData { name: name.clone(), arity: binders.len(), variants };
+        let id = self.data_defs.alloc(data_def);
+        self.check_item_id(name, id, |items| &items.data_defs);
+        id
+    }
+
+    /// Generates the instance of the prelude's `Eq` or `Show` for a data type whose constructors
+    /// have no signatures. The instance requires an instance of the class for each parameter.
+    fn derive(
+        &mut self,
+        ast_data_def: &ast::DataDef,
+        data_def: cir::DataDef,
+        class_path: &cir::Path,
+    ) -> Option<cir::Instance> {
+        let class = self.lower_class_path(class_path)?;
+        let name = &ast_data_def.name;
+        let data_def_id = match self.resolver.resolve_type_path(&cir::Path::single(name.clone())) {
+            Ok(TypeNs::DataDef(data_def_id)) => data_def_id,
+            // A duplicate definition, which is already reported
+            _ => return None,
+        };
+        let variants = self.data_defs[data_def]
+            .variants
+            .iter()
+            .map(|(variant, data)| {
+                let variant = cir::VariantId { data_def: data_def_id, variant };
+                (variant, data.name.clone(), data.fields.clone())
+            })
+            .collect::<Vec<_>>();
+        let has_sigs = self.data_defs[data_def]
+            .variants
+            .iter()
+            .any(|(_, variant)| variant.result.is_some() || !variant.ty_params.is_empty());
+        let derivable = !has_sigs
+            && variants.iter().all(|(_, _, fields)| fields.iter().all(may_have_instances));
+        let def_map = Arc::clone(self.resolver.def_map());
+        let is_prelude_class = |name| def_map.prelude_type(name) == Some(TypeNs::Class(class));
+        let arity = ast_data_def.binders.len();
+        let vars = (0..arity as u32)
+            .rev()
+            .map(|index| cir::TyKind::Var(Debruijn::new(index)).intern())
+            .collect::<Vec<_>>();
+        let head = cir::TyKind::Adt(data_def_id, vars.clone()).intern();
+        let prelude_values = (
+            def_map.prelude_value("eq"),
+            def_map.prelude_value("show"),
+            def_map.prelude_value("showParens"),
+        );
+        let body = match prelude_values {
+            (Some(ValueNs::Method(eq)), _, _) if derivable && is_prelude_class("Eq") =>
+                BodyLowerCtxt::new(self).derive_eq(arity, &head, &variants, eq),
+            (_, Some(ValueNs::Method(show)), Some(ValueNs::ValueDef(show_parens)))
+                if derivable && is_prelude_class("Show") =>
+                BodyLowerCtxt::new(self).derive_show(arity, &head, &variants, show, show_parens),
+            _ => {
+                let kind = ResolveErrorKind::NotDerivable(class_path.clone(), name.clone());
+                self.errors.push(ResolveError::new(class_path.span(), kind));
+                return None;
+            }
+        };
+        let context = vars.into_iter().map(|var| (class, var)).collect();
+        let params = vec![cir::Kind::Type; arity];
+        let span = class_path.span();
+        let instance = cir::InstanceData { span, class, params, context, head, body };
+        Some(self.instances.alloc(instance))
+    }
+
+    fn lower_variant(
+        &mut self,
+        data_name: &cir::Name,
+        binders: &[ast::TyVar],
+        variant: &ast::Variant,
+    ) -> cir::VariantData {
+        let arity = binders.len();
+        let lower_fields =
+            |lcx: &mut Self| variant.params.iter().map(|ty| lcx.lower_ty(&ty.node)).collect();
+        let sig = match &variant.sig {
+            Some(sig) => sig,
+            None => {
+                let fields = lower_fields(self);
+                return cir::VariantData {
+                    name: variant.name.clone(),
+                    ty_params: vec![],
+                    fields,
+                    result: None,
+                };
+            }
+        };
+        let vars = sig.binders.iter().map(|(var, _)| var.clone()).collect::<Vec<_>>();
+        let (fields, result) = self.in_foralls(&vars, |lcx| {
+            let fields = lower_fields(lcx);
+            (fields, lcx.lower_ty(&sig.result))
+        });
+        let data_def = self.resolver.resolve_type_path(&cir::Path::single(data_name.clone()));
+        let args = match (data_def, result.kind()) {
+            (Ok(TypeNs::DataDef(data_def)), cir::TyKind::Adt(adt, args))
+                if *adt == data_def && args.len() == arity =>
+                args.clone(),
+            (_, cir::TyKind::Err) => vec![cir::TyKind::Err.intern(); arity],
+            _ => {
+                let kind =
+                    ResolveErrorKind::InvalidCtorResult(variant.name.clone(), data_name.clone());
+                self.errors.push(ResolveError::new(variant.name.span, kind));
+                vec![cir::TyKind::Err.intern(); arity]
+            }
+        };
+        // The parameters of the data definition are instantiated with the scrutinee's type when
+        // matching on the constructor, so they must be determined by the result type
+        for (i, var) in binders.iter().enumerate() {
+            let index = cir::Debruijn::new((sig.binders.len() + arity - 1 - i) as u32);
+            let is_arg =
+                args.iter().any(|arg| matches!(arg.kind(), cir::TyKind::Var(var) if *var == index));
+            let occurs = fields.iter().chain(&args).any(|ty| cir::subst::occurs(ty, index));
+            if occurs && !is_arg {
+                let kind =
+                    ResolveErrorKind::UndeterminedTyParam(var.name.clone(), variant.name.clone());
+                self.errors.push(ResolveError::new(variant.name.span, kind));
+            }
+        }
+        // The data type applied to its parameters needs no refinement when matched on
+        let ty_params = sig.binders.iter().map(|(_, kind)| kind.clone()).collect::<Vec<_>>();
+        let identity = ty_params.is_empty()
+            && args.iter().enumerate().all(|(i, arg)| {
+                matches!(arg.kind(), cir::TyKind::Var(var) if var.index() as usize == arity - 1 - i)
+            });
+        cir::VariantData {
+            name: variant.name.clone(),
+            ty_params,
+            fields,
+            result: (!identity).then_some(args),
+        }
+    }
+
+    fn lower_type_synonym(&mut self, type_synonym: &ast::TypeSynonym) -> cir::TypeSynonym {
+        let ast::TypeSynonym { vis: _, name, params, ty } = type_synonym;
+        let ty = self.in_foralls(params, |lcx| lcx.lower_ty(ty));
+        let type_synonym = cir::TypeSynonymData { name: name.clone(), arity: params.len(), ty };
+        let id = self.type_synonyms.alloc(type_synonym);
+        self.check_item_id(name, id, |items| &items.type_synonyms);
+        id
+    }
+
+    fn lower_class(&mut self, class: &ast::Class) -> cir::Class {
+        let ast::Class { vis: _, name, param: (var, kind), methods } = class;
+        let methods = self.in_forall(var, |lcx| {
+            methods.iter().map(|(name, ty)| (name.clone(), lcx.lower_ty(ty))).collect()
+        });
+        let class = cir::ClassData { name: name.clone(), kind: kind.clone(), methods };
+        let id = self.classes.alloc(class);
+        self.check_item_id(name, id, |items| &items.classes);
+        id
+    }
+
+    /// Lowers an instance, or returns `None` if its type is not a (constrained) class applied to
+    /// a type
+    fn lower_instance(
+        &mut self,
+        span: cir::Span,
+        instance: &ast::Instance,
+    ) -> Option<cir::Instance> {
+        let ast::Instance { ty, methods } = instance;
+        let mut params = vec![];
+        let mut ty = ty;
+        while let ast::Type::ForAll(var, kind, body) = ty {
+            params.push((var.clone(), kind.clone()));
+            ty = body;
+        }
+        let mut context = vec![];
+        while let ast::Type::Constrained(class, arg, body) = ty {
+            context.push((class, arg));
+            ty = body;
+        }
+        let class_and_head = match ty {
+            ast::Type::App(f, head) => match &**f {
+                ast::Type::Path(class) => Some((class, head)),
+                _ => None,
+            },
+            _ => None,
+        };
+        let (class, head) = match class_and_head {
+            Some(class_and_head) => class_and_head,
+            None => {
+                self.errors.push(ResolveError::new(span, ResolveErrorKind::InvalidInstanceHead));
+                return None;
+            }
+        };
+        let class = self.lower_class_path(class)?;
+        let vars = params.iter().map(|(var, _)| var.clone()).collect::<Vec<_>>();
+        let (context, head) = self.in_foralls(&vars, |lcx| {
+            let context = context
+                .iter()
+                .filter_map(|(class, arg)| Some((lcx.lower_class_path(class)?, lcx.lower_ty(arg))))
+                .collect();
+            (context, lcx.lower_ty(head))
+        });
+        let body = BodyLowerCtxt::new(self).lower_dict(&params, methods);
+        let params = params.into_iter().map(|(_, kind)| kind).collect();
+        let instance = cir::InstanceData { span, class, params, context, head, body };
+        Some(self.instances.alloc(instance))
+    }
+
+    fn lower_class_path(&mut self, path: &cir::Path) -> Option<cir::ClassId> {
+        match self.resolver.resolve_type_path(path) {
+            Ok(TypeNs::Class(class)) => Some(class),
+            Ok(TypeNs::DataDef(_) | TypeNs::TypeSynonym(_)) => {
+                let kind = ResolveErrorKind::NotAClass(path.clone());
+                self.errors.push(ResolveError::new(path.span(), kind));
+                None
+            }
+            Err(err) => {
+                self.errors.push(err);
+                None
+            }
+        }
     }
 
     fn lower_value_def(&mut self, value_def: &ast::ValueDef) -> cir::ValueDef {
-        let ast::ValueDef { name, ty, expr } = value_def;
+        let ast::ValueDef { vis: _, name, ty, expr } = value_def;
         let value_def = cir::ValueDefData {
             name: name.clone(),
-            ty: self.lower_ty(ty),
+            ty: ty.as_ref().map(|ty| self.lower_ty(ty)),
             body: self.lower_body(expr),
         };
-        self.value_defs.alloc(value_def)
+        let id = self.value_defs.alloc(value_def);
+        self.check_item_id(name, id, |items| &items.value_defs);
+        id
+    }
+
+    /// Checks that the ID allocated for the definition `name` is the one the `DefMap` assigned
+    /// it
+    fn check_item_id<T>(
+        &self,
+        name: &cir::Name,
+        id: Idx<T>,
+        names: impl FnOnce(&FileItems) -> &[cir::Name],
+    ) {
+        if cfg!(debug_assertions) {
+            if let Some(items) = self.resolver.def_map().file_items(name.span.file) {
+                let index = u32::from(id.into_raw()) as usize;
+                assert_eq!(
+                    names(items).get(index),
+                    Some(name),
+                    "the `DefMap` assigned a different ID to `{}`",
+                    name.symbol.as_str()
+                );
+            }
+        }
     }
 
     pub(crate) fn lower_ty(&mut self, ty: &ast::Type) -> cir::Ty {
         let kind = match &ty {
-            ast::Type::Var(var) => cir::TyKind::Var(self.lower_ty_var(var)),
+            ast::Type::Var(var) => match self.lower_ty_var(var) {
+                Some(debruijn) => cir::TyKind::Var(debruijn),
+                None => {
+                    let kind = ResolveErrorKind::UnboundTyVar(var.name.clone());
+                    self.errors.push(ResolveError::new(var.name.span, kind));
+                    cir::TyKind::Err
+                }
+            },
             ast::Type::Scalar(scalar) => cir::TyKind::Scalar(*scalar),
             ast::Type::Fn(l, r) => cir::TyKind::Fn(self.lower_ty(l), self.lower_ty(r)),
             // TODO not sure how to deal with var
-            ast::Type::ForAll(var, ty) =>
-                self.in_forall(var, |lcx| cir::TyKind::ForAll(lcx.lower_ty(ty))),
-            ast::Type::Path(_) => todo!(),
+            ast::Type::ForAll(var, kind, ty) =>
+                self.in_forall(var, |lcx| cir::TyKind::ForAll(kind.clone(), lcx.lower_ty(ty))),
+            ast::Type::Exists(var, ty) =>
+                self.in_forall(var, |lcx| cir::TyKind::Exists(lcx.lower_ty(ty))),
+            ast::Type::Mu(var, ty) => self.in_forall(var, |lcx| cir::TyKind::Mu(lcx.lower_ty(ty))),
+            ast::Type::Lambda(var, kind, ty) =>
+                self.in_forall(var, |lcx| cir::TyKind::Lambda(kind.clone(), lcx.lower_ty(ty))),
+            ast::Type::Path(path) => self.lower_ty_app(path, &[]),
+            ast::Type::App(f, x) => {
+                let mut args = vec![];
+                let mut head = ty;
+                while let ast::Type::App(f, x) = head {
+                    args.push(&**x);
+                    head = f;
+                }
+                args.reverse();
+                match head {
+                    ast::Type::Path(path) => self.lower_ty_app(path, &args),
+                    // Other type operators are applied one argument at a time
+                    _ => cir::TyKind::App(self.lower_ty(f), self.lower_ty(x)),
+                }
+            }
+            ast::Type::Tuple(tys) =>
+                cir::TyKind::Tuple(tys.iter().map(|ty| self.lower_ty(ty)).collect()),
+            ast::Type::Record(fields) => {
+                let fields = fields.iter().map(|(name, ty)| (name, self.lower_ty(ty))).collect();
+                cir::TyKind::Record(self.sort_fields(fields))
+            }
+            ast::Type::Constrained(class, arg, ty) => match self.lower_class_path(class) {
+                Some(class) =>
+                    cir::TyKind::Constrained(class, self.lower_ty(arg), self.lower_ty(ty)),
+                None => cir::TyKind::Err,
+            },
         };
         kind.intern()
     }
 
-    fn lower_ty_var(&mut self, var: &ast::TyVar) -> Debruijn {
-        let index = self.foralls.iter().rev().position(|name| name == &var.name).unwrap();
-        Debruijn::new(index as u32)
+    /// Sorts the fields of a record by name, reporting (and dropping) duplicate fields
+    fn sort_fields<T>(&mut self, mut fields: Vec<(&cir::Name, T)>) -> Vec<(cir::Symbol, T)> {
+        // The sort is stable so the first occurrence of a duplicate field is kept
+        fields.sort_by(|(a, _), (b, _)| a.symbol.as_str().cmp(b.symbol.as_str()));
+        let mut sorted: Vec<(cir::Symbol, T)> = Vec::with_capacity(fields.len());
+        for (name, x) in fields {
+            match sorted.last() {
+                Some((prev, _)) if *prev == name.symbol => {
+                    let kind = ResolveErrorKind::DuplicateField(name.clone());
+                    self.errors.push(ResolveError::new(name.span, kind));
+                }
+                _ => sorted.push((name.symbol.clone(), x)),
+            }
+        }
+        sorted
+    }
+
+    fn lower_ty_app(&mut self, path: &cir::Path, args: &[&ast::Type]) -> cir::TyKind {
+        match self.resolver.resolve_type_path(path) {
+            Ok(TypeNs::DataDef(data_def)) => {
+                let args = args.iter().map(|arg| self.lower_ty(arg)).collect();
+                cir::TyKind::Adt(data_def, args)
+            }
+            Ok(TypeNs::TypeSynonym(type_synonym)) =>
+                self.expand_type_synonym(path, type_synonym, args),
+            Ok(TypeNs::Class(_)) => {
+                let kind = ResolveErrorKind::ClassAsType(path.clone());
+                self.errors.push(ResolveError::new(path.span(), kind));
+                cir::TyKind::Err
+            }
+            Err(err) => {
+                self.errors.push(err);
+                cir::TyKind::Err
+            }
+        }
+    }
+
+    /// Expands a use of a type synonym by substituting the arguments for its parameters.
+    /// If there are fewer arguments than parameters the result is a type operator.
+    fn expand_type_synonym(
+        &mut self,
+        path: &cir::Path,
+        type_synonym: cir::TypeSynonymId,
+        args: &[&ast::Type],
+    ) -> cir::TyKind {
+        let (arity, ty) = match self.type_synonym_ty(type_synonym) {
+            Some(expansion) => expansion,
+            None => {
+                let kind = ResolveErrorKind::CyclicTypeSynonym(path.last().clone());
+                self.errors.push(ResolveError::new(path.span(), kind));
+                return cir::TyKind::Err;
+            }
+        };
+        // The first parameter is bound by the outermost type operator
+        let mut ty = (0..arity).fold(ty, |ty, _| cir::TyKind::Lambda(cir::Kind::Type, ty).intern());
+        for arg in args {
+            let arg = self.lower_ty(arg);
+            ty = match ty.kind() {
+                cir::TyKind::Lambda(_, body) => body.substitute(&arg),
+                _ => cir::TyKind::App(ty, arg).intern(),
+            };
+        }
+        ty.kind().clone()
+    }
+
+    /// The arity and the type of a type synonym (defined in any file), or `None` if its
+    /// expansion refers to itself
+    fn type_synonym_ty(&mut self, type_synonym: cir::TypeSynonymId) -> Option<(usize, cir::Ty)> {
+        if self.expanding.contains(&type_synonym) {
+            return None;
+        }
+        let def_map = Arc::clone(self.resolver.def_map());
+        let source = def_map.type_synonym(type_synonym);
+        let resolver = Resolver::new(Arc::clone(&def_map), source.module);
+        let resolver = std::mem::replace(&mut self.resolver, resolver);
+        // The use site's type variables are not in scope in the synonym
+        let foralls = std::mem::take(&mut self.foralls);
+        // Errors within the synonym are reported when its definition is lowered
+        let errors = self.errors.len();
+        self.expanding.push(type_synonym);
+        let ty = self.in_foralls(&source.params, |lcx| lcx.lower_ty(&source.ty));
+        assert_eq!(self.expanding.pop(), Some(type_synonym));
+        self.resolver = resolver;
+        self.foralls = foralls;
+        let cyclic = self
+            .errors
+            .drain(errors..)
+            .any(|err| matches!(err.kind, ResolveErrorKind::CyclicTypeSynonym(_)));
+        (!cyclic).then_some((source.params.len(), ty))
+    }
+
+    fn lower_ty_var(&mut self, var: &ast::TyVar) -> Option<Debruijn> {
+        let index = self.foralls.iter().rev().position(|symbol| symbol == &var.name.symbol)?;
+        Some(Debruijn::new(index as u32))
     }
 
     fn in_forall<R>(&mut self, var: &ast::TyVar, f: impl FnOnce(&mut Self) -> R) -> R {
-        self.foralls.push(var.name.clone());
+        self.in_foralls(std::slice::from_ref(var), f)
+    }
+
+    fn in_foralls<R>(&mut self, vars: &[ast::TyVar], f: impl FnOnce(&mut Self) -> R) -> R {
+        self.foralls.extend(vars.iter().map(|var| var.name.symbol.clone()));
         let r = f(self);
-        assert_eq!(self.foralls.pop().unwrap(), var.name);
+        for var in vars.iter().rev() {
+            assert_eq!(self.foralls.pop().unwrap(), var.name.symbol);
+        }
         r
     }
 
@@ -76,7 +502,7 @@ pub(crate) struct BodyLowerCtxt<'lcx> {
     lcx: &'lcx mut LowerCtxt,
     exprs: Arena<cir::ExprData>,
     binders: Arena<cir::BinderData>,
-    binder_map: Namespaced<HashMap<cir::Name, Vec<cir::Binder>>>,
+    binder_map: Namespaced<HashMap<cir::Symbol, Vec<cir::Binder>>>,
 }
 
 type Namespaced<T> = EnumMap<Ns, T>;
@@ -99,6 +525,160 @@ impl<'lcx> BodyLowerCtxt<'lcx> {
 
     pub(crate) fn lower(mut self, expr: &ast::Expr) -> cir::Body {
         let expr = self.lower_expr(expr);
+        self.finish(expr)
+    }
+
+    /// Lowers the methods of an instance to its dictionary, a record of the methods under a type
+    /// lambda for each of the instance's parameters
+    fn lower_dict(
+        mut self,
+        params: &[(ast::TyVar, cir::Kind)],
+        methods: &[(cir::Name, ast::Expr)],
+    ) -> cir::Body {
+        self.lcx.foralls.extend(params.iter().map(|(var, _)| var.name.symbol.clone()));
+        let fields = methods.iter().map(|(name, expr)| (name, self.lower_expr(expr))).collect();
+        let fields = self.lcx.sort_fields(fields);
+        for (var, _) in params.iter().rev() {
+            assert_eq!(self.lcx.foralls.pop().unwrap(), var.name.symbol);
+        }
+        let kinds = params.iter().map(|(_, kind)| kind.clone()).collect::<Vec<_>>();
+        self.finish_dict(&kinds, fields)
+    }
+
+    /// The derived dictionary of `Eq` for the data type `ty` (with `arity` parameters), values
+    /// are equal if they are built by the same constructor from equal fields. The constructors
+    /// are compared by their indices so that the body is linear in their number, i.e.
+    /// `\x:T.\y:T. tag x == tag y && match x { C x1 -> match y { C y1 -> eq x1 y1 }, D -> ... }`
+    /// where `tag x` is `match x { C x1 -> 0, D -> 1 }`
+    fn derive_eq(
+        mut self,
+        arity: usize,
+        ty: &cir::Ty,
+        variants: &[(cir::VariantId, cir::Name, Vec<cir::Ty>)],
+        eq: cir::MethodId,
+    ) -> cir::Body {
+        let x = self.binders.alloc(cir::BinderData::Val(cir::Ty::clone(ty)));
+        let y = self.binders.alloc(cir::BinderData::Val(cir::Ty::clone(ty)));
+        let mut alts = vec![];
+        for (variant, _, fields) in variants {
+            let xs = fields.iter().map(|_| self.binders.alloc(cir::BinderData::Pat)).collect();
+            let ys = fields.iter().map(|_| self.binders.alloc(cir::BinderData::Pat));
+            let ys = ys.collect::<Vec<_>>();
+            let body = fields
+                .iter()
+                .zip(&xs)
+                .zip(&ys)
+                .map(|((field, &x), &y)| {
+                    let eq = self.apply_method(eq, field, x);
+                    let y = self.exprs.alloc(cir::ExprData::Var(y));
+                    self.exprs.alloc(cir::ExprData::App(eq, y))
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .reduce(|l, r| self.exprs.alloc(cir::ExprData::BinOp(cir::BinOp::And, l, r)))
+                .unwrap_or_else(|| self.exprs.alloc(cir::ExprData::Lit(cir::Lit::Bool(true))));
+            // `y` is built by the same constructor once the tags are equal
+            let y_alt = cir::Alt { pat: cir::Pat::Variant(*variant, vec![], ys), body };
+            let scrutinee = self.exprs.alloc(cir::ExprData::Var(y));
+            let body = self.exprs.alloc(cir::ExprData::Case(scrutinee, vec![y_alt]));
+            alts.push(cir::Alt { pat: cir::Pat::Variant(*variant, vec![], xs), body });
+        }
+        let scrutinee = self.exprs.alloc(cir::ExprData::Var(x));
+        let mut case = self.exprs.alloc(cir::ExprData::Case(scrutinee, alts));
+        if variants.len() > 1 {
+            let (x_tag, y_tag) = (self.tag(x, variants), self.tag(y, variants));
+            let tags_eq = self.exprs.alloc(cir::ExprData::BinOp(cir::BinOp::Eq, x_tag, y_tag));
+            case = self.exprs.alloc(cir::ExprData::BinOp(cir::BinOp::And, tags_eq, case));
+        }
+        let lambda = self.exprs.alloc(cir::ExprData::Lambda(y, case));
+        let lambda = self.exprs.alloc(cir::ExprData::Lambda(x, lambda));
+        self.finish_dict(&vec![cir::Kind::Type; arity], vec![(cir::Symbol::new("eq"), lambda)])
+    }
+
+    /// The index of the constructor of `x` among `variants`, i.e. `match x { C x1 x2 -> 0, ... }`
+    fn tag(
+        &mut self,
+        x: cir::Binder,
+        variants: &[(cir::VariantId, cir::Name, Vec<cir::Ty>)],
+    ) -> cir::Expr {
+        let mut alts = vec![];
+        for (i, (variant, _, fields)) in variants.iter().enumerate() {
+            let xs = fields.iter().map(|_| self.binders.alloc(cir::BinderData::Pat)).collect();
+            let body = self.exprs.alloc(cir::ExprData::Lit(cir::Lit::Int(i as i64)));
+            alts.push(cir::Alt { pat: cir::Pat::Variant(*variant, vec![], xs), body });
+        }
+        let scrutinee = self.exprs.alloc(cir::ExprData::Var(x));
+        self.exprs.alloc(cir::ExprData::Case(scrutinee, alts))
+    }
+
+    /// The derived dictionary of `Show` for the data type `ty` (with `arity` parameters), which
+    /// shows values as the evaluator displays them, e.g. `Cons 1 (Cons 2 Nil)`
+    fn derive_show(
+        mut self,
+        arity: usize,
+        ty: &cir::Ty,
+        variants: &[(cir::VariantId, cir::Name, Vec<cir::Ty>)],
+        show: cir::MethodId,
+        show_parens: cir::ValueDefId,
+    ) -> cir::Body {
+        let x = self.binders.alloc(cir::BinderData::Val(cir::Ty::clone(ty)));
+        let mut alts = vec![];
+        for (variant, name, fields) in variants {
+            let xs = fields.iter().map(|_| self.binders.alloc(cir::BinderData::Pat)).collect();
+            let lit = cir::Lit::String(name.symbol.as_str().into());
+            let mut body = self.exprs.alloc(cir::ExprData::Lit(lit));
+            for (field, &x) in fields.iter().zip(&xs) {
+                let space = self.exprs.alloc(cir::ExprData::Lit(cir::Lit::String(" ".into())));
+                body = self.concat(body, space);
+                let shown = self.apply_method(show, field, x);
+                let show_parens = self.exprs.alloc(cir::ExprData::Def(show_parens));
+                let shown = self.exprs.alloc(cir::ExprData::App(show_parens, shown));
+                body = self.concat(body, shown);
+            }
+            alts.push(cir::Alt { pat: cir::Pat::Variant(*variant, vec![], xs), body });
+        }
+        let scrutinee = self.exprs.alloc(cir::ExprData::Var(x));
+        let case = self.exprs.alloc(cir::ExprData::Case(scrutinee, alts));
+        let lambda = self.exprs.alloc(cir::ExprData::Lambda(x, case));
+        self.finish_dict(&vec![cir::Kind::Type; arity], vec![(cir::Symbol::new("show"), lambda)])
+    }
+
+    /// `method @ty x`
+    fn apply_method(&mut self, method: cir::MethodId, ty: &cir::Ty, x: cir::Binder) -> cir::Expr {
+        let method = self.exprs.alloc(cir::ExprData::Method(method));
+        let ty = self.exprs.alloc(cir::ExprData::Type(cir::Ty::clone(ty)));
+        let method = self.exprs.alloc(cir::ExprData::App(method, ty));
+        let x = self.exprs.alloc(cir::ExprData::Var(x));
+        self.exprs.alloc(cir::ExprData::App(method, x))
+    }
+
+    /// `stringConcat l r`
+    fn concat(&mut self, l: cir::Expr, r: cir::Expr) -> cir::Expr {
+        let concat = self.exprs.alloc(cir::ExprData::Prim(cir::Prim::StringConcat));
+        let concat = self.exprs.alloc(cir::ExprData::App(concat, l));
+        self.exprs.alloc(cir::ExprData::App(concat, r))
+    }
+
+    /// Finishes a dictionary, a record of the (sorted) methods under a type lambda for each
+    /// parameter of the instance
+    fn finish_dict(
+        mut self,
+        kinds: &[cir::Kind],
+        methods: Vec<(cir::Symbol, cir::Expr)>,
+    ) -> cir::Body {
+        let ty_binders = kinds
+            .iter()
+            .map(|kind| self.binders.alloc(cir::BinderData::Ty(kind.clone())))
+            .collect::<Vec<_>>();
+        let record = self.exprs.alloc(cir::ExprData::Record(methods));
+        let expr = ty_binders
+            .into_iter()
+            .rev()
+            .fold(record, |expr, binder| self.exprs.alloc(cir::ExprData::Lambda(binder, expr)));
+        self.finish(expr)
+    }
+
+    fn finish(self, expr: cir::Expr) -> cir::Body {
         let Self { exprs, binders, .. } = self;
         self.lcx.bodies.alloc(cir::BodyData::new(expr, exprs, binders))
     }
@@ -106,27 +686,166 @@ impl<'lcx> BodyLowerCtxt<'lcx> {
     fn lower_expr(&mut self, expr: &ast::Expr) -> cir::Expr {
         let expr = match expr {
             ast::Expr::Var(var) => self.lower_var_expr(var),
-            ast::Expr::Lit(lit) => cir::ExprData::Lit(match lit.kind {
-                ast::LiteralKind::Int(i) => cir::Lit::Int(i),
-                ast::LiteralKind::Bool(b) => cir::Lit::Bool(b),
-            }),
+            ast::Expr::Lit(lit) => match &lit.kind {
+                &ast::LiteralKind::Int(i) => self.lower_int(lit.span, i),
+                &ast::LiteralKind::Float(f) => cir::ExprData::Lit(cir::Lit::Float(f)),
+                &ast::LiteralKind::Bool(b) => cir::ExprData::Lit(cir::Lit::Bool(b)),
+                &ast::LiteralKind::Char(c) => cir::ExprData::Lit(cir::Lit::Char(c)),
+                ast::LiteralKind::String(s) => cir::ExprData::Lit(cir::Lit::String(s.clone())),
+            },
             ast::Expr::Lambda(binder, expr) => self.in_binder(binder, |bcx, binder| {
                 cir::ExprData::Lambda(binder, bcx.lower_expr(expr))
             }),
             ast::Expr::App(f, x) => cir::ExprData::App(self.lower_expr(f), self.lower_expr(x)),
             ast::Expr::Type(ty) => cir::ExprData::Type(self.lcx.lower_ty(ty)),
-            ast::Expr::Case(_, _) => todo!(),
-            ast::Expr::Path(_) => todo!(),
+            ast::Expr::Case(scrutinee, alts) => {
+                let scrutinee = self.lower_expr(scrutinee);
+                let alts = alts.iter().map(|alt| self.lower_alt(alt)).collect();
+                cir::ExprData::Case(scrutinee, alts)
+            }
+            ast::Expr::If(c, t, e) =>
+                cir::ExprData::If(self.lower_expr(c), self.lower_expr(t), self.lower_expr(e)),
+            ast::Expr::Let(name, ty, e1, e2) => {
+                let binder_data = match ty {
+                    Some(ty) => cir::BinderData::Val(self.lcx.lower_ty(ty)),
+                    None => cir::BinderData::Let,
+                };
+                // The bound expression is lowered outside the scope of the binder
+                let e1 = self.lower_expr(e1);
+                let binder = self.binders.alloc(binder_data);
+                let e2 =
+                    self.in_scope(std::slice::from_ref(name), &[binder], |bcx| bcx.lower_expr(e2));
+                cir::ExprData::Let(binder, e1, e2)
+            }
+            ast::Expr::LetRec(bindings, e) => {
+                let names = bindings.iter().map(|binding| binding.name.clone()).collect::<Vec<_>>();
+                let binders = bindings
+                    .iter()
+                    .map(|binding| {
+                        let binder_data = cir::BinderData::Val(self.lcx.lower_ty(&binding.ty));
+                        self.binders.alloc(binder_data)
+                    })
+                    .collect::<Vec<_>>();
+                self.in_scope(&names, &binders, |bcx| {
+                    let exprs = bindings.iter().map(|binding| bcx.lower_expr(&binding.expr));
+                    let bindings = binders.iter().copied().zip(exprs).collect();
+                    cir::ExprData::LetRec(bindings, bcx.lower_expr(e))
+                })
+            }
+            ast::Expr::BinOp(op, l, r) =>
+                cir::ExprData::BinOp(*op, self.lower_expr(l), self.lower_expr(r)),
+            ast::Expr::UnOp(op, expr) => match (op, &**expr) {
+                // Negative literals are parsed as negations
+                (
+                    ast::UnOp::Neg,
+                    &ast::Expr::Lit(ast::Literal { span, kind: ast::LiteralKind::Int(i) }),
+                ) => self.lower_int(span, -i),
+                (
+                    ast::UnOp::Neg,
+                    ast::Expr::Lit(ast::Literal { kind: ast::LiteralKind::Float(f), .. }),
+                ) => cir::ExprData::Lit(cir::Lit::Float(cir::Float::new(-f.value()))),
+                _ => cir::ExprData::UnOp(*op, self.lower_expr(expr)),
+            },
+            ast::Expr::Tuple(exprs) =>
+                cir::ExprData::Tuple(exprs.iter().map(|expr| self.lower_expr(expr)).collect()),
+            ast::Expr::Record(fields) => {
+                let fields =
+                    fields.iter().map(|(name, expr)| (name, self.lower_expr(expr))).collect();
+                cir::ExprData::Record(self.lcx.sort_fields(fields))
+            }
+            ast::Expr::Field(expr, name) =>
+                cir::ExprData::Field(self.lower_expr(expr), name.symbol.clone()),
+            ast::Expr::Pack(hidden, expr, ty) => {
+                let hidden = self.lcx.lower_ty(hidden);
+                cir::ExprData::Pack(hidden, self.lower_expr(expr), self.lcx.lower_ty(ty))
+            }
+            ast::Expr::Unpack(var, name, e1, e2) => {
+                let e1 = self.lower_expr(e1);
+                let ty_binder = self.binders.alloc(cir::BinderData::Ty(cir::Kind::Type));
+                let binder = self.binders.alloc(cir::BinderData::Pat);
+                // The type variable is in scope in the type of `x` and the body
+                self.lcx.foralls.push(var.name.symbol.clone());
+                let e2 =
+                    self.in_scope(std::slice::from_ref(name), &[binder], |bcx| bcx.lower_expr(e2));
+                assert_eq!(self.lcx.foralls.pop().unwrap(), var.name.symbol);
+                cir::ExprData::Unpack(ty_binder, binder, e1, e2)
+            }
+            ast::Expr::Fold(ty, expr) =>
+                cir::ExprData::Fold(self.lcx.lower_ty(ty), self.lower_expr(expr)),
+            ast::Expr::Unfold(expr) => cir::ExprData::Unfold(self.lower_expr(expr)),
+            ast::Expr::Path(path) => self.lower_path_expr(path),
         };
         self.exprs.alloc(expr)
     }
 
-    fn lower_var_expr(&self, var: &ast::Var) -> cir::ExprData {
-        let binder = match self.lookup_var(var) {
-            Some(binder) => binder,
-            None => todo!("unbound var: {:?}", var),
+    fn lower_alt(&mut self, alt: &ast::Alt) -> cir::Alt {
+        let (ty_vars, names) = match &alt.pat {
+            ast::Pat::Variant(_, ty_vars, names) => (&ty_vars[..], names),
+            ast::Pat::Tuple(names) => (&[][..], names),
+        };
+        let ty_binders = ty_vars
+            .iter()
+            .map(|_| self.binders.alloc(cir::BinderData::Ty(cir::Kind::Type)))
+            .collect::<Vec<_>>();
+        let binders =
+            names.iter().map(|_| self.binders.alloc(cir::BinderData::Pat)).collect::<Vec<_>>();
+        // The type variables are in scope in the types of the binders and the body
+        self.lcx.foralls.extend(ty_vars.iter().map(|var| var.name.symbol.clone()));
+        let body = self.in_scope(names, &binders, |bcx| bcx.lower_expr(&alt.body));
+        for var in ty_vars.iter().rev() {
+            assert_eq!(self.lcx.foralls.pop().unwrap(), var.name.symbol);
+        }
+
+        let path = match &alt.pat {
+            ast::Pat::Variant(path, ..) => path,
+            ast::Pat::Tuple(_) => return cir::Alt { pat: cir::Pat::Tuple(binders), body },
+        };
+        let pat = match self.lcx.resolver.resolve_value_path(path) {
+            Ok(ValueNs::Variant(variant)) => cir::Pat::Variant(variant, ty_binders, binders),
+            Ok(ValueNs::ValueDef(_) | ValueNs::Method(_) | ValueNs::Prim(_)) => {
+                let kind = ResolveErrorKind::NotAConstructor(path.clone());
+                self.lcx.errors.push(ResolveError::new(path.span(), kind));
+                cir::Pat::Err(ty_binders, binders)
+            }
+            Err(err) => {
+                self.lcx.errors.push(err);
+                cir::Pat::Err(ty_binders, binders)
+            }
         };
-        cir::ExprData::Var(binder)
+        cir::Alt { pat, body }
+    }
+
+    /// The integer literal `i`, or an error if it does not fit in an `Int`
+    fn lower_int(&mut self, span: cir::Span, i: i128) -> cir::ExprData {
+        match i64::try_from(i) {
+            Ok(i) => cir::ExprData::Lit(cir::Lit::Int(i)),
+            Err(_) => {
+                self.lcx.errors.push(ResolveError::new(span, ResolveErrorKind::IntOutOfRange));
+                cir::ExprData::Err
+            }
+        }
+    }
+
+    fn lower_var_expr(&mut self, var: &ast::Var) -> cir::ExprData {
+        match (self.lookup_var(var), var) {
+            (Some(binder), _) => cir::ExprData::Var(binder),
+            (None, ast::Var::Val { name }) =>
+                self.lower_path_expr(&cir::Path::single(name.clone())),
+            (None, ast::Var::Ty(_)) => todo!(),
+        }
+    }
+
+    fn lower_path_expr(&mut self, path: &cir::Path) -> cir::ExprData {
+        match self.lcx.resolver.resolve_value_path(path) {
+            Ok(ValueNs::ValueDef(def)) => cir::ExprData::Def(def),
+            Ok(ValueNs::Variant(variant)) => cir::ExprData::Ctor(variant),
+            Ok(ValueNs::Method(method)) => cir::ExprData::Method(method),
+            Ok(ValueNs::Prim(prim)) => cir::ExprData::Prim(prim),
+            Err(err) => {
+                self.lcx.errors.push(err);
+                cir::ExprData::Err
+            }
+        }
     }
 
     fn lookup_var(&self, name: &ast::Var) -> Option<cir::Binder> {
@@ -135,7 +854,24 @@ impl<'lcx> BodyLowerCtxt<'lcx> {
             ast::Var::Ty(_) => todo!(),
         };
 
-        self.binder_map[ns].get(name).and_then(|binders| binders.last().copied())
+        self.binder_map[ns].get(&name.symbol).and_then(|binders| binders.last().copied())
+    }
+
+    /// Brings the value binders `binders` into scope as `names` for the duration of `f`
+    fn in_scope<R>(
+        &mut self,
+        names: &[cir::Name],
+        binders: &[cir::Binder],
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        for (name, &binder) in names.iter().zip(binders) {
+            self.binder_map[Ns::Val].entry(name.symbol.clone()).or_default().push(binder);
+        }
+        let r = f(self);
+        for (name, &binder) in names.iter().zip(binders).rev() {
+            assert_eq!(self.binder_map[Ns::Val].get_mut(&name.symbol).unwrap().pop(), Some(binder));
+        }
+        r
     }
 
     fn in_binder<R>(
@@ -147,28 +883,41 @@ impl<'lcx> BodyLowerCtxt<'lcx> {
             ast::Binder::Val(name, ty) => {
                 // (name, Ns::Val, cir::BinderData::Val(self.lcx.lower_ty(ty)));
                 let ns = Ns::Val;
-                let binder_data = cir::BinderData::Val(self.lcx.lower_ty(ty));
+                let binder_data = match ty {
+                    Some(ty) => cir::BinderData::Val(self.lcx.lower_ty(ty)),
+                    None => cir::BinderData::Infer,
+                };
                 let binder = self.binders.alloc(binder_data);
-                self.binder_map[ns].entry(name.clone()).or_default().push(binder);
+                self.binder_map[ns].entry(name.symbol.clone()).or_default().push(binder);
                 let r = f(self, binder);
-                assert_eq!(self.binder_map[ns].get_mut(name).unwrap().pop(), Some(binder));
+                assert_eq!(self.binder_map[ns].get_mut(&name.symbol).unwrap().pop(), Some(binder));
                 r
             }
-            ast::Binder::Ty(var) => {
-                // (&var.name, Ns::Ty, cir::BinderData::Ty),
+            ast::Binder::Ty(var, kind) => {
+                // (&var.name, Ns::Ty, cir::BinderData::Ty(kind)),
                 // FIXME hack (copying `in_forall` impl for now)
-                self.lcx.foralls.push(var.name.clone());
+                self.lcx.foralls.push(var.name.symbol.clone());
 
                 // FIXME do we need this binder
-                let binder_data = cir::BinderData::Ty;
+                let binder_data = cir::BinderData::Ty(kind.clone());
                 let binder = self.binders.alloc(binder_data);
                 let r = f(self, binder);
-                assert_eq!(self.lcx.foralls.pop().unwrap(), var.name);
+                assert_eq!(self.lcx.foralls.pop().unwrap(), var.name.symbol);
                 r
             }
         }
     }
 }
 
+/// Whether `ty` may have instances of a class, which is not the case for tuples, records, function
+/// types and the like as instance heads are data types and scalars
+fn may_have_instances(ty: &cir::Ty) -> bool {
+    match ty.kind() {
+        cir::TyKind::Scalar(_) | cir::TyKind::Var(_) | cir::TyKind::Err => true,
+        cir::TyKind::Adt(_, args) => args.iter().all(may_have_instances),
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests;
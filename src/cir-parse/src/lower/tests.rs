@@ -1,15 +1,16 @@
-use cir::{BinderData, Expr, ExprData, TyKind};
-use la_arena::RawIdx;
+use cir::{BinderData, Expr, ExprData, FileId, TyKind};
+use la_arena::{Idx, RawIdx};
 
 use crate::{parse_body, parse_ty};
 
 use super::*;
 
+const FILE: FileId = FileId(0);
+
 #[test]
 fn test_lower_program() -> anyhow::Result<()> {
-    let source = crate::cirparser::source_file("let x: Int = 1; let y: Bool = false;")?;
-    let mut lcx = LowerCtxt::default();
-    let file = lcx.lower_source_file(&source);
+    let source = crate::cirparser::source_file("let x: Int = 1; let y: Bool = false;", FILE)?;
+    let file = LowerCtxt::default().lower_source_file(&source);
     assert_eq!(file.items.len(), 2);
     Ok(())
 }
@@ -18,7 +19,7 @@ fn test_lower_program() -> anyhow::Result<()> {
 fn test_lower_value_def() -> anyhow::Result<()> {
     macro_rules! lower {
         ($s:expr) => {{
-            let value_def = crate::cirparser::value_def($s)?;
+            let value_def = crate::cirparser::value_def($s, FILE)?;
             let mut lcx = LowerCtxt::default();
             lcx.lower_value_def(&value_def);
         }};
@@ -74,3 +75,45 @@ fn test_lower_binders_shadows() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_lower_record_fields_are_sorted() -> anyhow::Result<()> {
+    assert_eq!(parse_ty("{ y: Bool, x: Int }"), parse_ty("{ x: Int, y: Bool }"));
+    let body = parse_body("{ b = 1, a = true }");
+    match &body.exprs[body.expr] {
+        ExprData::Record(fields) =>
+            assert_eq!(fields.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), ["a", "b"]),
+        _ => panic!(),
+    }
+
+    let ty = crate::cirparser::ty("{ x: Int, y: Bool, x: Bool }", FILE)?;
+    let mut lcx = LowerCtxt::default();
+    let ty = lcx.lower_ty(&ty);
+    assert_eq!(ty, parse_ty("{ x: Int, y: Bool }"));
+    assert_eq!(
+        lcx.errors.iter().map(|err| err.to_string()).collect::<Vec<_>>(),
+        ["field `x` is specified more than once"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_lower_int_range() -> anyhow::Result<()> {
+    let body = parse_body("-9223372036854775808");
+    assert_eq!(body.exprs[body.expr], ExprData::Lit(cir::Lit::Int(i64::MIN)));
+    let body = parse_body("9223372036854775807");
+    assert_eq!(body.exprs[body.expr], ExprData::Lit(cir::Lit::Int(i64::MAX)));
+
+    let mut lcx = LowerCtxt::default();
+    for src in ["9223372036854775808", "-9223372036854775809"] {
+        let value_def = crate::cirparser::value_def(&format!("let x: Int = {}", src), FILE)?;
+        let def = lcx.lower_value_def(&value_def);
+        let body = &lcx.bodies[lcx.value_defs[def].body];
+        assert_eq!(body.exprs[body.expr], ExprData::Err);
+    }
+    assert_eq!(
+        lcx.errors.iter().map(|err| err.to_string()).collect::<Vec<_>>(),
+        ["integer literal is out of range for `Int`"; 2]
+    );
+    Ok(())
+}
@@ -0,0 +1,12 @@
+use cir::{FileId, SourceMap};
+
+/// The path the bundled prelude is registered under, the resolver recognises the prelude by it
+pub const PRELUDE_PATH: &str = "<prelude>";
+
+const PRELUDE_SOURCE: &str = include_str!("prelude.cir");
+
+/// Adds the bundled prelude to `source_map`, its public items are then implicitly in scope in
+/// every module
+pub fn add_prelude(source_map: &mut SourceMap) -> FileId {
+    source_map.add(PRELUDE_PATH, PRELUDE_SOURCE)
+}
@@ -0,0 +1,663 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use cir::{
+    ClassId, DataDefId, FileId, MethodId, Name, Path, Span, Symbol, TypeSynonymId, ValueDefId,
+    VariantId, Visibility,
+};
+use la_arena::{Arena, Idx, RawIdx};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::ast;
+use crate::db::LowerDatabase;
+use crate::prelude::PRELUDE_PATH;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypeNs {
+    DataDef(DataDefId),
+    TypeSynonym(TypeSynonymId),
+    Class(ClassId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueNs {
+    ValueDef(ValueDefId),
+    Variant(VariantId),
+    Method(MethodId),
+    Prim(cir::Prim),
+}
+
+pub type ModuleId = Idx<ModuleData>;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ModuleData {
+    /// `None` for the root module
+    pub name: Option<Name>,
+    pub parent: Option<ModuleId>,
+    /// The items defined in this module
+    pub scope: Scope,
+    /// The items brought into scope by `import M.x`
+    imports: Scope,
+    /// The modules brought into scope by `import M`
+    glob_imports: Vec<ModuleId>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Scope {
+    pub types: FxHashMap<Symbol, (TypeNs, Visibility)>,
+    pub values: FxHashMap<Symbol, (ValueNs, Visibility)>,
+    /// Modules are always public
+    pub modules: FxHashMap<Symbol, (ModuleId, Visibility)>,
+}
+
+/// The definition of a type synonym, kept so that its uses can be expanded in any file
+#[derive(Debug, PartialEq, Eq)]
+pub struct TypeSynonymSource {
+    /// The module the synonym is defined in, its type is resolved relative to this module
+    pub module: ModuleId,
+    pub params: Vec<ast::TyVar>,
+    pub ty: ast::Type,
+}
+
+/// The names of the definitions of a file, indexed by the IDs the `DefMap` assigns them.
+/// `LowerCtxt` allocates the definitions in the same order, which it checks against these.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FileItems {
+    pub value_defs: Vec<Name>,
+    pub data_defs: Vec<Name>,
+    pub type_synonyms: Vec<Name>,
+    pub classes: Vec<Name>,
+}
+
+/// The module tree of the entire program.
+/// The top-level items of every file that is not declared as a file module by `module M;` are
+/// defined in the root module.
+/// The items of the prelude (if it is loaded) are defined in the `Prelude` module, whose public
+/// items are in scope everywhere unless shadowed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DefMap {
+    modules: Arena<ModuleData>,
+    root: ModuleId,
+    prelude: Option<ModuleId>,
+    file_modules: FxHashMap<FileId, ModuleId>,
+    file_items: FxHashMap<FileId, FileItems>,
+    type_synonyms: FxHashMap<TypeSynonymId, TypeSynonymSource>,
+    errors: Vec<ResolveError>,
+}
+
+impl Default for DefMap {
+    fn default() -> Self {
+        let mut modules = Arena::default();
+        let root = modules.alloc(ModuleData::default());
+        Self {
+            modules,
+            root,
+            prelude: None,
+            file_modules: Default::default(),
+            file_items: Default::default(),
+            type_synonyms: Default::default(),
+            errors: Default::default(),
+        }
+    }
+}
+
+impl std::ops::Index<ModuleId> for DefMap {
+    type Output = ModuleData;
+
+    fn index(&self, index: ModuleId) -> &Self::Output {
+        &self.modules[index]
+    }
+}
+
+impl DefMap {
+    pub fn root(&self) -> ModuleId {
+        self.root
+    }
+
+    pub fn prelude(&self) -> Option<ModuleId> {
+        self.prelude
+    }
+
+    /// The module the top-level items of `file` are defined in
+    pub fn file_module(&self, file: FileId) -> ModuleId {
+        self.file_modules.get(&file).copied().unwrap_or(self.root)
+    }
+
+    /// The type or class `name` defined by the prelude (if it is loaded), even if it is shadowed
+    pub fn prelude_type(&self, name: &str) -> Option<TypeNs> {
+        let &(res, _) = self.modules[self.prelude?].scope.types.get(&Symbol::new(name))?;
+        Some(res)
+    }
+
+    /// The value `name` defined by the prelude (if it is loaded), even if it is shadowed
+    pub fn prelude_value(&self, name: &str) -> Option<ValueNs> {
+        let &(res, _) = self.modules[self.prelude?].scope.values.get(&Symbol::new(name))?;
+        Some(res)
+    }
+
+    /// The definitions of `file`, or `None` if it was not collected
+    pub fn file_items(&self, file: FileId) -> Option<&FileItems> {
+        self.file_items.get(&file)
+    }
+
+    pub fn type_synonym(&self, id: TypeSynonymId) -> &TypeSynonymSource {
+        &self.type_synonyms[&id]
+    }
+
+    /// Errors found while collecting items and resolving imports
+    pub fn errors(&self) -> &[ResolveError] {
+        &self.errors
+    }
+
+    pub fn resolve_type_path(&self, module: ModuleId, path: &Path) -> Result<TypeNs, ResolveError> {
+        self.resolve_path(module, path, |scope| &scope.types)
+    }
+
+    pub fn resolve_value_path(
+        &self,
+        module: ModuleId,
+        path: &Path,
+    ) -> Result<ValueNs, ResolveError> {
+        let res = self.resolve_path(module, path, |scope| &scope.values);
+        // Primitives are in scope after the prelude
+        match (res, path.as_single()) {
+            (Err(err), Some(name)) => match cir::Prim::from_name(name.symbol.as_str()) {
+                Some(prim) => Ok(ValueNs::Prim(prim)),
+                None => Err(err),
+            },
+            (res, _) => res,
+        }
+    }
+
+    pub fn resolve_module_path<'a>(
+        &self,
+        module: ModuleId,
+        mut segments: impl Iterator<Item = &'a Name>,
+    ) -> Result<ModuleId, ResolveError> {
+        let first = match segments.next() {
+            Some(first) => first,
+            None => return Ok(module),
+        };
+        let mut module = self.lookup(module, first, |scope| &scope.modules).ok_or_else(|| {
+            ResolveError::new(first.span, ResolveErrorKind::UnresolvedModule(first.clone()))
+        })?;
+        for segment in segments {
+            module = match self.modules[module].scope.modules.get(&segment.symbol) {
+                Some(&(module, _)) => module,
+                None => {
+                    let kind = ResolveErrorKind::UnresolvedModule(segment.clone());
+                    return Err(ResolveError::new(segment.span, kind));
+                }
+            };
+        }
+        Ok(module)
+    }
+
+    fn resolve_path<T: Copy>(
+        &self,
+        module: ModuleId,
+        path: &Path,
+        ns: impl Fn(&Scope) -> &FxHashMap<Symbol, (T, Visibility)>,
+    ) -> Result<T, ResolveError> {
+        let unresolved =
+            || ResolveError::new(path.span(), ResolveErrorKind::UnresolvedPath(path.clone()));
+        if let Some(name) = path.as_single() {
+            return self.lookup(module, name, &ns).ok_or_else(|| {
+                match self.is_private_glob_import(module, name, &ns) {
+                    true => ResolveError::new(path.span(), ResolveErrorKind::Private(path.clone())),
+                    false => unresolved(),
+                }
+            });
+        }
+        let owner = self.resolve_module_path(module, path.qualifier())?;
+        let &(res, vis) =
+            ns(&self.modules[owner].scope).get(&path.last().symbol).ok_or_else(unresolved)?;
+        if !self.is_visible(module, owner, vis) {
+            return Err(ResolveError::new(path.span(), ResolveErrorKind::Private(path.clone())));
+        }
+        Ok(res)
+    }
+
+    /// Whether an item defined in `owner` with visibility `vis` can be accessed from `module`
+    pub fn is_visible(&self, module: ModuleId, owner: ModuleId, vis: Visibility) -> bool {
+        match vis {
+            Visibility::Public => true,
+            Visibility::Private => self.is_descendant(module, owner),
+        }
+    }
+
+    /// Whether `module` is `ancestor` or is nested within it
+    fn is_descendant(&self, module: ModuleId, ancestor: ModuleId) -> bool {
+        let mut module = Some(module);
+        while let Some(m) = module {
+            if m == ancestor {
+                return true;
+            }
+            module = self.modules[m].parent;
+        }
+        false
+    }
+
+    /// Lexical lookup of an unqualified name, searching the enclosing modules outwards and then
+    /// the prelude. Private items of enclosing modules are always visible.
+    fn lookup<T: Copy>(
+        &self,
+        module: ModuleId,
+        name: &Name,
+        ns: impl Fn(&Scope) -> &FxHashMap<Symbol, (T, Visibility)>,
+    ) -> Option<T> {
+        let mut scope = Some(module);
+        while let Some(m) = scope {
+            let data = &self.modules[m];
+            let found =
+                ns(&data.scope).get(&name.symbol).or_else(|| ns(&data.imports).get(&name.symbol));
+            if let Some(&(res, _)) = found {
+                return Some(res);
+            }
+            for &glob in &data.glob_imports {
+                match ns(&self.modules[glob].scope).get(&name.symbol) {
+                    Some(&(res, vis)) if self.is_visible(module, glob, vis) => return Some(res),
+                    _ => {}
+                }
+            }
+            scope = data.parent;
+        }
+        let prelude = self.prelude?;
+        match ns(&self.modules[prelude].scope).get(&name.symbol) {
+            Some(&(res, vis)) if self.is_visible(module, prelude, vis) => Some(res),
+            _ => None,
+        }
+    }
+
+    /// Whether `name` is a private item of a module that is glob imported by `module` or one of
+    /// its enclosing modules, so that `lookup` skipped it
+    fn is_private_glob_import<T>(
+        &self,
+        module: ModuleId,
+        name: &Name,
+        ns: impl Fn(&Scope) -> &FxHashMap<Symbol, (T, Visibility)>,
+    ) -> bool {
+        let mut scope = Some(module);
+        while let Some(m) = scope {
+            let data = &self.modules[m];
+            if data
+                .glob_imports
+                .iter()
+                .any(|&glob| ns(&self.modules[glob].scope).contains_key(&name.symbol))
+            {
+                return true;
+            }
+            scope = data.parent;
+        }
+        false
+    }
+}
+
+/// Resolves paths relative to a particular module
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    def_map: Arc<DefMap>,
+    module: ModuleId,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        let def_map = Arc::new(DefMap::default());
+        let module = def_map.root();
+        Self { def_map, module }
+    }
+}
+
+impl Resolver {
+    pub fn new(def_map: Arc<DefMap>, module: ModuleId) -> Self {
+        Self { def_map, module }
+    }
+
+    pub fn module(&self) -> ModuleId {
+        self.module
+    }
+
+    pub fn def_map(&self) -> &Arc<DefMap> {
+        &self.def_map
+    }
+
+    /// A resolver for the inline module `name` declared in the current module
+    pub fn child(&self, name: &Name) -> Option<Self> {
+        let &(module, _) = self.def_map[self.module].scope.modules.get(&name.symbol)?;
+        Some(Self { def_map: Arc::clone(&self.def_map), module })
+    }
+
+    pub fn resolve_type_path(&self, path: &Path) -> Result<TypeNs, ResolveError> {
+        self.def_map.resolve_type_path(self.module, path)
+    }
+
+    pub fn resolve_value_path(&self, path: &Path) -> Result<ValueNs, ResolveError> {
+        self.def_map.resolve_value_path(self.module, path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolveError {
+    pub span: Span,
+    pub kind: ResolveErrorKind,
+}
+
+impl ResolveError {
+    pub fn new(span: Span, kind: ResolveErrorKind) -> Self {
+        Self { span, kind }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveErrorKind {
+    UnresolvedPath(Path),
+    UnresolvedModule(Name),
+    /// A path to an item that is not visible from the current module
+    Private(Path),
+    /// A pattern that refers to a value rather than a constructor
+    NotAConstructor(Path),
+    UnboundTyVar(Name),
+    Duplicate(Name),
+    /// A field that occurs more than once in a record type or expression
+    DuplicateField(Name),
+    /// A `module M;` declaration with no corresponding file
+    MissingModuleFile(Name, PathBuf),
+    /// A use of a type synonym whose expansion refers to itself
+    CyclicTypeSynonym(Name),
+    /// A constructor signature whose result is not the data type being defined, e.g. `C : Int`
+    InvalidCtorResult(Name, Name),
+    /// A parameter of a data definition that is used in a constructor's signature without being
+    /// an argument of its result type, e.g. `a` in `C : a -> Expr Int`
+    UndeterminedTyParam(Name, Name),
+    /// A class used as a type, e.g. `Eq -> Int`
+    ClassAsType(Path),
+    /// A type used as the class of a constraint or an instance, e.g. `Int a => a`
+    NotAClass(Path),
+    /// An integer literal that is not in the range of `Int`
+    IntOutOfRange,
+    /// An instance that is not of the form `forall a b. C1 T1 => C T`
+    InvalidInstanceHead,
+    /// A class in `deriving (...)` that is not `Eq` or `Show`, or any class for a data type
+    /// whose constructors have signatures or whose fields have types without instances, e.g.
+    /// tuples
+    NotDerivable(Path, Name),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ResolveErrorKind::UnresolvedPath(path) => write!(f, "unresolved path `{}`", path),
+            ResolveErrorKind::Private(path) => write!(f, "`{}` is private", path),
+            ResolveErrorKind::NotAConstructor(path) => write!(f, "`{}` is not a constructor", path),
+            ResolveErrorKind::UnresolvedModule(name) =>
+                write!(f, "unresolved module `{}`", name.symbol.as_str()),
+            ResolveErrorKind::UnboundTyVar(name) =>
+                write!(f, "unbound type variable `{}`", name.symbol.as_str()),
+            ResolveErrorKind::Duplicate(name) =>
+                write!(f, "`{}` is defined multiple times", name.symbol.as_str()),
+            ResolveErrorKind::DuplicateField(name) =>
+                write!(f, "field `{}` is specified more than once", name.symbol.as_str()),
+            ResolveErrorKind::CyclicTypeSynonym(name) =>
+                write!(f, "type synonym `{}` is defined in terms of itself", name.symbol.as_str()),
+            ResolveErrorKind::InvalidCtorResult(ctor, data) => write!(
+                f,
+                "the signature of `{}` must construct a `{}`",
+                ctor.symbol.as_str(),
+                data.symbol.as_str()
+            ),
+            ResolveErrorKind::UndeterminedTyParam(var, ctor) => write!(
+                f,
+                "`{}` must be an argument of the result type of `{}` (or bound by a `forall`)",
+                var.symbol.as_str(),
+                ctor.symbol.as_str()
+            ),
+            ResolveErrorKind::ClassAsType(path) => write!(f, "`{}` is a class, not a type", path),
+            ResolveErrorKind::NotAClass(path) => write!(f, "`{}` is not a class", path),
+            ResolveErrorKind::IntOutOfRange =>
+                write!(f, "integer literal is out of range for `Int`"),
+            ResolveErrorKind::InvalidInstanceHead =>
+                write!(f, "an instance must be a class applied to a type"),
+            ResolveErrorKind::NotDerivable(class, data) =>
+                write!(f, "`{}` cannot be derived for `{}`", class, data.symbol.as_str()),
+            ResolveErrorKind::MissingModuleFile(name, path) => write!(
+                f,
+                "file for module `{}` not found (expected `{}`)",
+                name.symbol.as_str(),
+                path.display()
+            ),
+        }
+    }
+}
+
+pub(crate) fn def_map(db: &dyn LowerDatabase) -> Arc<DefMap> {
+    let files = db.files();
+    let paths = files.iter().map(|&file| (db.file_path(file).to_path_buf(), file)).collect();
+    let mut collector = DefCollector {
+        db,
+        paths,
+        def_map: DefMap::default(),
+        visited: Default::default(),
+        imports: Default::default(),
+    };
+
+    let mut module_files = FxHashSet::default();
+    for &file in files.iter() {
+        if let Ok(source_file) = db.parse(file) {
+            collector.module_files(file, &source_file.items, &mut module_files);
+        }
+    }
+
+    let prelude_file =
+        files.iter().copied().find(|&file| db.file_path(file).as_os_str() == PRELUDE_PATH);
+    if let Some(file) = prelude_file {
+        // The prelude has no parent so that it cannot see the items of the root module
+        let name = Name::new(Span::new(file, 0, 0), "Prelude");
+        let prelude =
+            collector.def_map.modules.alloc(ModuleData { name: Some(name), ..Default::default() });
+        collector.def_map.prelude = Some(prelude);
+        module_files.insert(file);
+        collector.collect_file(file, prelude);
+    }
+
+    let root = collector.def_map.root;
+    for &file in files.iter().filter(|file| !module_files.contains(file)) {
+        collector.collect_file(file, root);
+    }
+    if let Some(prelude) = collector.def_map.prelude {
+        // Allow `Prelude.x`, unless the program defines its own `Prelude`
+        let modules = &mut collector.def_map.modules[root].scope.modules;
+        modules.entry(Symbol::new("Prelude")).or_insert((prelude, Visibility::Public));
+    }
+    collector.resolve_imports();
+    Arc::new(collector.def_map)
+}
+
+struct DefCollector<'db> {
+    db: &'db dyn LowerDatabase,
+    paths: FxHashMap<PathBuf, FileId>,
+    def_map: DefMap,
+    visited: FxHashSet<FileId>,
+    imports: Vec<(ModuleId, Path)>,
+}
+
+impl DefCollector<'_> {
+    /// The file containing the items of the file module `name` declared in `file`
+    fn module_file_path(&self, file: FileId, name: &Name) -> PathBuf {
+        self.db.file_path(file).with_file_name(format!("{}.cir", name.symbol.as_str()))
+    }
+
+    /// Finds all files that are declared as modules, these do not contribute to the root module
+    fn module_files(&self, file: FileId, items: &[ast::Item], acc: &mut FxHashSet<FileId>) {
+        for item in items {
+            if let ast::ItemKind::Module(module) = &item.kind {
+                match &module.kind {
+                    ast::ModuleKind::Inline(items) => self.module_files(file, items, acc),
+                    ast::ModuleKind::File => {
+                        let path = self.module_file_path(file, &module.name);
+                        acc.extend(self.paths.get(&path).copied());
+                    }
+                }
+            }
+        }
+    }
+
+    fn collect_file(&mut self, file: FileId, module: ModuleId) {
+        if !self.visited.insert(file) {
+            return;
+        }
+        self.def_map.file_modules.insert(file, module);
+        let mut file_items = FileItems::default();
+        if let Ok(source_file) = self.db.parse(file) {
+            self.collect_items(file, &source_file.items, module, &mut file_items);
+        }
+        self.def_map.file_items.insert(file, file_items);
+    }
+
+    fn collect_items(
+        &mut self,
+        file: FileId,
+        items: &[ast::Item],
+        module: ModuleId,
+        file_items: &mut FileItems,
+    ) {
+        for item in items {
+            match &item.kind {
+                ast::ItemKind::ValueDef(value_def) => {
+                    let def = Idx::from_raw(RawIdx::from(file_items.value_defs.len() as u32));
+                    file_items.value_defs.push(value_def.name.clone());
+                    let res = ValueNs::ValueDef(ValueDefId { file, def });
+                    self.define(module, &value_def.name, res, value_def.vis, |scope| {
+                        &mut scope.values
+                    });
+                }
+                ast::ItemKind::DataDef(data_def) => {
+                    let def = Idx::from_raw(RawIdx::from(file_items.data_defs.len() as u32));
+                    file_items.data_defs.push(data_def.name.clone());
+                    let data_def_id = DataDefId { file, def };
+                    let res = TypeNs::DataDef(data_def_id);
+                    self.define(module, &data_def.name, res, data_def.vis, |scope| {
+                        &mut scope.types
+                    });
+                    for (i, variant) in data_def.variants.iter().enumerate() {
+                        let variant_id = VariantId {
+                            data_def: data_def_id,
+                            variant: Idx::from_raw(RawIdx::from(i as u32)),
+                        };
+                        let res = ValueNs::Variant(variant_id);
+                        self.define(module, &variant.name, res, variant.vis, |scope| {
+                            &mut scope.values
+                        });
+                    }
+                }
+                ast::ItemKind::TypeSynonym(type_synonym) => {
+                    let def = Idx::from_raw(RawIdx::from(file_items.type_synonyms.len() as u32));
+                    file_items.type_synonyms.push(type_synonym.name.clone());
+                    let id = TypeSynonymId { file, def };
+                    let res = TypeNs::TypeSynonym(id);
+                    self.define(module, &type_synonym.name, res, type_synonym.vis, |scope| {
+                        &mut scope.types
+                    });
+                    let source = TypeSynonymSource {
+                        module,
+                        params: type_synonym.params.clone(),
+                        ty: type_synonym.ty.clone(),
+                    };
+                    self.def_map.type_synonyms.insert(id, source);
+                }
+                ast::ItemKind::Class(class) => {
+                    let def = Idx::from_raw(RawIdx::from(file_items.classes.len() as u32));
+                    file_items.classes.push(class.name.clone());
+                    let class_id = ClassId { file, def };
+                    let res = TypeNs::Class(class_id);
+                    self.define(module, &class.name, res, class.vis, |scope| &mut scope.types);
+                    for (index, (name, _)) in class.methods.iter().enumerate() {
+                        let res = ValueNs::Method(MethodId { class: class_id, index });
+                        self.define(module, name, res, class.vis, |scope| &mut scope.values);
+                    }
+                }
+                // Instances are not named, they are found by the typechecker
+                ast::ItemKind::Instance(_) => {}
+                ast::ItemKind::Module(ast::Module { name, kind }) => {
+                    let child = self.def_map.modules.alloc(ModuleData {
+                        name: Some(name.clone()),
+                        parent: Some(module),
+                        ..Default::default()
+                    });
+                    self.define(module, name, child, Visibility::Public, |scope| {
+                        &mut scope.modules
+                    });
+                    match kind {
+                        ast::ModuleKind::Inline(items) =>
+                            self.collect_items(file, items, child, file_items),
+                        ast::ModuleKind::File => {
+                            let path = self.module_file_path(file, name);
+                            match self.paths.get(&path) {
+                                Some(&module_file) => self.collect_file(module_file, child),
+                                None => self.def_map.errors.push(ResolveError::new(
+                                    name.span,
+                                    ResolveErrorKind::MissingModuleFile(name.clone(), path),
+                                )),
+                            }
+                        }
+                    }
+                }
+                ast::ItemKind::Import(import) => self.imports.push((module, import.path.clone())),
+            }
+        }
+    }
+
+    fn define<T>(
+        &mut self,
+        module: ModuleId,
+        name: &Name,
+        res: T,
+        vis: Visibility,
+        ns: impl FnOnce(&mut Scope) -> &mut FxHashMap<Symbol, (T, Visibility)>,
+    ) {
+        let scope = ns(&mut self.def_map.modules[module].scope);
+        if scope.insert(name.symbol.clone(), (res, vis)).is_some() {
+            self.def_map
+                .errors
+                .push(ResolveError::new(name.span, ResolveErrorKind::Duplicate(name.clone())));
+        }
+    }
+
+    fn resolve_imports(&mut self) {
+        for (module, path) in std::mem::take(&mut self.imports) {
+            if let Ok(imported) = self.def_map.resolve_module_path(module, path.names()) {
+                self.def_map.modules[module].glob_imports.push(imported);
+                continue;
+            }
+
+            let name = path.last().symbol.clone();
+            let ty = self.def_map.resolve_type_path(module, &path);
+            let value = self.def_map.resolve_value_path(module, &path);
+            let (ty, value) = match (ty, value) {
+                (Err(ty_err), Err(value_err)) => {
+                    // report the item as private if either namespace found it
+                    let err = match (&ty_err.kind, &value_err.kind) {
+                        (ResolveErrorKind::Private(_), _) => ty_err,
+                        (_, ResolveErrorKind::Private(_)) => value_err,
+                        _ => ResolveError::new(path.span(), ResolveErrorKind::UnresolvedPath(path)),
+                    };
+                    self.def_map.errors.push(err);
+                    continue;
+                }
+                (ty, value) => (ty, value),
+            };
+
+            // imports are never re-exported, so they are private to the importing module
+            let imports = &mut self.def_map.modules[module].imports;
+            if let Ok(ty) = ty {
+                imports.types.insert(name.clone(), (ty, Visibility::Private));
+            }
+            if let Ok(value) = value {
+                imports.values.insert(name, (value, Visibility::Private));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,393 @@
+use cir::db::{self, SourceDatabaseStorage};
+use cir::{ExprData, FileId, Item, SourceMap, TyKind};
+
+use crate::{LowerDatabase, LowerDatabaseStorage};
+
+#[salsa::database(SourceDatabaseStorage, LowerDatabaseStorage)]
+#[derive(Default)]
+struct TestDatabase {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for TestDatabase {
+}
+
+fn db(files: &[(&str, &str)]) -> (TestDatabase, Vec<FileId>) {
+    db_with_source_map(SourceMap::default(), files)
+}
+
+fn db_with_source_map(
+    mut source_map: SourceMap,
+    files: &[(&str, &str)],
+) -> (TestDatabase, Vec<FileId>) {
+    let mut db = TestDatabase::default();
+    let files = files.iter().map(|&(name, text)| source_map.add(name, text)).collect();
+    db::set_source_map(&mut db, &source_map);
+    (db, files)
+}
+
+/// The top-level expression of the body of the value def named `name`
+fn body_expr(db: &TestDatabase, file: FileId, name: &str) -> ExprData {
+    let items = db.lower_file(file).unwrap();
+    let (_, def) = items.value_defs.iter().find(|(_, def)| def.name.symbol == name).unwrap();
+    let body = &items[def.body];
+    body[body.expr].clone()
+}
+
+fn errors(db: &TestDatabase, file: FileId) -> Vec<String> {
+    let (_, errors) = db.lower_file_with_errors(file).unwrap();
+    errors.iter().map(|err| err.to_string()).collect()
+}
+
+#[test]
+fn test_resolve_top_level_value() {
+    let (db, files) = db(&[("main.cir", "let x: Int = 1; let y: Int = x;")]);
+    let items = db.lower_file(files[0]).unwrap();
+    let x = match items.items[0] {
+        Item::ValueDef(def) => def,
+        _ => panic!(),
+    };
+    assert_eq!(
+        body_expr(&db, files[0], "y"),
+        ExprData::Def(cir::ValueDefId { file: files[0], def: x })
+    );
+}
+
+#[test]
+fn test_resolve_qualified_paths_in_inline_module() {
+    let src = r#"
+        module List {
+            pub let map: Int = 0;
+            pub data Maybe = pub Nothing | pub Just Int;
+        }
+        let y: Int = List.map;
+        let m: List.Maybe = List.Nothing;
+    "#;
+    let (db, files) = db(&[("main.cir", src)]);
+    assert!(errors(&db, files[0]).is_empty());
+    assert!(matches!(body_expr(&db, files[0], "y"), ExprData::Def(_)));
+    assert!(matches!(body_expr(&db, files[0], "m"), ExprData::Ctor(_)));
+
+    let items = db.lower_file(files[0]).unwrap();
+    let (_, m) = items.value_defs.iter().find(|(_, def)| def.name.symbol == "m").unwrap();
+    assert!(matches!(m.ty.as_ref().unwrap().kind(), TyKind::Adt(_, _)));
+}
+
+#[test]
+fn test_resolve_inner_module_sees_outer_items() {
+    let src = "let x: Int = 1; module M { let y: Int = x; }";
+    let (db, files) = db(&[("main.cir", src)]);
+    assert!(matches!(body_expr(&db, files[0], "y"), ExprData::Def(_)));
+}
+
+#[test]
+fn test_resolve_imports() {
+    let src = r#"
+        module M { pub let x: Int = 1; pub let z: Int = 2; }
+        module N { import M; let y: Int = x; }
+        module O { import M.z; let w: Int = z; }
+    "#;
+    let (db, files) = db(&[("main.cir", src)]);
+    assert!(errors(&db, files[0]).is_empty());
+    assert!(matches!(body_expr(&db, files[0], "y"), ExprData::Def(_)));
+    assert!(matches!(body_expr(&db, files[0], "w"), ExprData::Def(_)));
+}
+
+#[test]
+fn test_resolve_file_module() {
+    let (db, files) = db(&[
+        ("src/main.cir", "module List; let y: Int = List.map;"),
+        ("src/List.cir", "pub let map: Int = 0;"),
+    ]);
+    let def_map = db.def_map();
+    assert_ne!(def_map.file_module(files[1]), def_map.root());
+    assert!(errors(&db, files[0]).is_empty());
+    assert_eq!(
+        body_expr(&db, files[0], "y"),
+        ExprData::Def(cir::ValueDefId {
+            file: files[1],
+            def: db.lower_file(files[1]).unwrap().value_defs.iter().next().unwrap().0
+        })
+    );
+}
+
+#[test]
+fn test_resolve_across_root_files() {
+    let (db, files) = db(&[("a.cir", "let x: Int = 1;"), ("b.cir", "let y: Int = x;")]);
+    assert!(matches!(body_expr(&db, files[1], "y"), ExprData::Def(def) if def.file == files[0]));
+}
+
+#[test]
+fn test_resolve_errors() {
+    let src = "module M; let y: Int = Foo.bar; let z: Int = M.nope; let w: Nope = 0; let x: Int = 0; let x: Int = 1;";
+    let (db, files) = db(&[("main.cir", src)]);
+    assert_eq!(body_expr(&db, files[0], "y"), ExprData::Err);
+    let errors = errors(&db, files[0]);
+    assert_eq!(
+        errors,
+        [
+            "file for module `M` not found (expected `M.cir`)",
+            "`x` is defined multiple times",
+            "unresolved module `Foo`",
+            "unresolved path `M.nope`",
+            "unresolved path `Nope`",
+        ]
+    );
+}
+
+#[test]
+fn test_resolve_type_synonyms() {
+    let src = r#"
+        let p: Pair Int Bool = 0;
+        type Pair a b = forall r. (a -> b -> r) -> r;
+        module M {
+            pub type Twice f a = f (f a);
+            pub type Endo a = a -> a;
+        }
+        let q: forall a. M.Twice M.Endo a = 0;
+        let r: M.Twice (Pair Int) Bool = 0;
+    "#;
+    let (db, files) = db(&[("main.cir", src)]);
+    assert!(errors(&db, files[0]).is_empty());
+    let items = db.lower_file(files[0]).unwrap();
+    let ty = |name: &str| {
+        let (_, def) = items.value_defs.iter().find(|(_, def)| def.name.symbol == name).unwrap();
+        def.ty.clone().unwrap()
+    };
+    assert_eq!(ty("p"), crate::parse_ty("forall r. (Int -> Bool -> r) -> r"));
+    // Partially applied synonyms are type operators, which are applied when types are normalized
+    assert_eq!(ty("q"), crate::parse_ty("forall a. (\\a. a -> a) ((\\a. a -> a) a)"));
+    assert_eq!(
+        ty("r"),
+        crate::parse_ty(
+            "(\\b. forall r. (Int -> b -> r) -> r) ((\\b. forall r. (Int -> b -> r) -> r) Bool)"
+        )
+    );
+    assert_eq!(items.type_synonyms.len(), 3);
+}
+
+#[test]
+fn test_resolve_cyclic_type_synonyms() {
+    let src = r#"
+        type A = B -> Int;
+        type B = List A;
+        type C = C;
+        data List a = Nil | Cons a (List a);
+        type D = List B;
+        let x: A = 0;
+    "#;
+    let (db, files) = db(&[("main.cir", src)]);
+    assert_eq!(
+        errors(&db, files[0]),
+        [
+            "type synonym `B` is defined in terms of itself",
+            "type synonym `A` is defined in terms of itself",
+            "type synonym `C` is defined in terms of itself",
+            "type synonym `B` is defined in terms of itself",
+            "type synonym `A` is defined in terms of itself",
+        ]
+    );
+}
+
+#[test]
+fn test_resolve_ctor_sigs() {
+    let src = r#"
+        data Expr a where
+            IntE : Int -> Expr Int;
+            Pair : forall b. forall c. Expr b -> Expr c -> Expr (b, c);
+            Bad : Int -> Int;
+            Partial : Expr;
+            Unbound : b -> Expr a;
+            Hidden : a -> Expr Int;
+        data Other = Other;
+        data Wrong a where W : Other;
+    "#;
+    let (db, files) = db(&[("main.cir", src)]);
+    assert_eq!(
+        errors(&db, files[0]),
+        [
+            "the signature of `Bad` must construct a `Expr`",
+            "the signature of `Partial` must construct a `Expr`",
+            "unbound type variable `b`",
+            "`a` must be an argument of the result type of `Hidden` (or bound by a `forall`)",
+            "the signature of `W` must construct a `Wrong`",
+        ]
+    );
+}
+
+#[test]
+fn test_resolve_private_items() {
+    let src = r#"
+        module M {
+            let secret: Int = 1;
+            pub data Abstract = Mk Int;
+            pub let public: Int = secret;
+            module Inner { let y: Int = M.secret; }
+        }
+        let a: Int = M.secret;
+        let b: M.Abstract = M.Mk;
+        let c: Int = M.public;
+    "#;
+    let (db, files) = db(&[("main.cir", src)]);
+    assert!(matches!(body_expr(&db, files[0], "public"), ExprData::Def(_)));
+    assert!(matches!(body_expr(&db, files[0], "y"), ExprData::Def(_)));
+    assert_eq!(body_expr(&db, files[0], "a"), ExprData::Err);
+    assert_eq!(body_expr(&db, files[0], "b"), ExprData::Err);
+    assert!(matches!(body_expr(&db, files[0], "c"), ExprData::Def(_)));
+    assert_eq!(errors(&db, files[0]), ["`M.secret` is private", "`M.Mk` is private"]);
+}
+
+#[test]
+fn test_resolve_imports_respect_visibility() {
+    let src = r#"
+        module M { let x: Int = 1; pub let z: Int = 2; pub data T = A | pub B; }
+        module N { import M; let y: Int = x; let w: Int = z; let a: T = A; let b: T = B; }
+        import M.x;
+    "#;
+    let (db, files) = db(&[("main.cir", src)]);
+    assert_eq!(body_expr(&db, files[0], "y"), ExprData::Err);
+    assert!(matches!(body_expr(&db, files[0], "w"), ExprData::Def(_)));
+    assert_eq!(body_expr(&db, files[0], "a"), ExprData::Err);
+    assert!(matches!(body_expr(&db, files[0], "b"), ExprData::Ctor(_)));
+    assert_eq!(errors(&db, files[0]), ["`M.x` is private", "`x` is private", "`A` is private"]);
+}
+
+#[test]
+fn test_resolve_private_type_in_public_signature() {
+    // Visibility only restricts naming an item, `N` can use `x` without naming its type
+    let src = r#"
+        module M { data T = A; pub let x: T = A; pub let f: T -> Int = \t:T. 0; }
+        module N { import M; let y: Int = f x; }
+    "#;
+    let (db, files) = db(&[("main.cir", src)]);
+    assert!(errors(&db, files[0]).is_empty());
+    assert!(matches!(body_expr(&db, files[0], "y"), ExprData::App(..)));
+}
+
+#[test]
+fn test_resolve_prelude() {
+    let mut source_map = SourceMap::default();
+    let prelude = crate::add_prelude(&mut source_map);
+    let src = r#"
+        let m: Maybe Int = Nothing;
+        let xs: Int = Prelude.map;
+        data List = Nil;
+        let l: List = Nil;
+        module M { let y: Int = id; }
+    "#;
+    let (db, files) = db_with_source_map(source_map, &[("main.cir", src)]);
+    let def_map = db.def_map();
+    assert_eq!(def_map.file_module(prelude), def_map.prelude().unwrap());
+    assert!(errors(&db, prelude).is_empty());
+    assert!(errors(&db, files[0]).is_empty());
+    assert!(
+        matches!(body_expr(&db, files[0], "m"), ExprData::Ctor(v) if v.data_def.file == prelude)
+    );
+    assert!(matches!(body_expr(&db, files[0], "xs"), ExprData::Def(def) if def.file == prelude));
+    assert!(matches!(body_expr(&db, files[0], "y"), ExprData::Def(def) if def.file == prelude));
+    // Definitions in the program shadow the prelude
+    assert!(
+        matches!(body_expr(&db, files[0], "l"), ExprData::Ctor(v) if v.data_def.file == files[0])
+    );
+}
+
+#[test]
+fn test_resolve_deriving() {
+    let mut source_map = SourceMap::default();
+    let prelude = crate::add_prelude(&mut source_map);
+    let src = r#"
+        class Ord a where lt : a -> a -> Bool;
+        data T a = A | B a (T a) deriving (Eq, Show);
+        data U = U deriving (Ord);
+        data E a where I : Int -> E Int deriving (Eq);
+        data V = V deriving (U);
+        data P = P (Int, Bool) deriving (Eq);
+        data Q = Q () deriving (Show);
+        data R = R { x: Int } deriving (Show);
+        data F = F (List (Int -> Int)) deriving (Eq);
+    "#;
+    let (db, files) = db_with_source_map(source_map, &[("main.cir", src)]);
+    let items = db.lower_file(files[0]).unwrap();
+    let instances = items.instances.iter().map(|(_, instance)| instance).collect::<Vec<_>>();
+    assert_eq!(instances.len(), 2);
+    for instance in instances {
+        assert_eq!(instance.class.file, prelude);
+        assert_eq!(instance.params.len(), 1);
+        assert_eq!(instance.context.len(), 1);
+        assert!(matches!(instance.head.kind(), TyKind::Adt(..)));
+    }
+    assert_eq!(
+        errors(&db, files[0]),
+        [
+            "`Ord` cannot be derived for `U`",
+            "`Eq` cannot be derived for `E`",
+            "`U` is not a class",
+            "`Eq` cannot be derived for `P`",
+            "`Show` cannot be derived for `Q`",
+            "`Show` cannot be derived for `R`",
+            "`Eq` cannot be derived for `F`",
+        ]
+    );
+}
+
+#[test]
+fn test_resolve_deriving_without_show_parens() {
+    let mut source_map = SourceMap::default();
+    let prelude = "pub class Show a where show : a -> String;";
+    source_map.add(crate::prelude::PRELUDE_PATH, prelude);
+    let (db, files) =
+        db_with_source_map(source_map, &[("main.cir", "data T = T deriving (Show);")]);
+    assert_eq!(db.lower_file(files[0]).unwrap().instances.len(), 0);
+    assert_eq!(errors(&db, files[0]), ["`Show` cannot be derived for `T`"]);
+}
+
+#[test]
+fn test_resolve_deriving_eq_is_linear() {
+    let mut source_map = SourceMap::default();
+    crate::add_prelude(&mut source_map);
+    let src = "data T = A | B | C | D | E | F | G | H deriving (Eq);";
+    let (db, files) = db_with_source_map(source_map, &[("main.cir", src)]);
+    let items = db.lower_file(files[0]).unwrap();
+    let (_, instance) = items.instances.iter().next().unwrap();
+    let body = &items[instance.body];
+    let alts = body
+        .exprs
+        .iter()
+        .map(|(_, expr)| match expr {
+            ExprData::Case(_, alts) => alts.len(),
+            _ => 0,
+        })
+        .sum::<usize>();
+    // An alternative per constructor to match `x`, `y` and the tags of both
+    assert_eq!(alts, 4 * 8);
+}
+
+#[test]
+fn test_resolve_classes() {
+    let src = r#"
+        data List a = Nil | Cons a (List a);
+        module M { pub class Eq a where eq : a -> a -> Bool; }
+        import M;
+        instance forall a. Eq a => Eq (List a) where eq = \xs:List a.\ys:List a. true;
+        let e: forall a. Eq a => a -> a -> Bool = eq;
+        let t: Eq -> Int = 0;
+        let u: forall a. List a => a = 0;
+        instance Int where eq = 0;
+    "#;
+    let (db, files) = db(&[("main.cir", src)]);
+    let items = db.lower_file(files[0]).unwrap();
+    assert_eq!(items.classes.len(), 1);
+    let (_, instance) = items.instances.iter().next().unwrap();
+    assert_eq!(instance.params.len(), 1);
+    assert_eq!(instance.context.len(), 1);
+    assert!(matches!(instance.head.kind(), TyKind::Adt(..)));
+    assert!(matches!(body_expr(&db, files[0], "e"), ExprData::Method(method) if method.index == 0));
+    assert_eq!(
+        errors(&db, files[0]),
+        [
+            "`Eq` is a class, not a type",
+            "`List` is not a class",
+            "an instance must be a class applied to a type"
+        ]
+    );
+}
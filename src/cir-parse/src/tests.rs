@@ -1,47 +1,114 @@
 use super::*;
 use expect_test::expect_file;
 
+const FILE: FileId = FileId(0);
+
 #[test]
 fn test_parse_lname() -> anyhow::Result<()> {
-    assert_eq!(cirparser::lname("x")?.symbol, "x");
-    assert_eq!(cirparser::lname("lowerIdent")?.symbol, "lowerIdent");
-    assert_eq!(cirparser::lname("lower123")?.symbol, "lower123");
-    assert_eq!(cirparser::lname("_lower")?.symbol, "_lower");
-    assert!(cirparser::lname("Upper123").is_err());
+    assert_eq!(cirparser::lname("x", FILE)?.symbol, "x");
+    assert_eq!(cirparser::lname("lowerIdent", FILE)?.symbol, "lowerIdent");
+    assert_eq!(cirparser::lname("lower123", FILE)?.symbol, "lower123");
+    assert_eq!(cirparser::lname("_lower", FILE)?.symbol, "_lower");
+    assert!(cirparser::lname("Upper123", FILE).is_err());
+    for keyword in KEYWORDS {
+        assert!(cirparser::lname(keyword, FILE).is_err(), "{}", keyword);
+    }
+    // Words that start with a keyword are names
+    assert_eq!(cirparser::lname("instances", FILE)?.symbol, "instances");
+    assert_eq!(cirparser::lname("iffy", FILE)?.symbol, "iffy");
+    Ok(())
+}
+
+#[test]
+fn test_parse_name_symbols_are_interned() -> anyhow::Result<()> {
+    let x = cirparser::lname("x", FILE)?;
+    let y = cirparser::lname("y", FILE)?;
+    let x2 = cirparser::expr("  x", FILE)?;
+    assert_ne!(x.symbol, y.symbol);
+    match x2 {
+        Expr::Var(Var::Val { name }) => {
+            assert_ne!(name.span, x.span);
+            assert!(std::sync::Arc::ptr_eq(&name.symbol, &x.symbol));
+        }
+        _ => panic!(),
+    }
     Ok(())
 }
 
 #[test]
 fn test_parse_uname() -> anyhow::Result<()> {
-    assert_eq!(cirparser::uname("X")?.symbol, "X");
-    assert_eq!(cirparser::uname("UpperIdent")?.symbol, "UpperIdent");
-    assert_eq!(cirparser::uname("Upper123")?.symbol, "Upper123");
-    assert!(cirparser::uname("lower123").is_err());
+    assert_eq!(cirparser::uname("X", FILE)?.symbol, "X");
+    assert_eq!(cirparser::uname("UpperIdent", FILE)?.symbol, "UpperIdent");
+    assert_eq!(cirparser::uname("Upper123", FILE)?.symbol, "Upper123");
+    assert!(cirparser::uname("lower123", FILE).is_err());
     Ok(())
 }
 
 #[test]
 fn test_parse_lit() -> anyhow::Result<()> {
     assert_eq!(
-        cirparser::literal(" false ")?,
-        Literal { span: Span::new(1, 6), kind: LiteralKind::Bool(false) }
+        cirparser::literal(" false ", FILE)?,
+        Literal { span: Span::new(FILE, 1, 6), kind: LiteralKind::Bool(false) }
+    );
+    assert_eq!(
+        cirparser::literal("  true ", FILE)?,
+        Literal { span: Span::new(FILE, 2, 6), kind: LiteralKind::Bool(true) }
     );
     assert_eq!(
-        cirparser::literal("  true ")?,
-        Literal { span: Span::new(2, 6), kind: LiteralKind::Bool(true) }
+        cirparser::literal(" -288 ", FILE)?,
+        Literal { span: Span::new(FILE, 1, 5), kind: LiteralKind::Int(-288) }
     );
     assert_eq!(
-        cirparser::literal(" -288 ")?,
-        Literal { span: Span::new(1, 5), kind: LiteralKind::Int(-288) }
+        cirparser::literal(" 2.75 ", FILE)?,
+        Literal { span: Span::new(FILE, 1, 5), kind: LiteralKind::Float(cir::Float::new(2.75)) }
     );
+    assert_eq!(cirparser::literal("1e-3", FILE)?.kind, LiteralKind::Float(cir::Float::new(1e-3)));
+    assert_eq!(
+        cirparser::literal("-2.5E2", FILE)?.kind,
+        LiteralKind::Float(cir::Float::new(-250.0))
+    );
+    assert!(cirparser::literal("1.", FILE).is_err());
+    // Too large for any literal, the range of `Int` is checked during lowering
+    assert!(cirparser::literal("1000000000000000000000000000000000000000", FILE).is_err());
+    assert!(cirparser::expr("1000000000000000000000000000000000000000", FILE).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_parse_string_and_char_lit() -> anyhow::Result<()> {
+    assert_eq!(
+        cirparser::literal(r#" "hello" "#, FILE)?,
+        Literal { span: Span::new(FILE, 1, 8), kind: LiteralKind::String("hello".into()) }
+    );
+    assert_eq!(
+        cirparser::literal(r#""a\tb\n\"c\"\\\u{1F600}""#, FILE)?.kind,
+        LiteralKind::String("a\tb\n\"c\"\\\u{1F600}".into())
+    );
+    assert_eq!(cirparser::literal(r#""""#, FILE)?.kind, LiteralKind::String("".into()));
+    assert_eq!(cirparser::literal("'x'", FILE)?.kind, LiteralKind::Char('x'));
+    assert_eq!(cirparser::literal(r"'\''", FILE)?.kind, LiteralKind::Char('\''));
+    assert_eq!(cirparser::literal(r"'\0'", FILE)?.kind, LiteralKind::Char('\0'));
+    assert!(cirparser::literal(r#""unterminated"#, FILE).is_err());
+    assert!(cirparser::literal(r#""\q""#, FILE).is_err());
+    assert!(cirparser::literal(r"'\u{D800}'", FILE).is_err());
+    assert!(cirparser::literal("'ab'", FILE).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_parse_float_ty() -> anyhow::Result<()> {
+    assert_eq!(cirparser::ty("Float", FILE)?, Type::Scalar(cir::Scalar::Float));
+    assert!(matches!(cirparser::ty("Floaty", FILE)?, Type::Path(_)));
+    assert_eq!(cirparser::ty("Char", FILE)?, Type::Scalar(cir::Scalar::Char));
+    assert_eq!(cirparser::ty("String", FILE)?, Type::Scalar(cir::Scalar::String));
     Ok(())
 }
 
 #[test]
 fn test_parse_expr_lit() -> anyhow::Result<()> {
     assert_eq!(
-        cirparser::expr("x")?,
-        Expr::Var(Var::Val { name: Name { span: Span::new(0, 1), symbol: "x".into() } })
+        cirparser::expr("x", FILE)?,
+        Expr::Var(Var::Val { name: Name { span: Span::new(FILE, 0, 1), symbol: "x".into() } })
     );
     Ok(())
 }
@@ -49,87 +116,136 @@ fn test_parse_expr_lit() -> anyhow::Result<()> {
 #[test]
 fn test_parse_expr_group() -> anyhow::Result<()> {
     expect_file!["tests/expect/expr/group.ast"]
-        .assert_debug_eq(&cirparser::expr("(\\x: a. x) (\\y: b.  y)")?);
+        .assert_debug_eq(&cirparser::expr("(\\x: a. x) (\\y: b.  y)", FILE)?);
     Ok(())
 }
 
 #[test]
 fn test_parse_expr_app() -> anyhow::Result<()> {
-    expect_file!["tests/expect/expr/app.ast"].assert_debug_eq(&cirparser::expr("f x")?);
+    expect_file!["tests/expect/expr/app.ast"].assert_debug_eq(&cirparser::expr("f x", FILE)?);
     expect_file!["tests/expect/expr/app-left-assoc.ast"]
-        .assert_debug_eq(&cirparser::expr("f x y")?);
+        .assert_debug_eq(&cirparser::expr("f x y", FILE)?);
     expect_file!["tests/expect/expr/lambda-app.ast"]
-        .assert_debug_eq(&cirparser::expr("(\\x: a. x) y")?);
-    assert_ne!(cirparser::expr("(\\x: b. x) y")?, cirparser::expr("\\x: b. x y")?);
+        .assert_debug_eq(&cirparser::expr("(\\x: a. x) y", FILE)?);
+    assert_ne!(cirparser::expr("(\\x: b. x) y", FILE)?, cirparser::expr("\\x: b. x y", FILE)?);
 
     expect_file!["tests/expect/expr/type-lambda-app.ast"]
-        .assert_debug_eq(&cirparser::expr("(\\@a. 0) @Int")?);
+        .assert_debug_eq(&cirparser::expr("(\\@a. 0) @Int", FILE)?);
     expect_file!["tests/expect/expr/type-lambda-app-nested.ast"]
-        .assert_debug_eq(&cirparser::expr("(\\@a. \\x: a. x) @Int 5")?);
+        .assert_debug_eq(&cirparser::expr("(\\@a. \\x: a. x) @Int 5", FILE)?);
     Ok(())
 }
 
 #[test]
 fn test_parse_binder() -> anyhow::Result<()> {
     assert_eq!(
-        cirparser::binder("x: a")?,
+        cirparser::binder("x: a", FILE)?,
         Binder::Val(
-            Name::new(Span::new(0, 1), "x"),
-            Type::Var(TyVar { name: Name::new(Span::new(3, 4), "a") })
+            Name::new(Span::new(FILE, 0, 1), "x"),
+            Some(Type::Var(TyVar { name: Name::new(Span::new(FILE, 3, 4), "a") }))
         )
     );
+    assert_eq!(
+        cirparser::binder("x", FILE)?,
+        Binder::Val(Name::new(Span::new(FILE, 0, 1), "x"), None)
+    );
 
     assert_eq!(
-        cirparser::binder("@t")?,
-        Binder::Ty(TyVar { name: Name::new(Span::new(1, 2), "t") })
+        cirparser::binder("@t", FILE)?,
+        Binder::Ty(TyVar { name: Name::new(Span::new(FILE, 1, 2), "t") }, cir::Kind::Type)
     );
     Ok(())
 }
 
 #[test]
 fn test_parse_lambda() -> anyhow::Result<()> {
-    expect_file!["tests/expect/expr/lambda.ast"].assert_debug_eq(&cirparser::expr("\\x: a. x")?);
+    expect_file!["tests/expect/expr/lambda.ast"]
+        .assert_debug_eq(&cirparser::expr("\\x: a. x", FILE)?);
     expect_file!["tests/expect/expr/nested-lambda.ast"]
-        .assert_debug_eq(&cirparser::expr("\\x: a. \\y: b. x")?);
+        .assert_debug_eq(&cirparser::expr("\\x: a. \\y: b. x", FILE)?);
     expect_file!["tests/expect/expr/type-lambda.ast"]
-        .assert_debug_eq(&cirparser::expr("\\@a. \\x: a. x")?);
+        .assert_debug_eq(&cirparser::expr("\\@a. \\x: a. x", FILE)?);
     Ok(())
 }
 
 #[test]
 fn test_parse_ty() -> anyhow::Result<()> {
-    assert_eq!(cirparser::ty("Int")?, ast::Type::Scalar(cir::Scalar::Int));
-    assert_eq!(cirparser::ty("((Int))")?, ast::Type::Scalar(cir::Scalar::Int));
-    assert_eq!(cirparser::ty("Bool")?, ast::Type::Scalar(cir::Scalar::Bool));
+    assert_eq!(cirparser::ty("Int", FILE)?, ast::Type::Scalar(cir::Scalar::Int));
+    assert_eq!(cirparser::ty("((Int))", FILE)?, ast::Type::Scalar(cir::Scalar::Int));
+    assert_eq!(cirparser::ty("Bool", FILE)?, ast::Type::Scalar(cir::Scalar::Bool));
     assert_eq!(
-        cirparser::ty("a")?,
-        ast::Type::Var(TyVar { name: Name { symbol: "a".into(), span: Span::new(0, 1) } })
+        cirparser::ty("a", FILE)?,
+        ast::Type::Var(TyVar { name: Name { symbol: "a".into(), span: Span::new(FILE, 0, 1) } })
     );
-    expect_file!["tests/expect/ty/arrow-simple.ast"].assert_debug_eq(&cirparser::ty("a -> b")?);
+    expect_file!["tests/expect/ty/arrow-simple.ast"]
+        .assert_debug_eq(&cirparser::ty("a -> b", FILE)?);
     expect_file!["tests/expect/ty/arrow-right-assoc.ast"]
-        .assert_debug_eq(&cirparser::ty("a -> b -> c")?);
-    expect_file!["tests/expect/ty/forall.ast"].assert_debug_eq(&cirparser::ty("forall a.a -> a")?);
+        .assert_debug_eq(&cirparser::ty("a -> b -> c", FILE)?);
+    expect_file!["tests/expect/ty/forall.ast"]
+        .assert_debug_eq(&cirparser::ty("forall a.a -> a", FILE)?);
     expect_file!["tests/expect/ty/nested-forall.ast"]
-        .assert_debug_eq(&cirparser::ty("forall a. forall b. a -> b")?);
+        .assert_debug_eq(&cirparser::ty("forall a. forall b. a -> b", FILE)?);
+    Ok(())
+}
+
+#[test]
+fn test_parse_ty_app() -> anyhow::Result<()> {
+    expect_file!["tests/expect/ty/app.ast"]
+        .assert_debug_eq(&cirparser::ty("Either (List a) b -> Maybe a", FILE)?);
+    Ok(())
+}
+
+#[test]
+fn test_parse_type_operators() -> anyhow::Result<()> {
+    let star = || Box::new(cir::Kind::Type);
+    assert_eq!(cirparser::kind("*", FILE)?, cir::Kind::Type);
+    assert_eq!(
+        cirparser::kind("* -> * -> *", FILE)?,
+        cir::Kind::Fn(star(), Box::new(cir::Kind::Fn(star(), star())))
+    );
+    assert_eq!(
+        cirparser::kind("(* -> *) -> *", FILE)?,
+        cir::Kind::Fn(Box::new(cir::Kind::Fn(star(), star())), star())
+    );
+    expect_file!["tests/expect/ty/forall-kind.ast"]
+        .assert_debug_eq(&cirparser::ty("forall (f : * -> *). f Int -> f Bool", FILE)?);
+    expect_file!["tests/expect/ty/lambda.ast"]
+        .assert_debug_eq(&cirparser::ty("\\a. (a, a)", FILE)?);
+    expect_file!["tests/expect/expr/type-lambda-kind.ast"]
+        .assert_debug_eq(&cirparser::expr("\\@(f : * -> *). x", FILE)?);
     Ok(())
 }
 
 #[test]
 fn test_parse_value_def() -> anyhow::Result<()> {
     let value_def = ValueDef {
-        name: Name { span: Span::new(5, 6), symbol: "x".into() },
-        ty: Type::Var(TyVar { name: Name { span: Span::new(8, 9), symbol: "a".into() } }),
-        expr: Expr::Var(Var::Val { name: Name { span: Span::new(12, 13), symbol: "k".into() } }),
+        vis: Visibility::Private,
+        name: Name { span: Span::new(FILE, 5, 6), symbol: "x".into() },
+        ty: Some(Type::Var(TyVar {
+            name: Name { span: Span::new(FILE, 8, 9), symbol: "a".into() },
+        })),
+        expr: Expr::Var(Var::Val {
+            name: Name { span: Span::new(FILE, 12, 13), symbol: "k".into() },
+        }),
+    };
+    assert_eq!(cirparser::value_def(" let x: a = k ", FILE)?, value_def);
+    let value_def = ValueDef {
+        vis: Visibility::Private,
+        name: Name { span: Span::new(FILE, 5, 6), symbol: "x".into() },
+        ty: None,
+        expr: Expr::Var(Var::Val {
+            name: Name { span: Span::new(FILE, 9, 10), symbol: "k".into() },
+        }),
     };
-    assert_eq!(cirparser::value_def(" let x: a = k ")?, value_def);
+    assert_eq!(cirparser::value_def(" let x = k ", FILE)?, value_def);
     Ok(())
 }
 
 #[test]
 fn test_parse_ty_arrow() -> anyhow::Result<()> {
     assert_eq!(
-        cirparser::ty("a")?,
-        Type::Var(TyVar { name: Name { span: Span::new(0, 1), symbol: "a".into() } })
+        cirparser::ty("a", FILE)?,
+        Type::Var(TyVar { name: Name { span: Span::new(FILE, 0, 1), symbol: "a".into() } })
     );
     Ok(())
 }
@@ -137,25 +253,49 @@ fn test_parse_ty_arrow() -> anyhow::Result<()> {
 #[test]
 fn test_parse_item() -> anyhow::Result<()> {
     expect_file!["tests/expect/item/value-def.ast"]
-        .assert_debug_eq(&cirparser::item(" let x: a = k ; ")?);
+        .assert_debug_eq(&cirparser::item(" let x: a = k ; ", FILE)?);
+    expect_file!["tests/expect/item/type-synonym.ast"]
+        .assert_debug_eq(&cirparser::item("type Pair a b = forall r. (a -> b -> r) -> r;", FILE)?);
     Ok(())
 }
 
 #[test]
 fn test_parse_source_file() -> anyhow::Result<()> {
     expect_file!["tests/expect/file/value-defs.ast"]
-        .assert_debug_eq(&cirparser::source_file(" let x: a = k; let y: b = g;")?);
+        .assert_debug_eq(&cirparser::source_file(" let x: a = k; let y: b = g;", FILE)?);
     Ok(())
 }
 
 #[test]
 fn test_parse_data_def() -> anyhow::Result<()> {
     expect_file!["tests/expect/data/simple-data-def.ast"]
-        .assert_debug_eq(&cirparser::source_file("data T = A;")?);
+        .assert_debug_eq(&cirparser::source_file("data T = A;", FILE)?);
     expect_file!["tests/expect/data/simple-data-def-multi-variant.ast"]
-        .assert_debug_eq(&cirparser::source_file("data T = A | B;")?);
+        .assert_debug_eq(&cirparser::source_file("data T = A | B;", FILE)?);
     expect_file!["tests/expect/data/data-def-either.ast"]
-        .assert_debug_eq(&cirparser::source_file("data Either a b = Left a | Right b;")?);
+        .assert_debug_eq(&cirparser::source_file("data Either a b = Left a | Right b;", FILE)?);
+    expect_file!["tests/expect/data/data-def-sigs.ast"].assert_debug_eq(&cirparser::source_file(
+        "data E a where I : Int -> E Int; P : forall b. E b -> (Int -> Int) -> E (b, Int);",
+        FILE,
+    )?);
+    expect_file!["tests/expect/data/data-def-deriving.ast"].assert_debug_eq(
+        &cirparser::source_file("data Maybe a = Nothing | Just a deriving (Eq, M.Show);", FILE)?,
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_classes() -> anyhow::Result<()> {
+    expect_file!["tests/expect/ty/constrained.ast"]
+        .assert_debug_eq(&cirparser::ty("forall a. Eq a => Show (List a) => a -> String", FILE)?);
+    expect_file!["tests/expect/class/class.ast"].assert_debug_eq(&cirparser::item(
+        "class Eq a where eq : a -> a -> Bool; ne : a -> a -> Bool;",
+        FILE,
+    )?);
+    expect_file!["tests/expect/class/instance.ast"].assert_debug_eq(&cirparser::item(
+        "instance forall a. Eq a => Eq (List a) where eq = f @a; ne = g;",
+        FILE,
+    )?);
     Ok(())
 }
 
@@ -166,6 +306,164 @@ fn test_parse_tycon_and_datacon_path_ref() -> anyhow::Result<()> {
         data Y = Y;
         let t2y: T -> Y = \t:T.Y;
     "#;
-    expect_file!["tests/expect/ty/path.ast"].assert_debug_eq(&cirparser::source_file(src)?);
+    expect_file!["tests/expect/ty/path.ast"].assert_debug_eq(&cirparser::source_file(src, FILE)?);
+    Ok(())
+}
+
+#[test]
+fn test_parse_qualified_paths() -> anyhow::Result<()> {
+    let path = |segments: &[(&str, u32, u32)]| -> Path {
+        segments.iter().map(|&(s, start, end)| Name::new(Span::new(FILE, start, end), s)).collect()
+    };
+    assert_eq!(
+        cirparser::expr("List.map", FILE)?,
+        Expr::Path(path(&[("List", 0, 4), ("map", 5, 8)]))
+    );
+    assert_eq!(
+        cirparser::ty("Prelude.Maybe", FILE)?,
+        Type::Path(path(&[("Prelude", 0, 7), ("Maybe", 8, 13)]))
+    );
+    // the `.` in a binder separates the body unless the type is parenthesized
+    assert_eq!(
+        cirparser::expr("\\t:T.Y", FILE)?,
+        Expr::Lambda(
+            Binder::Val(
+                Name::new(Span::new(FILE, 1, 2), "t"),
+                Some(Type::Path(path(&[("T", 3, 4)])))
+            ),
+            Box::new(Expr::Path(path(&[("Y", 5, 6)])))
+        )
+    );
+    assert_eq!(
+        cirparser::expr("\\t:(M.T).Y", FILE)?,
+        Expr::Lambda(
+            Binder::Val(
+                Name::new(Span::new(FILE, 1, 2), "t"),
+                Some(Type::Path(path(&[("M", 4, 5), ("T", 6, 7)])))
+            ),
+            Box::new(Expr::Path(path(&[("Y", 9, 10)])))
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_modules_and_imports() -> anyhow::Result<()> {
+    let src = r#"
+        module M {
+            pub let x: Int = 1;
+            pub data T = pub A | B;
+        }
+        module N;
+        import M.x;
+    "#;
+    expect_file!["tests/expect/file/modules.ast"]
+        .assert_debug_eq(&cirparser::source_file(src, FILE)?);
+    Ok(())
+}
+
+#[test]
+fn test_parse_case() -> anyhow::Result<()> {
+    let src = r#"match xs {
+        Nil -> 0, // comment
+        Cons x rest -> f x 1,
+    }"#;
+    expect_file!["tests/expect/expr/case.ast"].assert_debug_eq(&cirparser::expr(src, FILE)?);
+    Ok(())
+}
+
+#[test]
+fn test_parse_prelude() {
+    assert!(cirparser::source_file(include_str!("prelude.cir"), FILE).is_ok());
+}
+
+#[test]
+fn test_parse_operators() -> anyhow::Result<()> {
+    expect_file!["tests/expect/expr/operators.ast"]
+        .assert_debug_eq(&cirparser::expr("!a || f x + 2 * -3 < 4 && x -1 == 0", FILE)?);
+    Ok(())
+}
+
+#[test]
+fn test_parse_if() -> anyhow::Result<()> {
+    expect_file!["tests/expect/expr/if.ast"]
+        .assert_debug_eq(&cirparser::expr("if x < 0 then f x else -x", FILE)?);
+    // Keywords are not identifiers
+    assert!(cirparser::expr("if", FILE).is_err());
+    assert!(cirparser::lname("then", FILE).is_err());
+    assert!(cirparser::lname("iffy", FILE).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_parse_let() -> anyhow::Result<()> {
+    expect_file!["tests/expect/expr/let.ast"]
+        .assert_debug_eq(&cirparser::expr("let x: Int = 1 in let y = x + 1 in y", FILE)?);
+    Ok(())
+}
+
+#[test]
+fn test_parse_let_rec() -> anyhow::Result<()> {
+    let src = "let rec f: Int -> Int = \\x:Int. g x and g: Int -> Int = \\x:Int. f x in f 0";
+    expect_file!["tests/expect/expr/let-rec.ast"].assert_debug_eq(&cirparser::expr(src, FILE)?);
+    Ok(())
+}
+
+#[test]
+fn test_parse_tuple() -> anyhow::Result<()> {
+    expect_file!["tests/expect/expr/tuple.ast"]
+        .assert_debug_eq(&cirparser::expr("(1, (x, f y), ())", FILE)?);
+    expect_file!["tests/expect/expr/tuple-pat.ast"]
+        .assert_debug_eq(&cirparser::expr("match p { (x, y) -> x, () -> 0 }", FILE)?);
+    assert_eq!(cirparser::ty("()", FILE)?, ast::Type::Tuple(vec![]));
+    assert_eq!(cirparser::ty("( Int )", FILE)?, ast::Type::Scalar(cir::Scalar::Int));
+    assert_eq!(
+        cirparser::ty("(Int, Bool)", FILE)?,
+        ast::Type::Tuple(vec![
+            ast::Type::Scalar(cir::Scalar::Int),
+            ast::Type::Scalar(cir::Scalar::Bool)
+        ])
+    );
+    // There are no 1-tuples
+    assert!(cirparser::pat("(x)", FILE).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_parse_record() -> anyhow::Result<()> {
+    expect_file!["tests/expect/expr/record.ast"]
+        .assert_debug_eq(&cirparser::expr("f { x = 1, y = r.p.q } {}.z", FILE)?);
+    expect_file!["tests/expect/ty/record.ast"]
+        .assert_debug_eq(&cirparser::ty("{ x: Int, f: a -> a } -> {}", FILE)?);
+    // A record as the payload of a variant
+    let data_def = cirparser::data_def("data Point = Point { x: Int, y: Int }", FILE)?;
+    assert!(
+        matches!(&data_def.variants[0].params[..], [Spanned { node: Type::Record(fields), .. }] if fields.len() == 2)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_recursive_type() -> anyhow::Result<()> {
+    expect_file!["tests/expect/ty/mu.ast"]
+        .assert_debug_eq(&cirparser::ty("mu l. Maybe (Int, l)", FILE)?);
+    expect_file!["tests/expect/expr/fold.ast"]
+        .assert_debug_eq(&cirparser::expr("fold @IntList (unfold xs)", FILE)?);
+    assert!(cirparser::lname("unfold", FILE).is_err());
+    assert!(matches!(cirparser::expr("unfolded", FILE)?, Expr::Var(_)));
+    assert!(matches!(cirparser::ty("mux", FILE)?, Type::Var(_)));
+    Ok(())
+}
+
+#[test]
+fn test_parse_existential() -> anyhow::Result<()> {
+    expect_file!["tests/expect/ty/exists.ast"]
+        .assert_debug_eq(&cirparser::ty("exists a. (a, a -> Int)", FILE)?);
+    expect_file!["tests/expect/expr/pack.ast"]
+        .assert_debug_eq(&cirparser::expr("pack @Int (0, f) as exists a. (a, a -> Int)", FILE)?);
+    expect_file!["tests/expect/expr/unpack.ast"]
+        .assert_debug_eq(&cirparser::expr("unpack @t p = e in f p", FILE)?);
+    assert!(cirparser::lname("pack", FILE).is_err());
+    assert!(cirparser::lname("package", FILE).is_ok());
     Ok(())
 }
@@ -0,0 +1,344 @@
+use std::sync::Arc;
+
+use cir::subst::{self, Substitute};
+use cir::{
+    ClassId, DataDefId, Debruijn, FileId, InstanceId, Kind, MethodId, Ty, TyKind, ValueDefId,
+    VariantId,
+};
+use cir_parse::LowerDatabase;
+
+use crate::error::{TypeError, TypeErrorKind};
+use crate::infer;
+use crate::kind;
+use crate::normalize::normalize;
+use crate::positivity::{self, PositivityError};
+use crate::variance::{self, Variance};
+use crate::TypecheckCtxt;
+
+#[salsa::query_group(TypecheckDatabaseStorage)]
+pub trait TypecheckDatabase: LowerDatabase {
+    /// Checks the body of a `ValueDef` against its declared (or inferred) type
+    fn type_of_value_def(&self, def: ValueDefId) -> Ty;
+
+    /// The declared type of a `ValueDef`, or the type inferred for it if it has no signature
+    fn value_def_ty(&self, def: ValueDefId) -> Ty;
+
+    /// Infers the type of a `ValueDef` without a signature, see `infer`. Returns the generalised
+    /// type and the body with explicit type abstractions, type arguments and lambda binder
+    /// types, which is checked against it like the body of a definition with a signature, and
+    /// the errors found. A definition with errors has the error type.
+    #[salsa::invoke(infer::infer_value_def)]
+    fn infer_value_def(&self, def: ValueDefId) -> (Ty, Arc<cir::BodyData>, Arc<[TypeErrorKind]>);
+
+    /// Infers the types of the mutually recursive definitions without signatures whose first
+    /// definition is `root`, see `infer_value_def`
+    #[salsa::invoke(infer::infer_scc)]
+    fn infer_scc(&self, root: ValueDefId) -> Arc<[infer::InferredDef]>;
+
+    /// The body of a `ValueDef` after typechecking and the errors found in its signature and
+    /// body, see `elaborated_body`
+    fn elaborated_body_with_errors(
+        &self,
+        def: ValueDefId,
+    ) -> (Arc<cir::BodyData>, Arc<[TypeErrorKind]>);
+
+    /// The body of a `ValueDef` after typechecking, where constraints are explicit dictionary
+    /// parameters and arguments and methods are fields of dictionaries
+    fn elaborated_body(&self, def: ValueDefId) -> Arc<cir::BodyData>;
+
+    /// The dictionary of an instance after typechecking and the errors found in its head,
+    /// context and methods
+    fn elaborated_instance_body_with_errors(
+        &self,
+        instance: InstanceId,
+    ) -> (Arc<cir::BodyData>, Arc<[TypeErrorKind]>);
+
+    /// The dictionary of an instance after typechecking, see `instance_ty`
+    fn elaborated_instance_body(&self, instance: InstanceId) -> Arc<cir::BodyData>;
+
+    /// The type errors of the items of `file`
+    fn type_errors(&self, file: FileId) -> Arc<[TypeError]>;
+
+    /// The type of a data constructor, e.g. `forall a. a -> List a -> List a` for `Cons`.
+    /// Parameters of the data definition that the constructor's signature does not mention are
+    /// not bound, e.g. `IntE : Int -> Expr Int` has type `Int -> Expr Int`.
+    fn ctor_ty(&self, variant: VariantId) -> Ty;
+
+    /// The type of a method, e.g. `forall a. Eq a => a -> a -> Bool` for `eq`
+    fn method_ty(&self, method: MethodId) -> Ty;
+
+    /// The type of the dictionary of an instance, e.g.
+    /// `forall a. Eq a => { eq: List a -> List a -> Bool }` for
+    /// `instance forall a. Eq a => Eq (List a)`
+    fn instance_ty(&self, instance: InstanceId) -> Ty;
+
+    /// The instances of a class in every file, found from the signatures of the files
+    fn class_instances(&self, class: ClassId) -> Arc<[InstanceId]>;
+
+    /// Checks that the data types defined in `file` occur only strictly positively in their own
+    /// definitions. This check is optional, data types are accepted by the other queries
+    /// regardless.
+    #[salsa::invoke(positivity::positivity_errors)]
+    fn positivity_errors(&self, file: FileId) -> Arc<[PositivityError]>;
+
+    /// The inferred variance of each type parameter of a data type
+    #[salsa::invoke(variance::variances)]
+    fn variances(&self, data_def: DataDefId) -> Arc<[Variance]>;
+}
+
+fn type_of_value_def(db: &dyn TypecheckDatabase, def: ValueDefId) -> Ty {
+    db.elaborated_body(def);
+    normalize(&db.value_def_ty(def))
+}
+
+fn value_def_ty(db: &dyn TypecheckDatabase, def: ValueDefId) -> Ty {
+    match db.value_def_sig(def) {
+        // The error is reported by `elaborated_body_with_errors`
+        Some(ty) => match kind::check_kind(db, &[], &ty, &Kind::Type) {
+            Ok(()) => ty,
+            Err(_) => TyKind::Err.intern(),
+        },
+        None => db.infer_value_def(def).0,
+    }
+}
+
+fn elaborated_body_with_errors(
+    db: &dyn TypecheckDatabase,
+    def: ValueDefId,
+) -> (Arc<cir::BodyData>, Arc<[TypeErrorKind]>) {
+    let (ty, body) = match db.value_def_sig(def) {
+        Some(sig) => {
+            if let Err(err) = kind::check_kind(db, &[], &sig, &Kind::Type) {
+                return (db.value_def_body(def), Arc::from([err]));
+            }
+            (normalize(&sig), db.value_def_body(def))
+        }
+        None => {
+            let (ty, body, errors) = db.infer_value_def(def);
+            // The body is not fully explicit if inference failed
+            if !errors.is_empty() {
+                return (body, errors);
+            }
+            (ty, body)
+        }
+    };
+    let mut tcx = TypecheckCtxt::new(db, cir::BodyData::clone(&body));
+    tcx.expect_body(&ty);
+    (Arc::new(tcx.body), Arc::from(tcx.errors))
+}
+
+fn elaborated_body(db: &dyn TypecheckDatabase, def: ValueDefId) -> Arc<cir::BodyData> {
+    db.elaborated_body_with_errors(def).0
+}
+
+fn elaborated_instance_body_with_errors(
+    db: &dyn TypecheckDatabase,
+    instance: InstanceId,
+) -> (Arc<cir::BodyData>, Arc<[TypeErrorKind]>) {
+    let items = db.items(instance.file);
+    let body = db.instance_body(instance);
+    if let Err(err) = check_instance_kinds(db, &items[instance.def]) {
+        return (body, Arc::from([err]));
+    }
+    let ty = db.instance_ty(instance);
+    let mut tcx = TypecheckCtxt::new(db, cir::BodyData::clone(&body));
+    tcx.expect_body(&ty);
+    (Arc::new(tcx.body), Arc::from(tcx.errors))
+}
+
+fn elaborated_instance_body(
+    db: &dyn TypecheckDatabase,
+    instance: InstanceId,
+) -> Arc<cir::BodyData> {
+    db.elaborated_instance_body_with_errors(instance).0
+}
+
+fn type_errors(db: &dyn TypecheckDatabase, file: FileId) -> Arc<[TypeError]> {
+    // Items are equal regardless of the spans of their names, the errors are found again
+    // whenever the file changes so that their spans are current
+    db.file_text(file);
+    let items = match db.lower_file(file) {
+        Ok(items) => items,
+        Err(_) => return Arc::from([]),
+    };
+    let mut errors = vec![];
+    for item in &items.items {
+        match *item {
+            cir::Item::ValueDef(def) => {
+                let span = items[def].name.span;
+                let (_, kinds) = db.elaborated_body_with_errors(ValueDefId { file, def });
+                errors.extend(kinds.iter().map(|kind| TypeError::new(span, kind.clone())));
+            }
+            cir::Item::DataDef(def) =>
+                for (_, variant) in items[def].variants.iter() {
+                    if let Err(kind) = kind::check_variant(db, &items[def], variant) {
+                        errors.push(TypeError::new(variant.name.span, kind));
+                    }
+                },
+            cir::Item::Class(def) => {
+                let class = &items[def];
+                for (name, ty) in &class.methods {
+                    let env = std::slice::from_ref(&class.kind);
+                    if let Err(kind) = kind::check_kind(db, env, ty, &Kind::Type) {
+                        errors.push(TypeError::new(name.span, kind));
+                    }
+                }
+            }
+            cir::Item::Instance(def) => {
+                let span = items[def].span;
+                let (_, kinds) = db.elaborated_instance_body_with_errors(InstanceId { file, def });
+                errors.extend(kinds.iter().map(|kind| TypeError::new(span, kind.clone())));
+            }
+            cir::Item::TypeSynonym(_) => {}
+        }
+    }
+    Arc::from(errors)
+}
+
+fn ctor_ty(db: &dyn TypecheckDatabase, variant: VariantId) -> Ty {
+    let arity = db.items(variant.data_def.file)[variant.data_def.def].arity;
+    let mut ty = ctor_ty_with_params(db, variant);
+    for _ in 0..arity {
+        ty = ty.skip_binder();
+    }
+    // The innermost variable is always the next parameter to bind, an unused one is removed by
+    // substituting anything for it
+    for _ in 0..arity {
+        ty = match subst::occurs(&ty, Debruijn::INNER) {
+            true => TyKind::ForAll(Kind::Type, ty).intern(),
+            false => ty.substitute(&TyKind::Err.intern()),
+        };
+    }
+    ty
+}
+
+fn method_ty(db: &dyn TypecheckDatabase, method: MethodId) -> Ty {
+    let items = db.items(method.class.file);
+    let class = &items[method.class.def];
+    let (_, ty) = &class.methods[method.index];
+    if kind::check_kind(db, std::slice::from_ref(&class.kind), ty, &Kind::Type).is_err() {
+        return TyKind::Err.intern();
+    }
+    let param = TyKind::Var(Debruijn::INNER).intern();
+    let ty = TyKind::Constrained(method.class, param, normalize(ty)).intern();
+    TyKind::ForAll(class.kind.clone(), ty).intern()
+}
+
+fn instance_ty(db: &dyn TypecheckDatabase, instance: InstanceId) -> Ty {
+    let items = db.items(instance.file);
+    let instance = &items[instance.def];
+    if check_instance_kinds(db, instance).is_err() {
+        return TyKind::Err.intern();
+    }
+    let ty = dict_ty(db, instance.class, &instance.head);
+    let ty = instance
+        .context
+        .iter()
+        .rev()
+        .fold(ty, |ty, (class, arg)| TyKind::Constrained(*class, normalize(arg), ty).intern());
+    instance.params.iter().rev().fold(ty, |ty, kind| TyKind::ForAll(kind.clone(), ty).intern())
+}
+
+fn class_instances(db: &dyn TypecheckDatabase, class: ClassId) -> Arc<[InstanceId]> {
+    let mut instances = vec![];
+    for &file in db.files().iter() {
+        if let Ok(items) = db.file_signatures(file) {
+            instances.extend(
+                items
+                    .instances
+                    .iter()
+                    .filter(|(_, instance)| instance.class == class)
+                    .map(|(def, _)| InstanceId { file, def }),
+            );
+        }
+    }
+    Arc::from(instances)
+}
+
+/// Checks that the class of `instance` and those of its context are applied to types of the kinds
+/// of their parameters
+fn check_instance_kinds(
+    db: &dyn TypecheckDatabase,
+    instance: &cir::InstanceData,
+) -> Result<(), TypeErrorKind> {
+    kind::check_kind(db, &instance.params, &instance.head, &kind::class_kind(db, instance.class))?;
+    for (class, arg) in &instance.context {
+        kind::check_kind(db, &instance.params, arg, &kind::class_kind(db, *class))?;
+    }
+    Ok(())
+}
+
+/// The type of the dictionary of `class` for `arg`, a record of the methods of the class
+/// instantiated with `arg`
+pub(crate) fn dict_ty(db: &dyn TypecheckDatabase, class: ClassId, arg: &Ty) -> Ty {
+    let items = db.items(class.file);
+    let class = &items[class.def];
+    let mut fields = class
+        .methods
+        .iter()
+        .map(|(name, ty)| {
+            let ty = match kind::check_kind(db, std::slice::from_ref(&class.kind), ty, &Kind::Type)
+            {
+                Ok(()) => normalize(&ty.substitute(arg)),
+                Err(_) => TyKind::Err.intern(),
+            };
+            (name.symbol.clone(), ty)
+        })
+        .collect::<Vec<_>>();
+    fields.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+    TyKind::Record(fields).intern()
+}
+
+/// The (normalized) types of the fields of every variant of `data_def`, its parameters are bound
+/// as in `VariantData::fields`
+pub(crate) fn data_def_fields(db: &dyn TypecheckDatabase, data_def: DataDefId) -> Vec<Ty> {
+    let items = db.items(data_def.file);
+    let data = &items[data_def.def];
+    data.variants
+        .iter()
+        .flat_map(|(_, variant)| {
+            // The fields of an ill-kinded variant are left out, its error is reported by
+            // `type_errors`
+            let fields = match kind::check_variant(db, data, variant) {
+                Ok(()) => &variant.fields[..],
+                Err(_) => &[],
+            };
+            fields.iter().map(normalize)
+        })
+        .collect()
+}
+
+/// Like `ctor_ty` but every parameter of the data definition is bound
+pub(crate) fn ctor_ty_with_params(db: &dyn TypecheckDatabase, variant: VariantId) -> Ty {
+    let items = db.items(variant.data_def.file);
+    let data_def = &items[variant.data_def.def];
+    let variant_data = &data_def.variants[variant.variant];
+    // The error of an ill-kinded variant is reported by `type_errors`
+    let ty = match kind::check_variant(db, data_def, variant_data) {
+        Ok(()) => {
+            // The first parameter is bound by the outermost `forall`
+            let args = variant_data.result.clone().unwrap_or_else(|| {
+                (0..data_def.arity as u32)
+                    .rev()
+                    .map(|index| TyKind::Var(Debruijn::new(index)).intern())
+                    .collect()
+            });
+            let ty = TyKind::Adt(variant.data_def, args).intern();
+            variant_data
+                .fields
+                .iter()
+                .rev()
+                .fold(ty, |ty, field| TyKind::Fn(Ty::clone(field), ty).intern())
+        }
+        Err(_) => TyKind::Err.intern(),
+    };
+    let ty = variant_data
+        .ty_params
+        .iter()
+        .rev()
+        .fold(ty, |ty, kind| TyKind::ForAll(kind.clone(), ty).intern());
+    (0..data_def.arity).fold(ty, |ty, _| TyKind::ForAll(Kind::Type, ty).intern())
+}
+
+#[cfg(test)]
+mod tests;
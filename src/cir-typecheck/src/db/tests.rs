@@ -0,0 +1,638 @@
+use std::sync::Arc;
+
+use cir::db::SourceDatabase;
+use cir::{ExprData, Item, TyKind, ValueDefId};
+use cir_parse::LowerDatabase;
+
+use super::*;
+use crate::test_db::TestDatabase;
+
+#[test]
+fn test_typeck_value_defs() {
+    let src = "let x: Int = 1; let id: forall a. a -> a = \\@a.\\x:a.x;";
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    let file = files[0];
+    assert_eq!(db.checked_ty(db.value_def(file, "x")), ty!(Int));
+    assert_eq!(db.checked_ty(db.value_def(file, "id")), ty!(forall a. a -> a));
+}
+
+#[test]
+fn test_typeck_only_rechecks_changed_body() {
+    let src = r#"
+        data B = T | F;
+        let x: Int = 1;
+        let f: B -> Int = \b:B. match b { T -> 1, F -> 0 };
+    "#;
+    let (mut db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    let file = files[0];
+    db.elaborated_body(db.value_def(file, "x"));
+    db.elaborated_body(db.value_def(file, "f"));
+    db.take_executed();
+
+    // `f` depends on the signatures of the file, which are unaffected by the body of `x`
+    db.set_file_text(file, Arc::from(src.replace("let x: Int = 1;", "let x: Int = 42;")));
+    db.elaborated_body(db.value_def(file, "x"));
+    db.elaborated_body(db.value_def(file, "f"));
+    // `elaborated_body` projects the body checked by `elaborated_body_with_errors`
+    let rechecked = db
+        .take_executed()
+        .into_iter()
+        .filter(|query| query.starts_with("elaborated_body_with_errors("))
+        .collect::<Vec<_>>();
+    assert_eq!(rechecked.len(), 1, "{:?}", rechecked);
+}
+
+#[test]
+fn test_typeck_multiple_files() {
+    let (db, files) =
+        TestDatabase::with_files(&[("a.cir", "let x: Int = 1;"), ("b.cir", "let y: Bool = true;")]);
+    let (a, b) = (files[0], files[1]);
+    assert_eq!(*db.files(), vec![a, b]);
+
+    let y = db.value_def(b, "y");
+    assert_eq!(db.checked_ty(y), ty!(Bool));
+    let name = &db.lower_file(b).unwrap()[y.def].name;
+    assert_eq!(name.span.file, b);
+    assert_eq!(&db.file_text(b)[name.span.range()], "y");
+}
+
+#[test]
+fn test_typeck_prelude() {
+    let (db, _) = TestDatabase::with_prelude(&[]);
+    let prelude = db.files()[0];
+    assert_eq!(db.type_error_messages(prelude), Vec::<String>::new());
+    let items = db.lower_file(prelude).unwrap();
+    for item in &items.items {
+        match *item {
+            Item::ValueDef(def) => {
+                let def = ValueDefId { file: prelude, def };
+                assert_eq!(db.checked_ty(def), db.value_def_ty(def));
+            }
+            // Includes the derived instances
+            Item::Instance(def) => {
+                db.elaborated_instance_body(InstanceId { file: prelude, def });
+            }
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_typeck_deriving() {
+    let src = r#"
+        data Tree a = Leaf | Node (Tree a) a (Tree a) deriving (Eq, Show);
+        let t: Tree Int = Node @Int (Leaf @Int) 1 (Leaf @Int);
+        let e: Bool = eq @(Tree (Maybe Int)) (Leaf @(Maybe Int)) (Leaf @(Maybe Int));
+        let s: String = show @(List (Tree Int)) (Cons @(Tree Int) t (Nil @(Tree Int)));
+    "#;
+    let (db, files) = TestDatabase::with_prelude(&[("main.cir", src)]);
+    let (items, errors) = db.lower_file_with_errors(files[0]).unwrap();
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(db.type_error_messages(files[0]), Vec::<String>::new());
+    for (def, _) in items.instances.iter() {
+        db.elaborated_instance_body(InstanceId { file: files[0], def });
+    }
+    for name in ["t", "e", "s"] {
+        let def = db.value_def(files[0], name);
+        assert_eq!(db.checked_ty(def), db.value_def_ty(def));
+    }
+}
+
+#[test]
+fn test_typeck_using_prelude() {
+    let src = r#"
+        let xs: List Int = Cons @Int 1 (Cons @Int 2 (Nil @Int));
+        let ys: List Bool = map @Int @Bool (\x:Int. true) (reverse @Int xs);
+        let m: Maybe (Pair Int Bool) = head @(Pair Int Bool) (zip @Int @Bool xs ys);
+        let n: Int = match m { Nothing -> 0, Just p -> fst @Int @Bool p };
+        let zs: List Int = map @Int @Int (\x. x + 1) xs;
+    "#;
+    let (db, files) = TestDatabase::with_prelude(&[("main.cir", src)]);
+    let (_, errors) = db.lower_file_with_errors(files[0]).unwrap();
+    assert!(errors.is_empty(), "{:?}", errors);
+    for name in ["xs", "ys", "m", "n", "zs"] {
+        let def = db.value_def(files[0], name);
+        assert_eq!(db.checked_ty(def), db.value_def_ty(def));
+    }
+}
+
+#[test]
+fn test_display_ty() {
+    // Only the signatures are displayed, the bodies are not checked
+    let src = r#"
+        data Rose a = Rose a (List (Rose a));
+        let a: Int -> (Bool -> Bool) -> Int = 0;
+        let b: forall a. Maybe (List a) -> (a, { x: Int, y: String }) = 0;
+        let c: forall (f : * -> *). forall a. f a -> f (f a) = 0;
+        let d: forall a. Eq a => a -> List (Rose a) -> Bool = 0;
+        let e: exists a. (a, a -> Int) = 0;
+        let f: mu a. Either () (Int, a) = 0;
+    "#;
+    let (db, files) = TestDatabase::with_prelude(&[("main.cir", src)]);
+    let display = |name| {
+        let ty = db.value_def_ty(db.value_def(files[0], name));
+        let display = cir_parse::display_ty(&db, &ty).to_string();
+        display
+    };
+    assert_eq!(display("a"), "Int -> (Bool -> Bool) -> Int");
+    assert_eq!(display("b"), "forall a. Maybe (List a) -> (a, { x: Int, y: String })");
+    assert_eq!(display("c"), "forall (a : * -> *). forall b. a b -> a (a b)");
+    assert_eq!(display("d"), "forall a. Eq a => a -> List (Rose a) -> Bool");
+    assert_eq!(display("e"), "exists a. (a, a -> Int)");
+    assert_eq!(display("f"), "mu a. Either () (Int, a)");
+}
+
+#[test]
+fn test_ctor_ty() {
+    let src = "data Either a b = Left a | Right b; let x: Int = 0;";
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    let items = db.lower_file(files[0]).unwrap();
+    let (def, data_def) = items.data_defs.iter().next().unwrap();
+    let data_def_id = cir::DataDefId { file: files[0], def };
+    let mut variants = data_def
+        .variants
+        .iter()
+        .map(|(variant, _)| db.ctor_ty(cir::VariantId { data_def: data_def_id, variant }));
+    let either = |a, b| cir::TyKind::Adt(data_def_id, vec![a, b]).intern();
+    let var = |index| cir::TyKind::Var(cir::Debruijn::new(index)).intern();
+    let forall = |ty| cir::TyKind::ForAll(cir::Kind::Type, ty).intern();
+    let left = forall(forall(cir::TyKind::Fn(var(1), either(var(1), var(0))).intern()));
+    let right = forall(forall(cir::TyKind::Fn(var(0), either(var(1), var(0))).intern()));
+    assert_eq!(variants.next(), Some(left));
+    assert_eq!(variants.next(), Some(right));
+}
+
+#[test]
+fn test_typeck_type_operators() {
+    let src = r#"
+        data Box a = Box a;
+        let both: forall (f : * -> *). (forall a. a -> f a) -> (Int, Bool) -> (f Int, f Bool) =
+            \@(f : * -> *). \pure:(forall a. a -> f a). \p:(Int, Bool).
+                match p { (x, y) -> (pure @Int x, pure @Bool y) };
+        let boxed: (Box Int, Box Bool) = both @Box (\@a. \x:a. Box @a x) (1, true);
+        let pairs: ((Int, Int), (Bool, Bool)) = both @(\a. (a, a)) (\@a. \x:a. (x, x)) (1, true);
+    "#;
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    let file = files[0];
+    db.checked_ty(db.value_def(file, "both"));
+    db.checked_ty(db.value_def(file, "boxed"));
+    assert_eq!(db.checked_ty(db.value_def(file, "pairs")), ty!(((Int, Int), (Bool, Bool))));
+}
+
+#[test]
+fn test_typeck_kind_of_signature() {
+    let src = r#"
+        data Maybe a = Nothing | Just a;
+        let id: forall a. a -> a = \@a. \x:a. x;
+        let g: Maybe -> Maybe = id @Maybe;
+    "#;
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    assert_eq!(db.type_error_messages(files[0]), ["kind mismatch: expected `*`, found `* -> *`"]);
+}
+
+#[test]
+fn test_typeck_kind_of_type_argument() {
+    let src = r#"
+        let app: forall (f : * -> *). f Int -> f Int = \@(f : * -> *). \x:f Int. x;
+        let bad: Int -> Int = app @Int;
+    "#;
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    assert_eq!(db.type_error_messages(files[0]), ["kind mismatch: expected `* -> *`, found `*`"]);
+}
+
+#[test]
+fn test_typeck_kind_of_field() {
+    let src = "data Maybe a = Nothing | Just a; data T = T Maybe; let bad: Maybe Int -> T = T;";
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    assert_eq!(db.type_error_messages(files[0]), ["kind mismatch: expected `*`, found `* -> *`"]);
+}
+
+#[test]
+fn test_typeck_kind_of_application() {
+    let (db, files) = TestDatabase::with_files(&[("main.cir", "let bad: Int Int = 0;")]);
+    assert_eq!(db.type_error_messages(files[0]), ["a type of kind `*` is applied to an argument"]);
+}
+
+#[test]
+fn test_typeck_kind_of_self_application() {
+    // Would not terminate if normalized
+    let src = "let bad: (\\a. a a) (\\a. a a) = 3;";
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    assert_eq!(db.type_error_messages(files[0]), ["a type of kind `*` is applied to an argument"]);
+}
+
+#[test]
+fn test_typeck_type_synonyms() {
+    let src = r#"
+        type Pair a b = forall r. (a -> b -> r) -> r;
+        let pair: forall a. forall b. a -> b -> Pair a b =
+            \@a. \@b. \x:a. \y:b. \@r. \k:(a -> b -> r). k x y;
+        let fst: forall a. forall b. Pair a b -> a =
+            \@a. \@b. \p:Pair a b. p @a (\x:a. \y:b. x);
+        let x: Int = fst @Int @Bool (pair @Int @Bool 1 true);
+    "#;
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    let file = files[0];
+    assert_eq!(
+        db.checked_ty(db.value_def(file, "fst")),
+        ty!(forall a. forall b. (forall r. (a -> b -> r) -> r) -> a)
+    );
+    assert_eq!(db.checked_ty(db.value_def(file, "x")), ty!(Int));
+}
+
+#[test]
+fn test_positivity() {
+    let src = r#"
+        data Bad = Bad (Bad -> Int);
+        data Good = Good ((Int -> Good) -> Good) (List Good);
+        data List a = Nil | Cons a (List a);
+        data Pred a = Pred (a -> Bool);
+        data Nested = Nested (Pred Nested) (Int -> List Nested);
+        data Even = Zero | Succ Odd;
+        data Odd = OddSucc (Even -> Bool);
+        module M { data Hidden = Hidden Int ((Hidden, Int) -> Int); }
+    "#;
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    let errors = db.positivity_errors(files[0]);
+    let errors =
+        errors.iter().map(|err| (err.to_string(), &src[err.span.range()])).collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            ("`Bad` occurs in a position that is not strictly positive".to_owned(), "(Bad -> Int)"),
+            (
+                "`Good` occurs in a position that is not strictly positive".to_owned(),
+                "((Int -> Good) -> Good)"
+            ),
+            (
+                "`Nested` occurs in a position that is not strictly positive".to_owned(),
+                "(Pred Nested)"
+            ),
+            ("`Even` occurs in a position that is not strictly positive".to_owned(), "Odd"),
+            (
+                "`Odd` occurs in a position that is not strictly positive".to_owned(),
+                "(Even -> Bool)"
+            ),
+            (
+                "`Hidden` occurs in a position that is not strictly positive".to_owned(),
+                "((Hidden, Int) -> Int)"
+            ),
+        ]
+    );
+
+    let (db, files) = TestDatabase::with_prelude(&[("main.cir", "let x: Int = 0;")]);
+    let prelude = db.files().iter().copied().find(|file| !files.contains(file)).unwrap();
+    assert!(db.positivity_errors(prelude).is_empty());
+}
+
+#[test]
+fn test_variances() {
+    let src = r#"
+        data Phantom a = Phantom Int;
+        data Box a = Box a;
+        data Pred a = Pred (a -> Bool);
+        data Endo a = Endo (a -> a);
+        data Cont r a = Cont ((a -> r) -> r);
+        data List a = Nil | Cons a (List a);
+        data Wrap a = Wrap (Pred (Pred a)) (List (Phantom (a -> a)));
+        data Tree a = Leaf | Node a (Forest a);
+        data Rose a = Rose (RoseForest a);
+        data RoseForest a = RoseForest (List (Rose a));
+        data Forest a = Forest (List (Tree a));
+    "#;
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    let variances = |name| db.variances(db.data_def(files[0], name)).to_vec();
+    use Variance::*;
+    assert_eq!(variances("Phantom"), [Bivariant]);
+    assert_eq!(variances("Box"), [Covariant]);
+    assert_eq!(variances("Pred"), [Contravariant]);
+    assert_eq!(variances("Endo"), [Invariant]);
+    assert_eq!(variances("Cont"), [Invariant, Covariant]);
+    assert_eq!(variances("List"), [Covariant]);
+    assert_eq!(variances("Wrap"), [Covariant]);
+    assert_eq!(variances("Tree"), [Covariant]);
+    assert_eq!(variances("Forest"), [Covariant]);
+    // `a` never occurs outside of the recursion
+    assert_eq!(variances("Rose"), [Bivariant]);
+}
+
+const TYPED_EXPR: &str = r#"
+    data Expr a where
+        IntE : Int -> Expr Int;
+        BoolE : Bool -> Expr Bool;
+        Add : Expr Int -> Expr Int -> Expr Int;
+        IsZero : Expr Int -> Expr Bool;
+        If : Expr Bool -> Expr a -> Expr a -> Expr a;
+        Pair : forall b. forall c. Expr b -> Expr c -> Expr (b, c);
+        Fst : forall b. Expr (a, b) -> Expr a;
+
+    let eval: forall a. Expr a -> a = \@a.\e:Expr a. match e {
+        IntE n -> n,
+        BoolE b -> b,
+        Add x y -> eval @Int x + eval @Int y,
+        IsZero x -> eval @Int x == 0,
+        If c t f -> if eval @Bool c then eval @a t else eval @a f,
+        Pair @b @c x y -> (eval @b x, eval @c y),
+        Fst @b p -> match eval @(a, b) p { (x, y) -> x },
+    };
+"#;
+
+#[test]
+fn test_typeck_ctor_sigs() {
+    let src = format!(
+        "{}{}",
+        TYPED_EXPR,
+        r#"
+        let isInt: Expr Int -> Bool = \e:Expr Int. match e { IntE n -> true, Add x y -> true };
+        let fst: Expr (Int, Bool) -> Int = \e:Expr (Int, Bool). match e {
+            Pair @b @c x y -> eval @b x,
+        };
+        let test: Int = eval @Int (If @Int (IsZero (IntE 0)) (IntE 1) (IntE 2));
+        let intE: Int -> Expr Int = IntE;
+        let ifE: forall a. Expr Bool -> Expr a -> Expr a -> Expr a = If;
+        let pair: forall b. forall c. Expr b -> Expr c -> Expr (b, c) = Pair;
+        let fstE: forall a. forall b. Expr (a, b) -> Expr a = Fst;
+    "#
+    );
+    let (db, files) = TestDatabase::with_files(&[("main.cir", &src)]);
+    for name in ["intE", "ifE", "pair", "fstE", "eval", "isInt", "fst", "test"] {
+        let def = db.value_def(files[0], name);
+        assert_eq!(db.checked_ty(def), db.value_def_ty(def));
+    }
+    let expr = db.data_def(files[0], "Expr");
+    assert_eq!(db.variances(expr).to_vec(), [Variance::Invariant]);
+}
+
+#[test]
+fn test_typeck_ctor_sigs_unrefined() {
+    // `Add` only refines `a` to `Int` in its own alternative
+    let src = r#"
+        data Expr a where IntE : Int -> Expr Int; Add : Expr Int -> Expr Int -> Expr Int;
+        let bad: forall a. Expr a -> a = \@a.\e:Expr a. match e {
+            IntE n -> n,
+            Add x y -> x,
+        };
+    "#;
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    assert_eq!(
+        db.type_error_messages(files[0]),
+        ["type mismatch: expected `Int`, found `Expr Int`"]
+    );
+}
+
+#[test]
+fn test_typeck_recursion() {
+    let src = r#"
+        let factorial: Int -> Int = \n:Int. if n == 0 then 1 else n * factorial (n - 1);
+        let even: Int -> Bool =
+            let rec even: Int -> Bool = \n:Int. if n == 0 then true else odd (n - 1)
+            and odd: Int -> Bool = \n:Int. if n == 0 then false else even (n - 1)
+            in even;
+    "#;
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    assert_eq!(db.checked_ty(db.value_def(files[0], "factorial")), ty!(Int -> Int));
+    assert_eq!(db.checked_ty(db.value_def(files[0], "even")), ty!(Int -> Bool));
+}
+
+#[test]
+fn test_typeck_unused_type_parameter() {
+    let src = r#"
+        let poly: forall a. Int -> Int = \@a.\x:Int. x;
+        let apply: (Int -> Int) -> Int = \f:Int -> Int. f 1;
+        let main: Int = apply poly;
+    "#;
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    assert_eq!(db.checked_ty(db.value_def(files[0], "main")), ty!(Int));
+}
+
+const EQ_CLASS: &str = r#"
+    class Eq a where eq : a -> a -> Bool;
+    data List a = Nil | Cons a (List a);
+    instance Eq Int where eq = \x:Int.\y:Int. x == y;
+    instance forall a. Eq a => Eq (List a) where
+        eq = \xs:List a.\ys:List a. match xs {
+            Nil -> match ys { Nil -> true, Cons y ys2 -> false },
+            Cons x xs2 -> match ys { Nil -> false, Cons y ys2 -> eq @a x y && eq @(List a) xs2 ys2 },
+        };
+"#;
+
+#[test]
+fn test_typeck_classes() {
+    let src = format!(
+        "{}{}",
+        EQ_CLASS,
+        r#"
+        let neq: forall a. Eq a => a -> a -> Bool = \@a.\x:a.\y:a. !(eq @a x y);
+        let test: Bool = neq @(List Int) (Cons @Int 1 (Nil @Int)) (Nil @Int);
+    "#
+    );
+    let (db, files) = TestDatabase::with_files(&[("main.cir", &src)]);
+    for name in ["neq", "test"] {
+        let def = db.value_def(files[0], name);
+        assert_eq!(db.checked_ty(def), db.value_def_ty(def));
+    }
+    let items = db.lower_file(files[0]).unwrap();
+    for (def, _) in items.instances.iter() {
+        db.elaborated_instance_body(cir::InstanceId { file: files[0], def });
+    }
+    // `neq` takes the dictionary after its type parameter and `test` passes the dictionary of
+    // `Eq (List Int)`, which is built from that of `Eq Int`
+    let neq = db.elaborated_body(db.value_def(files[0], "neq"));
+    let dict = match neq[neq.expr] {
+        ExprData::Lambda(_, inner) => match neq[inner] {
+            ExprData::Lambda(dict, _) => dict,
+            ref expr => panic!("expected the dictionary parameter, found {:?}", expr),
+        },
+        ref expr => panic!("expected a type lambda, found {:?}", expr),
+    };
+    assert!(
+        matches!(&neq.binders[dict], cir::BinderData::Val(ty) if matches!(ty.kind(), TyKind::Record(_)))
+    );
+    let test = db.elaborated_body(db.value_def(files[0], "test"));
+    let instances = test.exprs.iter().filter(|(_, expr)| matches!(expr, ExprData::Instance(_)));
+    assert_eq!(instances.count(), 2);
+    assert!(!test.exprs.iter().any(|(_, expr)| matches!(expr, ExprData::Method(_))));
+}
+
+#[test]
+fn test_typeck_class_instances_unaffected_by_bodies() {
+    let src = format!("{}{}", EQ_CLASS, "let x: Bool = eq @Int 1 2;");
+    let (mut db, files) = TestDatabase::with_files(&[("main.cir", &src)]);
+    let file = files[0];
+    db.elaborated_body(db.value_def(file, "x"));
+    db.take_executed();
+
+    db.set_file_text(file, Arc::from(src.replace("eq @Int 1 2", "eq @Int 1 1")));
+    db.elaborated_body(db.value_def(file, "x"));
+    let executed = db.take_executed();
+    assert!(!executed.iter().any(|query| query.starts_with("class_instances(")), "{:?}", executed);
+}
+
+#[test]
+fn test_typeck_classes_missing_instance() {
+    let src = format!("{}{}", EQ_CLASS, "let bad: Bool = eq @(List Bool) (Nil @Bool) (Nil @Bool);");
+    let (db, files) = TestDatabase::with_files(&[("main.cir", &src)]);
+    // Found when resolving the context of the instance for `List Bool`
+    assert_eq!(db.type_error_messages(files[0]), ["no instance of `Eq` for `Bool`"]);
+}
+
+#[test]
+fn test_typeck_classes_overlapping_instances() {
+    let src = format!(
+        "{}{}",
+        EQ_CLASS,
+        r#"
+        instance Eq Int where eq = \x:Int.\y:Int. true;
+        let bad: Bool = eq @Int 1 2;
+    "#
+    );
+    let (db, files) = TestDatabase::with_files(&[("main.cir", &src)]);
+    assert_eq!(db.type_error_messages(files[0]), ["overlapping instances of `Eq` for `Int`"]);
+}
+
+#[test]
+fn test_typeck_infer() {
+    let src = r#"
+        let myId = \x. x;
+        let twice = \f.\x. f (f x);
+        let compose = \f.\g.\x. f (g x);
+        let add = \x.\y. x + y;
+        let half = \x. x / 2.0;
+        let one = myId 1;
+        let len = \xs. match xs { Nil -> 0, Cons x rest -> 1 + len rest };
+        let useLen: Int = len @Bool (Cons @Bool true (Nil @Bool));
+        let firsts = \ps. map (\p. match p { (x, y) -> x }) ps;
+        let useFirsts: List Int = firsts @Int @Bool (Nil @(Int, Bool));
+        let hasOne = \xs. eq xs (Cons 1 Nil);
+        let useHasOne: Bool = hasOne (Nil @Int);
+        let inc: Int -> Int = \x. x + 1;
+    "#;
+    let (db, files) = TestDatabase::with_prelude(&[("main.cir", src)]);
+    let (_, errors) = db.lower_file_with_errors(files[0]).unwrap();
+    assert!(errors.is_empty(), "{:?}", errors);
+    let ty = |name| db.checked_ty(db.value_def(files[0], name));
+    assert_eq!(ty("myId"), ty!(forall a. a -> a));
+    assert_eq!(ty("twice"), ty!(forall a. (a -> a) -> a -> a));
+    assert_eq!(ty("compose"), ty!(forall a. forall b. forall c. (a -> b) -> (c -> a) -> c -> b));
+    // The operands of arithmetic default to `Int`
+    assert_eq!(ty("add"), ty!(Int -> Int -> Int));
+    assert_eq!(ty("half"), ty!(Float -> Float));
+    assert_eq!(ty("one"), ty!(Int));
+    // The constraint on `eq` is resolved once the type of the body is known
+    assert_eq!(ty("inc"), ty!(Int -> Int));
+    for name in ["len", "useLen", "firsts", "useFirsts", "hasOne", "useHasOne"] {
+        ty(name);
+    }
+}
+
+#[test]
+fn test_typeck_infer_elaborates() {
+    let (db, files) = TestDatabase::with_prelude(&[("main.cir", "let f = \\x. singleton x;")]);
+    let body = db.elaborated_body(db.value_def(files[0], "f"));
+    // `\@a.\x:a. singleton @a x`
+    let (ty_binder, lambda) = match body[body.expr] {
+        ExprData::Lambda(binder, lambda) => (binder, lambda),
+        _ => panic!("expected a type lambda"),
+    };
+    assert!(matches!(body.binders[ty_binder], cir::BinderData::Ty(_)));
+    let (binder, app) = match body[lambda] {
+        ExprData::Lambda(binder, app) => (binder, app),
+        _ => panic!("expected a lambda"),
+    };
+    assert!(
+        matches!(&body.binders[binder], cir::BinderData::Val(ty) if *ty == TyKind::Var(Debruijn::INNER).intern())
+    );
+    let f = match body[app] {
+        ExprData::App(f, _) => f,
+        _ => panic!("expected an application"),
+    };
+    assert!(
+        matches!(body[f], ExprData::App(_, ty_arg) if matches!(body[ty_arg], ExprData::Type(_)))
+    );
+}
+
+#[test]
+fn test_typeck_infer_infinite_type() {
+    let (db, files) = TestDatabase::with_files(&[("main.cir", "let bad = \\x. x x;")]);
+    assert_eq!(
+        db.type_error_messages(files[0]),
+        ["infinite type in a definition without a signature"]
+    );
+}
+
+#[test]
+fn test_typeck_infer_mismatch() {
+    let (db, files) =
+        TestDatabase::with_files(&[("main.cir", "let bad = \\x. if x then 1 else x;")]);
+    assert_eq!(
+        db.type_error_messages(files[0]),
+        ["type mismatch in a definition without a signature: `Int` and `Bool`"]
+    );
+    // Bodies have no spans, the error is reported on the name of the definition
+    let err = &db.type_errors(files[0])[0];
+    assert_eq!(&db.file_text(files[0])[err.span.range()], "bad");
+}
+
+#[test]
+fn test_typeck_infer_mutual_recursion() {
+    let src = r#"
+        let even = \n. n == 0 || odd (n - 1);
+        let odd = \n. if n == 0 then false else even (n - 1);
+        let ping = \x. pong x;
+        let pong = \x. ping x;
+    "#;
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    let ty = |name| db.checked_ty(db.value_def(files[0], name));
+    assert_eq!(ty("odd"), ty!(Int -> Bool));
+    assert_eq!(ty("even"), ty!(Int -> Bool));
+    assert_eq!(ty("pong"), ty!(forall a. forall b. a -> b));
+    assert_eq!(ty("ping"), ty!(forall a. forall b. a -> b));
+}
+
+#[test]
+fn test_typeck_infer_constraints() {
+    let src = r#"
+        let same = \x.\y. eq x y;
+        let useSame: Bool = same @Int 1 2;
+        let elem = \x.\xs. match xs { Nil -> false, Cons y rest -> eq x y || elem x rest };
+        let isNil = \xs. eq xs Nil;
+        let showBoth = \x.\y. stringConcat (show x) (show y);
+        let showNil = show Nil;
+    "#;
+    let (db, files) = TestDatabase::with_prelude(&[("main.cir", src)]);
+    let (_, errors) = db.lower_file_with_errors(files[0]).unwrap();
+    assert!(errors.is_empty(), "{:?}", errors);
+    let display = |name| {
+        let ty = db.checked_ty(db.value_def(files[0], name));
+        let display = cir_parse::display_ty(&db, &ty).to_string();
+        display
+    };
+    assert_eq!(display("same"), "forall a. Eq a => a -> a -> Bool");
+    assert_eq!(display("useSame"), "Bool");
+    assert_eq!(display("elem"), "forall a. Eq a => a -> List a -> Bool");
+    assert_eq!(display("isNil"), "forall a. Eq (List a) => List a -> Bool");
+    assert_eq!(display("showBoth"), "forall a. forall b. Show a => Show b => a -> b -> String");
+    // The element type of `Nil` is ambiguous and defaults to `Int`
+    assert_eq!(display("showNil"), "String");
+}
+
+#[test]
+fn test_typeck_infer_let_generalised() {
+    let src = r#"
+        let pair = let f = \x. x in (f 1, f true);
+        let konst = \y. let g = \x. y in (g 1, g true);
+        let nested = let f = \x. let g = \y. (x, y) in g in (f 1 true, f true 1);
+        let numeric = \z. let h = \x. x + z in h 1;
+        let recursive = \x. let k = \u. recursive x in x;
+    "#;
+    let (db, files) = TestDatabase::with_files(&[("main.cir", src)]);
+    let ty = |name| db.checked_ty(db.value_def(files[0], name));
+    assert_eq!(ty("pair"), ty!((Int, Bool)));
+    // `y` is in scope so its type is not generalised by `g`
+    assert_eq!(ty("konst"), ty!(forall a. a -> (a, a)));
+    assert_eq!(ty("nested"), ty!(((Int, Bool), (Bool, Int))));
+    // The operand of `+` is not generalised
+    assert_eq!(ty("numeric"), ty!(Int -> Int));
+    // The recursive reference is applied to the parameter of `recursive` under that of `k`
+    assert_eq!(ty("recursive"), ty!(forall a. a -> a));
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+
+use cir::{ClassId, DataDefId, Kind, Span, Symbol, Ty};
+use cir_parse::{display_ty, LowerDatabase};
+
+/// A type error in an item. Bodies contain no spans, so the span is that of the name of the
+/// definition (or of the constructor or method) the error is found in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    pub span: Span,
+    pub kind: TypeErrorKind,
+}
+
+impl TypeError {
+    pub fn new(span: Span, kind: TypeErrorKind) -> Self {
+        Self { span, kind }
+    }
+
+    /// Displays the error using the names of data types and classes
+    pub fn display<'a>(&'a self, db: &'a dyn LowerDatabase) -> impl fmt::Display + 'a {
+        self.kind.display(db)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeErrorKind {
+    /// An expression whose type is not the expected one
+    Mismatch {
+        expected: Ty,
+        found: Ty,
+    },
+    /// A pattern that cannot match a value of the scrutinee's type
+    PatternMismatch(Ty),
+    /// A constructor pattern that does not bind each type parameter of the constructor
+    PatternTyParams,
+    /// A constructor pattern that does not bind each field of the constructor
+    PatternFields,
+    /// A constructor with a signature whose result type cannot be the type of the scrutinee
+    CtorResultMismatch(Ty),
+    /// The type of a `match` alternative that refers to a type parameter bound by its pattern
+    EscapingTyParam,
+    /// The operand of an arithmetic or comparison operator that is not an `Int` or a `Float`
+    NotNumeric(Ty),
+    /// The operand of `==` or `!=` that is not a scalar
+    NotScalar(Ty),
+    /// A polymorphic argument whose type arguments cannot be inferred from the parameter type
+    CannotInstantiate {
+        expected: Ty,
+        found: Ty,
+    },
+    /// A lambda binder without a type annotation whose type is not known from the context
+    UnannotatedBinder,
+    /// An application of an expression that is not a function
+    NotAFunction(Ty),
+    /// A polymorphic expression applied to a value rather than a type
+    ExpectedTyArg(Ty),
+    NoField(Ty, Symbol),
+    NotARecord(Ty),
+    /// `pack` annotated with, or `unpack` of, a type that is not existential
+    NotExistential(Ty),
+    /// An `unpack` whose type refers to the hidden type
+    HiddenTyEscapes,
+    /// `fold` annotated with, or `unfold` of, a type that is not recursive
+    NotRecursive(Ty),
+    KindMismatch {
+        expected: Kind,
+        found: Kind,
+    },
+    /// A type of kind `*` applied to an argument, e.g. `Int Int`
+    NotATyOperator,
+    /// A data type applied to more arguments than it has parameters
+    TooManyTyArgs(DataDefId),
+    /// A constraint `class ty` that no instance satisfies
+    NoInstance {
+        class: ClassId,
+        ty: Ty,
+    },
+    /// A constraint `class ty` that more than one instance satisfies
+    OverlappingInstances {
+        class: ClassId,
+        ty: Ty,
+    },
+    /// A unification variable that would have to contain itself, e.g. in `\x. x x`
+    InfiniteType,
+    /// Two types that do not unify in a definition without a signature, unsolved unification
+    /// variables are displayed as `()`
+    InferMismatch(Ty, Ty),
+    /// A field access in a definition without a signature on a record whose type is not known
+    UnknownRecord,
+    /// A feature of the language whose types are not inferred, e.g. "type lambdas"
+    NeedsSignature(&'static str),
+}
+
+impl TypeErrorKind {
+    pub fn display<'a>(&'a self, db: &'a dyn LowerDatabase) -> impl fmt::Display + 'a {
+        DisplayTypeError { db, kind: self }
+    }
+}
+
+struct DisplayTypeError<'a> {
+    db: &'a dyn LowerDatabase,
+    kind: &'a TypeErrorKind,
+}
+
+impl fmt::Display for DisplayTypeError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let db = self.db;
+        let ty = |ty| display_ty(db, ty);
+        let class = |class: &ClassId| db.items(class.file)[class.def].name.symbol.clone();
+        match self.kind {
+            TypeErrorKind::Mismatch { expected, found } =>
+                write!(f, "type mismatch: expected `{}`, found `{}`", ty(expected), ty(found)),
+            TypeErrorKind::PatternMismatch(scrutinee) =>
+                write!(f, "the pattern cannot match a value of type `{}`", ty(scrutinee)),
+            TypeErrorKind::PatternTyParams =>
+                write!(f, "the pattern must bind each type parameter of the constructor"),
+            TypeErrorKind::PatternFields =>
+                write!(f, "the pattern must bind each field of the constructor"),
+            TypeErrorKind::CtorResultMismatch(scrutinee) => write!(
+                f,
+                "the constructor's result type cannot be the type of the scrutinee `{}`",
+                ty(scrutinee)
+            ),
+            TypeErrorKind::EscapingTyParam => write!(
+                f,
+                "the type of the alternative refers to a type parameter of the constructor"
+            ),
+            TypeErrorKind::NotNumeric(operand) =>
+                write!(f, "arithmetic on the non-numeric type `{}`", ty(operand)),
+            TypeErrorKind::NotScalar(operand) =>
+                write!(f, "equality on the non-scalar type `{}`", ty(operand)),
+            TypeErrorKind::CannotInstantiate { expected, found } => write!(
+                f,
+                "cannot infer the type arguments to use `{}` as `{}`",
+                ty(found),
+                ty(expected)
+            ),
+            TypeErrorKind::UnannotatedBinder =>
+                write!(f, "cannot infer the type of a lambda binder without a type annotation"),
+            TypeErrorKind::NotAFunction(found) =>
+                write!(f, "expected a function, found `{}`", ty(found)),
+            TypeErrorKind::ExpectedTyArg(found) =>
+                write!(f, "expected a type argument for `{}`", ty(found)),
+            TypeErrorKind::NoField(record, name) =>
+                write!(f, "`{}` has no field `{}`", ty(record), name.as_str()),
+            TypeErrorKind::NotARecord(found) =>
+                write!(f, "field access on the non-record type `{}`", ty(found)),
+            TypeErrorKind::NotExistential(found) =>
+                write!(f, "expected an existential type, found `{}`", ty(found)),
+            TypeErrorKind::HiddenTyEscapes =>
+                write!(f, "the hidden type of `unpack` escapes its scope"),
+            TypeErrorKind::NotRecursive(found) =>
+                write!(f, "expected a recursive type, found `{}`", ty(found)),
+            TypeErrorKind::KindMismatch { expected, found } =>
+                write!(f, "kind mismatch: expected `{:?}`, found `{:?}`", expected, found),
+            TypeErrorKind::NotATyOperator =>
+                write!(f, "a type of kind `*` is applied to an argument"),
+            TypeErrorKind::TooManyTyArgs(data_def) => write!(
+                f,
+                "`{}` is applied to too many arguments",
+                db.items(data_def.file)[data_def.def].name.symbol.as_str()
+            ),
+            TypeErrorKind::NoInstance { class: c, ty: arg } =>
+                write!(f, "no instance of `{}` for `{}`", class(c).as_str(), ty(arg)),
+            TypeErrorKind::OverlappingInstances { class: c, ty: arg } =>
+                write!(f, "overlapping instances of `{}` for `{}`", class(c).as_str(), ty(arg)),
+            TypeErrorKind::InfiniteType =>
+                write!(f, "infinite type in a definition without a signature"),
+            TypeErrorKind::InferMismatch(l, r) => write!(
+                f,
+                "type mismatch in a definition without a signature: `{}` and `{}`",
+                ty(l),
+                ty(r)
+            ),
+            TypeErrorKind::UnknownRecord =>
+                write!(f, "the type of a record must be known to access its field"),
+            TypeErrorKind::NeedsSignature(feature) =>
+                write!(f, "{} in a definition without a signature", feature),
+        }
+    }
+}
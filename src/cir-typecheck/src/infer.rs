@@ -0,0 +1,822 @@
+//! Hindley-Milner inference of the types of definitions without a signature, e.g.
+//! `let twice = \f. \x. f (f x);` has type `forall a. (a -> a) -> a -> a`.
+//!
+//! Types are inferred by Algorithm W over the lowered body, with unification variables solved by
+//! union-find. Polymorphic definitions and constructors are instantiated with fresh variables and
+//! the type of the definition is generalised over the variables that remain unsolved, giving a
+//! prenex polymorphic type. The body is then made explicit System F, with a type lambda for each
+//! generalised variable, a type argument wherever something polymorphic is instantiated and a type
+//! for each lambda binder, so it can be checked and elaborated like any other body.
+//!
+//! Mutually recursive definitions without signatures are inferred together, each is generalised
+//! over the variables that remain unsolved in any of their types. The constraints of the
+//! instantiated types are generalised over too if they mention these variables, e.g.
+//! `let same = \x.\y. eq x y;` has type `forall a. Eq a => a -> a -> Bool`, and otherwise are
+//! resolved when the elaborated body is checked. A constrained variable that is in none of the
+//! types defaults to `Int`.
+//!
+//! A local `let` without an annotation is generalised over the variables of its type that are not
+//! free in the types of the binders in scope, e.g. `let f = \x. x in (f 1, f true)`. Its bound
+//! expression is put under a type lambda for each of them and each use of its binder is applied to
+//! type arguments. Variables of an operand or a constraint are not generalised by a `let`, as
+//! these are only checked once the whole definition has been inferred.
+//!
+//! Otherwise only prenex polymorphism is inferred: definitions without a signature cannot contain
+//! type lambdas, explicit type arguments, existential or recursive types or matches on
+//! constructors with signatures.
+
+use std::mem;
+use std::sync::Arc;
+
+use cir::subst;
+use cir::{ClassId, Debruijn, Kind, Symbol, Ty, TyKind, ValueDefId};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::db::TypecheckDatabase;
+use crate::error::TypeErrorKind;
+use crate::kind;
+use crate::normalize::normalize;
+use crate::prim_ty;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct Meta(u32);
+
+/// A type during inference, which may contain unification variables
+#[derive(Debug, Clone)]
+enum InferTy {
+    Meta(Meta),
+    /// A type constructor applied to its arguments, e.g. `Con::Fn` to the parameter and result
+    Con(Con, Vec<InferTy>),
+    /// A type without unification variables that is not decomposed by unification, e.g. a
+    /// scalar or a polymorphic type
+    Rigid(Ty),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Con {
+    Fn,
+    Adt(cir::DataDefId),
+    Tuple,
+    Record(Vec<Symbol>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Obligation {
+    /// The operand of an arithmetic or comparison operator
+    Numeric,
+    /// The operand of `==` or `!=`
+    Scalar,
+}
+
+struct InferCtxt<'db> {
+    db: &'db dyn TypecheckDatabase,
+    /// The mutually recursive definitions whose types are inferred
+    defs: Vec<ValueDefId>,
+    /// The body that is being inferred
+    body: cir::BodyData,
+    /// The solution of each unification variable, `None` while it is unsolved
+    metas: Vec<Option<InferTy>>,
+    binder_tys: FxHashMap<cir::Binder, InferTy>,
+    /// The value binders in scope
+    env: Vec<cir::Binder>,
+    /// The unification variables each local `let` is generalised over, by the order in which the
+    /// `let`s are inferred
+    let_params: Vec<Vec<Meta>>,
+    /// The binders of the generalised `let`s, with the index of the `let` in `let_params`
+    let_binders: FxHashMap<cir::Binder, usize>,
+    /// The `let`s (indices in `let_params`) whose bound expressions are being inferred, the
+    /// types recorded within them are under the type lambdas of these `let`s
+    lets: Vec<usize>,
+    /// The (monomorphic) types of `defs` within their bodies
+    def_tys: Vec<InferTy>,
+    /// The recursive references to `defs`, which are applied to the type parameters of their
+    /// generalised types, with the enclosing `lets`
+    rec_refs: Vec<(cir::Expr, Vec<usize>)>,
+    /// The type arguments inserted where something polymorphic is instantiated, with the
+    /// enclosing `lets`
+    ty_args: Vec<(cir::Expr, InferTy, Vec<usize>)>,
+    /// The lambda binders without a type annotation, with the enclosing `lets`
+    lambda_binders: Vec<(cir::Binder, InferTy, Vec<usize>)>,
+    /// The operand types that must be scalars, checked once the bodies have been inferred, with
+    /// the index of the definition they occur in
+    obligations: Vec<(InferTy, Obligation, usize)>,
+    /// The constraints of the instantiated types, e.g. `Eq a` for `eq`
+    constraints: Vec<(ClassId, InferTy)>,
+    /// The index of the definition whose body is being inferred
+    current: usize,
+    /// The errors found in each definition
+    errors: Vec<Vec<TypeErrorKind>>,
+}
+
+/// A body of `InferCtxt::defs` that has been inferred, with the types to make explicit once the
+/// unification variables are solved
+struct InferredBody {
+    body: cir::BodyData,
+    rec_refs: Vec<(cir::Expr, Vec<usize>)>,
+    ty_args: Vec<(cir::Expr, InferTy, Vec<usize>)>,
+    lambda_binders: Vec<(cir::Binder, InferTy, Vec<usize>)>,
+}
+
+pub(crate) fn infer_value_def(
+    db: &dyn TypecheckDatabase,
+    def: ValueDefId,
+) -> (Ty, Arc<cir::BodyData>, Arc<[TypeErrorKind]>) {
+    let scc = db.infer_scc(recursive_defs(db, def)[0]);
+    let (_, ty, body, errors) = scc.iter().find(|(member, ..)| *member == def).unwrap();
+    (Ty::clone(ty), Arc::clone(body), Arc::clone(errors))
+}
+
+/// A definition of an SCC with its inferred type, its elaborated body and its type errors
+pub type InferredDef = (ValueDefId, Ty, Arc<cir::BodyData>, Arc<[TypeErrorKind]>);
+
+pub(crate) fn infer_scc(db: &dyn TypecheckDatabase, root: ValueDefId) -> Arc<[InferredDef]> {
+    let defs = recursive_defs(db, root);
+    let mut icx = InferCtxt {
+        db,
+        defs: defs.clone(),
+        body: cir::BodyData::clone(&db.value_def_body(root)),
+        metas: vec![],
+        binder_tys: Default::default(),
+        env: vec![],
+        let_params: vec![],
+        let_binders: Default::default(),
+        lets: vec![],
+        def_tys: vec![],
+        rec_refs: vec![],
+        ty_args: vec![],
+        lambda_binders: vec![],
+        obligations: vec![],
+        constraints: vec![],
+        current: 0,
+        errors: defs.iter().map(|_| vec![]).collect(),
+    };
+    icx.def_tys = defs.iter().map(|_| icx.new_meta()).collect();
+    let mut bodies = vec![];
+    for (i, &def) in defs.iter().enumerate() {
+        icx.current = i;
+        icx.body = cir::BodyData::clone(&db.value_def_body(def));
+        icx.binder_tys.clear();
+        icx.let_binders.clear();
+        let ty = icx.infer(icx.body.expr);
+        let def_ty = icx.def_tys[i].clone();
+        icx.unify(&def_ty, &ty);
+        bodies.push(InferredBody {
+            body: cir::BodyData::clone(&icx.body),
+            rec_refs: mem::take(&mut icx.rec_refs),
+            ty_args: mem::take(&mut icx.ty_args),
+            lambda_binders: mem::take(&mut icx.lambda_binders),
+        });
+    }
+    icx.check_obligations();
+    let errors = mem::take(&mut icx.errors);
+    let inferred = defs.into_iter().zip(icx.generalise(bodies)).zip(errors);
+    inferred
+        .map(|((def, (ty, body)), errors)| {
+            // A definition with errors has the error type so that its uses cause no further
+            // errors
+            let ty = if errors.is_empty() { ty } else { TyKind::Err.intern() };
+            (def, ty, body, Arc::from(errors))
+        })
+        .collect()
+}
+
+/// The definitions without signatures that `def` refers to (transitively) and that refer back to
+/// it, including `def` itself, ordered by their files and positions so that each member of the
+/// component gives the same result
+fn recursive_defs(db: &dyn TypecheckDatabase, def: ValueDefId) -> Vec<ValueDefId> {
+    let mut defs = reachable_defs(db, def)
+        .into_iter()
+        .filter(|&other| other != def && reachable_defs(db, other).contains(&def))
+        .collect::<Vec<_>>();
+    defs.push(def);
+    defs.sort_by_key(|def| (def.file, def.def.into_raw()));
+    defs
+}
+
+/// The definitions without signatures that `def` refers to, transitively
+fn reachable_defs(db: &dyn TypecheckDatabase, def: ValueDefId) -> FxHashSet<ValueDefId> {
+    let mut reachable = FxHashSet::default();
+    let mut stack = vec![def];
+    while let Some(def) = stack.pop() {
+        for (_, expr) in db.value_def_body(def).exprs.iter() {
+            match *expr {
+                cir::ExprData::Def(other)
+                    if db.value_def_sig(other).is_none() && reachable.insert(other) =>
+                    stack.push(other),
+                _ => {}
+            }
+        }
+    }
+    reachable
+}
+
+impl<'db> InferCtxt<'db> {
+    /// Records an error in the current definition, the erroneous expression has the error type
+    fn error(&mut self, kind: TypeErrorKind) -> InferTy {
+        self.errors[self.current].push(kind);
+        rigid(TyKind::Err)
+    }
+
+    /// Converts a type of the body, see `from_ty`
+    fn infer_ty(&mut self, ty: &Ty, args: &[InferTy]) -> InferTy {
+        from_ty(ty, args).unwrap_or_else(|| {
+            self.error(TypeErrorKind::NeedsSignature("types that are not prenex polymorphic"))
+        })
+    }
+
+    /// Converts an annotation of a binder, checking its kind first
+    fn infer_annotation(&mut self, ty: &Ty) -> InferTy {
+        match kind::check_kind(self.db, &[], ty, &Kind::Type) {
+            Ok(()) => self.infer_ty(&normalize(ty), &[]),
+            Err(err) => self.error(err),
+        }
+    }
+
+    fn new_meta(&mut self) -> InferTy {
+        self.metas.push(None);
+        InferTy::Meta(Meta(self.metas.len() as u32 - 1))
+    }
+
+    /// Brings `binder` into scope, until the end of the expression that binds it
+    fn bind(&mut self, binder: cir::Binder, ty: InferTy) {
+        self.binder_tys.insert(binder, ty);
+        self.env.push(binder);
+    }
+
+    /// Follows the solutions of unification variables until a type that is not a solved
+    /// variable, compressing the path
+    fn resolve(&mut self, ty: &InferTy) -> InferTy {
+        match ty {
+            &InferTy::Meta(meta) => match self.metas[meta.0 as usize].clone() {
+                Some(solution) => {
+                    let resolved = self.resolve(&solution);
+                    self.metas[meta.0 as usize] = Some(resolved.clone());
+                    resolved
+                }
+                None => ty.clone(),
+            },
+            _ => ty.clone(),
+        }
+    }
+
+    fn occurs(&mut self, meta: Meta, ty: &InferTy) -> bool {
+        match self.resolve(ty) {
+            InferTy::Meta(other) => other == meta,
+            InferTy::Con(_, args) => args.iter().any(|arg| self.occurs(meta, arg)),
+            InferTy::Rigid(_) => false,
+        }
+    }
+
+    fn unify(&mut self, l: &InferTy, r: &InferTy) {
+        let (l, r) = (self.resolve(l), self.resolve(r));
+        match (&l, &r) {
+            (InferTy::Meta(a), InferTy::Meta(b)) if a == b => {}
+            (&InferTy::Meta(meta), ty) | (ty, &InferTy::Meta(meta)) => {
+                if self.occurs(meta, ty) {
+                    self.error(TypeErrorKind::InfiniteType);
+                    return;
+                }
+                self.metas[meta.0 as usize] = Some(ty.clone());
+            }
+            (InferTy::Con(l_con, ls), InferTy::Con(r_con, rs))
+                if l_con == r_con && ls.len() == rs.len() =>
+                for (l, r) in ls.iter().zip(rs) {
+                    self.unify(l, r);
+                },
+            (InferTy::Rigid(l), InferTy::Rigid(r)) if l == r => {}
+            (InferTy::Rigid(ty), _) | (_, InferTy::Rigid(ty))
+                if matches!(ty.kind(), TyKind::Err) => {}
+            _ => {
+                let (l, r) = (self.zonk(&l, &[]), self.zonk(&r, &[]));
+                self.error(TypeErrorKind::InferMismatch(l, r));
+            }
+        }
+    }
+
+    /// Instantiates the prenex type parameters of `ty`, the type of `expr`, with fresh
+    /// unification variables and applies `expr` to them. Constraints are recorded to be
+    /// generalised over, otherwise they are resolved when the elaborated body is checked.
+    fn instantiate(&mut self, expr: cir::Expr, ty: &Ty) -> InferTy {
+        let mut ty = normalize(ty);
+        let mut args = vec![];
+        while let TyKind::ForAll(kind, body_ty) = ty.kind() {
+            if *kind != Kind::Type {
+                let feature = "instantiations of type parameters of higher kinds";
+                return self.error(TypeErrorKind::NeedsSignature(feature));
+            }
+            args.push(self.new_meta());
+            ty = Ty::clone(body_ty);
+        }
+        let mut constraints = vec![];
+        while let TyKind::Constrained(class, arg, inner) = ty.kind() {
+            constraints.push((*class, Ty::clone(arg)));
+            ty = Ty::clone(inner);
+        }
+        self.apply_ty_args(expr, &args);
+        // The innermost variable is the last parameter
+        args.reverse();
+        for (class, arg) in constraints {
+            let arg = self.infer_ty(&normalize(&arg), &args);
+            self.constraints.push((class, arg));
+        }
+        self.infer_ty(&ty, &args)
+    }
+
+    /// Instantiates the parameters of the generalised `let` with index `id` in `ty`, the type of
+    /// its binder, with fresh unification variables and applies `expr`, a use of the binder, to
+    /// them
+    fn instantiate_let(&mut self, expr: cir::Expr, id: usize, ty: &InferTy) -> InferTy {
+        let params = self.let_params[id].clone();
+        let args = params.iter().map(|_| self.new_meta()).collect::<Vec<_>>();
+        self.apply_ty_args(expr, &args);
+        self.replace_metas(ty, &params, &args)
+    }
+
+    /// Applies `expr` to the types `args`, which are made explicit once they are solved
+    fn apply_ty_args(&mut self, expr: cir::Expr, args: &[InferTy]) {
+        if args.is_empty() {
+            return;
+        }
+        let mut f = self.move_expr(expr);
+        for arg in args {
+            let ty_arg = self.body.exprs.alloc(cir::ExprData::Type(TyKind::Err.intern()));
+            self.ty_args.push((ty_arg, arg.clone(), self.lets.clone()));
+            f = self.body.exprs.alloc(cir::ExprData::App(f, ty_arg));
+        }
+        self.body.exprs[expr] = self.body[f].clone();
+    }
+
+    /// Replaces the unification variables `params` in `ty` by `args`
+    fn replace_metas(&mut self, ty: &InferTy, params: &[Meta], args: &[InferTy]) -> InferTy {
+        match self.resolve(ty) {
+            InferTy::Meta(meta) => match params.iter().position(|&param| param == meta) {
+                Some(i) => args[i].clone(),
+                None => InferTy::Meta(meta),
+            },
+            InferTy::Con(con, tys) => {
+                let tys = tys.iter().map(|ty| self.replace_metas(ty, params, args)).collect();
+                InferTy::Con(con, tys)
+            }
+            ty @ InferTy::Rigid(_) => ty,
+        }
+    }
+
+    fn move_expr(&mut self, expr: cir::Expr) -> cir::Expr {
+        let data = self.body[expr].clone();
+        self.body.exprs.alloc(data)
+    }
+
+    /// Infers the type of `expr`, the binders it binds are only in scope within it
+    fn infer(&mut self, expr: cir::Expr) -> InferTy {
+        let scope = self.env.len();
+        let ty = self.infer_expr(expr);
+        self.env.truncate(scope);
+        ty
+    }
+
+    fn infer_expr(&mut self, expr: cir::Expr) -> InferTy {
+        match self.body[expr] {
+            cir::ExprData::Var(binder) => match self.binder_tys.get(&binder).cloned() {
+                Some(ty) => match self.let_binders.get(&binder) {
+                    Some(&id) => self.instantiate_let(expr, id, &ty),
+                    None => ty,
+                },
+                None => rigid(TyKind::Err),
+            },
+            cir::ExprData::Def(def) if self.defs.contains(&def) => {
+                self.rec_refs.push((expr, self.lets.clone()));
+                let i = self.defs.iter().position(|&other| other == def).unwrap();
+                self.def_tys[i].clone()
+            }
+            cir::ExprData::Def(def) => {
+                let ty = self.db.value_def_ty(def);
+                self.instantiate(expr, &ty)
+            }
+            cir::ExprData::Ctor(variant) => {
+                let ty = self.db.ctor_ty(variant);
+                self.instantiate(expr, &ty)
+            }
+            cir::ExprData::Method(method) => {
+                let ty = self.db.method_ty(method);
+                self.instantiate(expr, &ty)
+            }
+            cir::ExprData::Instance(_) => unreachable!("instance in the body of a definition"),
+            cir::ExprData::Lit(ref lit) => rigid(TyKind::Scalar(match lit {
+                cir::Lit::Bool(_) => cir::Scalar::Bool,
+                cir::Lit::Int(_) => cir::Scalar::Int,
+                cir::Lit::Float(_) => cir::Scalar::Float,
+                cir::Lit::Char(_) => cir::Scalar::Char,
+                cir::Lit::String(_) => cir::Scalar::String,
+            })),
+            cir::ExprData::Lambda(binder, body) => {
+                let param_ty = match self.body.binders[binder] {
+                    cir::BinderData::Val(ref ty) => {
+                        let ty = Ty::clone(ty);
+                        self.infer_annotation(&ty)
+                    }
+                    cir::BinderData::Infer => {
+                        let ty = self.new_meta();
+                        self.lambda_binders.push((binder, ty.clone(), self.lets.clone()));
+                        ty
+                    }
+                    cir::BinderData::Ty(_) =>
+                        return self.error(TypeErrorKind::NeedsSignature("type lambdas")),
+                    cir::BinderData::Pat | cir::BinderData::Let =>
+                        unreachable!("lambda with a pattern or let binder"),
+                };
+                self.bind(binder, param_ty.clone());
+                let body_ty = self.infer(body);
+                InferTy::Con(Con::Fn, vec![param_ty, body_ty])
+            }
+            cir::ExprData::App(f, x) => {
+                if let cir::ExprData::Type(_) = self.body[x] {
+                    return self.error(TypeErrorKind::NeedsSignature("explicit type arguments"));
+                }
+                let f_ty = self.infer(f);
+                let x_ty = self.infer(x);
+                let ret_ty = self.new_meta();
+                self.unify(&f_ty, &InferTy::Con(Con::Fn, vec![x_ty, ret_ty.clone()]));
+                ret_ty
+            }
+            cir::ExprData::Case(scrutinee, ref alts) => {
+                let alts = alts.clone();
+                let scrutinee_ty = self.infer(scrutinee);
+                let ty = self.new_meta();
+                for alt in &alts {
+                    let scope = self.env.len();
+                    let alt_ty = self.infer_alt(&scrutinee_ty, alt);
+                    self.env.truncate(scope);
+                    self.unify(&ty, &alt_ty);
+                }
+                ty
+            }
+            cir::ExprData::If(c, t, e) => {
+                let c_ty = self.infer(c);
+                self.unify(&c_ty, &rigid(TyKind::Scalar(cir::Scalar::Bool)));
+                let ty = self.infer(t);
+                let e_ty = self.infer(e);
+                self.unify(&ty, &e_ty);
+                ty
+            }
+            cir::ExprData::Let(binder, e1, e2) => {
+                if let cir::BinderData::Val(annotation) = &self.body.binders[binder] {
+                    let annotation = Ty::clone(annotation);
+                    let ty = self.infer(e1);
+                    let annotation = self.infer_annotation(&annotation);
+                    self.unify(&ty, &annotation);
+                    self.bind(binder, annotation);
+                } else {
+                    self.infer_generalised_let(expr, binder, e1, e2);
+                }
+                self.infer(e2)
+            }
+            cir::ExprData::LetRec(ref bindings, e) => {
+                let bindings = bindings.clone();
+                for &(binder, _) in &bindings {
+                    let ty = match &self.body.binders[binder] {
+                        cir::BinderData::Val(ty) => {
+                            let ty = Ty::clone(ty);
+                            self.infer_annotation(&ty)
+                        }
+                        _ => unreachable!("`let rec` binder without a type annotation"),
+                    };
+                    self.bind(binder, ty);
+                }
+                for (binder, expr) in bindings {
+                    let ty = self.infer(expr);
+                    let binder_ty = self.binder_tys[&binder].clone();
+                    self.unify(&ty, &binder_ty);
+                }
+                self.infer(e)
+            }
+            cir::ExprData::BinOp(op, l, r) => {
+                let l_ty = self.infer(l);
+                let r_ty = self.infer(r);
+                self.unify(&l_ty, &r_ty);
+                match op {
+                    cir::BinOp::Add
+                    | cir::BinOp::Sub
+                    | cir::BinOp::Mul
+                    | cir::BinOp::Div
+                    | cir::BinOp::Rem => {
+                        self.obligations.push((l_ty.clone(), Obligation::Numeric, self.current));
+                        l_ty
+                    }
+                    cir::BinOp::Lt | cir::BinOp::Le | cir::BinOp::Gt | cir::BinOp::Ge => {
+                        self.obligations.push((l_ty, Obligation::Numeric, self.current));
+                        rigid(TyKind::Scalar(cir::Scalar::Bool))
+                    }
+                    cir::BinOp::Eq | cir::BinOp::Ne => {
+                        self.obligations.push((l_ty, Obligation::Scalar, self.current));
+                        rigid(TyKind::Scalar(cir::Scalar::Bool))
+                    }
+                    cir::BinOp::And | cir::BinOp::Or => {
+                        let bool_ty = rigid(TyKind::Scalar(cir::Scalar::Bool));
+                        self.unify(&l_ty, &bool_ty);
+                        bool_ty
+                    }
+                }
+            }
+            cir::ExprData::UnOp(op, expr) => {
+                let ty = self.infer(expr);
+                match op {
+                    cir::UnOp::Not => self.unify(&ty, &rigid(TyKind::Scalar(cir::Scalar::Bool))),
+                    cir::UnOp::Neg =>
+                        self.obligations.push((ty.clone(), Obligation::Numeric, self.current)),
+                }
+                ty
+            }
+            cir::ExprData::Prim(prim) => self.infer_ty(&prim_ty(prim), &[]),
+            cir::ExprData::Tuple(ref exprs) => {
+                let exprs = exprs.clone();
+                InferTy::Con(Con::Tuple, exprs.into_iter().map(|expr| self.infer(expr)).collect())
+            }
+            cir::ExprData::Record(ref fields) => {
+                let fields = fields.clone();
+                let names = fields.iter().map(|(name, _)| name.clone()).collect();
+                let tys = fields.into_iter().map(|(_, expr)| self.infer(expr)).collect();
+                InferTy::Con(Con::Record(names), tys)
+            }
+            cir::ExprData::Field(expr, ref name) => {
+                let name = name.clone();
+                let ty = self.infer(expr);
+                match self.resolve(&ty) {
+                    InferTy::Con(Con::Record(names), tys) =>
+                        match names.iter().position(|field| *field == name) {
+                            Some(i) => tys[i].clone(),
+                            None => {
+                                let ty = self.zonk(&ty, &[]);
+                                self.error(TypeErrorKind::NoField(ty, name))
+                            }
+                        },
+                    InferTy::Rigid(ty) if matches!(ty.kind(), TyKind::Err) => InferTy::Rigid(ty),
+                    InferTy::Meta(_) => self.error(TypeErrorKind::UnknownRecord),
+                    ty => {
+                        let ty = self.zonk(&ty, &[]);
+                        self.error(TypeErrorKind::NotARecord(ty))
+                    }
+                }
+            }
+            cir::ExprData::Pack(..)
+            | cir::ExprData::Unpack(..)
+            | cir::ExprData::Fold(..)
+            | cir::ExprData::Unfold(_) =>
+                self.error(TypeErrorKind::NeedsSignature("existential and recursive types")),
+            cir::ExprData::Type(_) => unreachable!("found type in expression position"),
+            cir::ExprData::Err => rigid(TyKind::Err),
+        }
+    }
+
+    /// Infers the type of `e1`, the expression bound by the `let` `expr` without an annotation,
+    /// and generalises it over the unsolved variables that are not free in the types of the
+    /// binders in scope, the definitions or the obligations and constraints. `e1` is put under a
+    /// type lambda for each of them.
+    fn infer_generalised_let(
+        &mut self,
+        expr: cir::Expr,
+        binder: cir::Binder,
+        e1: cir::Expr,
+        e2: cir::Expr,
+    ) {
+        let id = self.let_params.len();
+        self.let_params.push(vec![]);
+        self.lets.push(id);
+        let ty = self.infer(e1);
+        self.lets.pop();
+        let env_tys = self.env.iter().map(|binder| self.binder_tys[binder].clone());
+        let obligation_tys = self.obligations.iter().map(|(ty, ..)| ty.clone());
+        let constraint_tys = self.constraints.iter().map(|(_, ty)| ty.clone());
+        let fixed_tys = env_tys
+            .chain(self.def_tys.iter().cloned())
+            .chain(obligation_tys)
+            .chain(constraint_tys)
+            .collect::<Vec<_>>();
+        let mut fixed = vec![];
+        for ty in &fixed_tys {
+            self.unsolved(ty, &mut fixed);
+        }
+        let mut params = vec![];
+        self.unsolved(&ty, &mut params);
+        params.retain(|param| !fixed.contains(param));
+        if !params.is_empty() {
+            let mut bound = e1;
+            for _ in &params {
+                let ty_binder = self.body.binders.alloc(cir::BinderData::Ty(Kind::Type));
+                bound = self.body.exprs.alloc(cir::ExprData::Lambda(ty_binder, bound));
+            }
+            self.body.exprs[expr] = cir::ExprData::Let(binder, bound, e2);
+            self.let_binders.insert(binder, id);
+        }
+        self.let_params[id] = params;
+        self.bind(binder, ty);
+    }
+
+    fn infer_alt(&mut self, scrutinee_ty: &InferTy, alt: &cir::Alt) -> InferTy {
+        match &alt.pat {
+            cir::Pat::Variant(variant, ty_binders, binders) => {
+                let items = self.db.items(variant.data_def.file);
+                let data_def = &items[variant.data_def.def];
+                let variant_data = &data_def.variants[variant.variant];
+                let error = if !ty_binders.is_empty() || variant_data.result.is_some() {
+                    let feature = "matches on constructors with signatures";
+                    Some(TypeErrorKind::NeedsSignature(feature))
+                } else if variant_data.fields.len() != binders.len() {
+                    Some(TypeErrorKind::PatternFields)
+                } else {
+                    kind::check_variant(self.db, data_def, variant_data).err()
+                };
+                if let Some(error) = error {
+                    self.error(error);
+                    // The binders have the error type
+                    for &binder in binders.iter() {
+                        self.bind(binder, rigid(TyKind::Err));
+                    }
+                    return self.infer(alt.body);
+                }
+                let params = (0..data_def.arity).map(|_| self.new_meta()).collect::<Vec<_>>();
+                let adt_ty = InferTy::Con(Con::Adt(variant.data_def), params.clone());
+                self.unify(scrutinee_ty, &adt_ty);
+                // The fields refer to the last parameter as the innermost variable
+                let args = params.into_iter().rev().collect::<Vec<_>>();
+                for (field, &binder) in variant_data.fields.iter().zip(binders) {
+                    let ty = self.infer_ty(&normalize(field), &args);
+                    self.bind(binder, ty);
+                }
+            }
+            cir::Pat::Tuple(binders) => {
+                let tys = binders.iter().map(|_| self.new_meta()).collect::<Vec<_>>();
+                self.unify(scrutinee_ty, &InferTy::Con(Con::Tuple, tys.clone()));
+                for (&binder, ty) in binders.iter().zip(tys) {
+                    self.bind(binder, ty);
+                }
+            }
+            cir::Pat::Err(_, binders) =>
+                for &binder in binders {
+                    self.bind(binder, rigid(TyKind::Err));
+                },
+        }
+        self.infer(alt.body)
+    }
+
+    /// Checks that the operands of the operators are scalars, an operand whose type is still
+    /// unknown defaults to `Int`
+    fn check_obligations(&mut self) {
+        for (ty, obligation, def) in mem::take(&mut self.obligations) {
+            self.current = def;
+            let ty = match self.resolve(&ty) {
+                ty @ InferTy::Meta(_) => {
+                    self.unify(&ty, &rigid(TyKind::Scalar(cir::Scalar::Int)));
+                    continue;
+                }
+                InferTy::Rigid(ty) => match (ty.kind(), obligation) {
+                    (TyKind::Scalar(cir::Scalar::Int | cir::Scalar::Float) | TyKind::Err, _)
+                    | (TyKind::Scalar(_), Obligation::Scalar) => continue,
+                    _ => ty,
+                },
+                ty @ InferTy::Con(..) => self.zonk(&ty, &[]),
+            };
+            self.error(match obligation {
+                Obligation::Numeric => TypeErrorKind::NotNumeric(ty),
+                Obligation::Scalar => TypeErrorKind::NotScalar(ty),
+            });
+        }
+    }
+
+    /// Generalises the types of the definitions over their unsolved unification variables (the
+    /// first to occur is bound by the outermost `forall`) and the constraints on them, and makes
+    /// the types in the bodies explicit
+    fn generalise(mut self, bodies: Vec<InferredBody>) -> Vec<(Ty, Arc<cir::BodyData>)> {
+        let mut params = vec![];
+        for ty in self.def_tys.clone() {
+            self.unsolved(&ty, &mut params);
+        }
+        let mut constraints: Vec<(ClassId, Ty)> = vec![];
+        for (class, arg) in mem::take(&mut self.constraints) {
+            let mut metas = vec![];
+            self.unsolved(&arg, &mut metas);
+            // The variables in none of the types are ambiguous
+            for &meta in metas.iter().filter(|meta| !params.contains(meta)) {
+                self.unify(&InferTy::Meta(meta), &rigid(TyKind::Scalar(cir::Scalar::Int)));
+            }
+            // Constraints without parameters are resolved when the elaborated body is checked
+            if !metas.iter().any(|meta| params.contains(meta)) {
+                continue;
+            }
+            let arg = self.zonk(&arg, &params);
+            if !constraints.contains(&(class, Ty::clone(&arg))) {
+                constraints.push((class, arg));
+            }
+        }
+        let mut generalised = vec![];
+        for (def_ty, mut inferred) in self.def_tys.clone().iter().zip(bodies) {
+            let body = &mut inferred.body;
+            for (binder, ty, lets) in inferred.lambda_binders {
+                let ty_params = self.ty_params(&params, &lets);
+                body.binders[binder] = cir::BinderData::Val(self.zonk(&ty, &ty_params));
+            }
+            for (expr, ty, lets) in inferred.ty_args {
+                let ty_params = self.ty_params(&params, &lets);
+                body.exprs[expr] = cir::ExprData::Type(self.zonk(&ty, &ty_params));
+            }
+            // The body is under a type lambda for each parameter and no other type binders than
+            // those of the generalised `let`s
+            for (expr, lets) in inferred.rec_refs {
+                let shift = lets.iter().map(|&id| self.let_params[id].len() as u32).sum::<u32>();
+                let mut f = body.exprs.alloc(body[expr].clone());
+                for i in (shift..shift + params.len() as u32).rev() {
+                    let var = TyKind::Var(Debruijn::new(i)).intern();
+                    let ty_arg = body.exprs.alloc(cir::ExprData::Type(var));
+                    f = body.exprs.alloc(cir::ExprData::App(f, ty_arg));
+                }
+                body.exprs[expr] = body[f].clone();
+            }
+            for _ in &params {
+                let binder = body.binders.alloc(cir::BinderData::Ty(Kind::Type));
+                body.expr = body.exprs.alloc(cir::ExprData::Lambda(binder, body.expr));
+            }
+            let ty =
+                constraints.iter().rev().fold(self.zonk(def_ty, &params), |ty, (class, arg)| {
+                    TyKind::Constrained(*class, Ty::clone(arg), ty).intern()
+                });
+            let ty = params.iter().fold(ty, |ty, _| TyKind::ForAll(Kind::Type, ty).intern());
+            generalised.push((ty, Arc::new(inferred.body)));
+        }
+        generalised
+    }
+
+    /// The type variables in scope within the `let`s `lets` of a definition generalised over
+    /// `params`, the first is bound by the outermost type lambda
+    fn ty_params(&self, params: &[Meta], lets: &[usize]) -> Vec<Meta> {
+        let let_params = lets.iter().flat_map(|&id| self.let_params[id].iter().copied());
+        params.iter().copied().chain(let_params).collect()
+    }
+
+    /// Collects the unsolved unification variables of `ty` in the order they occur
+    fn unsolved(&mut self, ty: &InferTy, acc: &mut Vec<Meta>) {
+        match self.resolve(ty) {
+            InferTy::Meta(meta) =>
+                if !acc.contains(&meta) {
+                    acc.push(meta)
+                },
+            InferTy::Con(_, args) =>
+                for arg in &args {
+                    self.unsolved(arg, acc);
+                },
+            InferTy::Rigid(_) => {}
+        }
+    }
+
+    /// Converts `ty` to a type under a binder for each of `params`. Other unsolved unification
+    /// variables are unconstrained, so any type will do and they are `()`.
+    fn zonk(&mut self, ty: &InferTy, params: &[Meta]) -> Ty {
+        match self.resolve(ty) {
+            InferTy::Meta(meta) => match params.iter().position(|&param| param == meta) {
+                Some(i) => TyKind::Var(Debruijn::new((params.len() - 1 - i) as u32)).intern(),
+                None => TyKind::Tuple(vec![]).intern(),
+            },
+            InferTy::Con(con, args) => {
+                let mut args =
+                    args.iter().map(|arg| self.zonk(arg, params)).collect::<Vec<_>>().into_iter();
+                match con {
+                    Con::Fn => {
+                        let (param, ret) = (args.next().unwrap(), args.next().unwrap());
+                        TyKind::Fn(param, ret).intern()
+                    }
+                    Con::Adt(adt) => TyKind::Adt(adt, args.collect()).intern(),
+                    Con::Tuple => TyKind::Tuple(args.collect()).intern(),
+                    Con::Record(names) =>
+                        TyKind::Record(names.into_iter().zip(args).collect()).intern(),
+                }
+            }
+            InferTy::Rigid(ty) => ty,
+        }
+    }
+}
+
+fn rigid(kind: TyKind) -> InferTy {
+    InferTy::Rigid(kind.intern())
+}
+
+/// Converts the (normalized) type `ty`, where the variable with index `i` is instantiated with
+/// `args[i]`. Returns `None` if one of `args` is used under a binder, i.e. if instantiating `ty`
+/// would not give a monomorphic type.
+fn from_ty(ty: &Ty, args: &[InferTy]) -> Option<InferTy> {
+    let con = |con, tys: &[Ty]| {
+        let tys = tys.iter().map(|ty| from_ty(ty, args)).collect::<Option<_>>()?;
+        Some(InferTy::Con(con, tys))
+    };
+    match ty.kind() {
+        TyKind::Var(var) if (var.index() as usize) < args.len() =>
+            Some(args[var.index() as usize].clone()),
+        TyKind::Fn(param, ret) => con(Con::Fn, &[Ty::clone(param), Ty::clone(ret)]),
+        TyKind::Adt(adt, tys) => con(Con::Adt(*adt), tys),
+        TyKind::Tuple(tys) => con(Con::Tuple, tys),
+        TyKind::Record(fields) => {
+            let (names, tys): (Vec<_>, Vec<_>) = fields.iter().cloned().unzip();
+            con(Con::Record(names), &tys)
+        }
+        _ => (0..args.len())
+            .try_fold(Ty::clone(ty), |ty, _| subst::shift_out(&ty))
+            .map(InferTy::Rigid),
+    }
+}
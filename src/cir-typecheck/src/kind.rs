@@ -0,0 +1,131 @@
+use cir::{ClassId, DataDefData, Kind, Ty, TyKind, VariantData};
+
+use crate::error::TypeErrorKind;
+use crate::TypecheckDatabase;
+
+/// Checks that `ty` has kind `kind`, where `env` are the kinds of the type variables in scope
+/// (the innermost last), returning the first error found. Types must be checked before they are normalized, as normalizing an
+/// ill-kinded type such as `(\a. a a) (\a. a a)` need not terminate.
+pub(crate) fn check_kind(
+    db: &dyn TypecheckDatabase,
+    env: &[Kind],
+    ty: &Ty,
+    kind: &Kind,
+) -> Result<(), TypeErrorKind> {
+    let mut kcx = KindCtxt { db, env: env.to_vec() };
+    kcx.check(ty, kind)
+}
+
+/// Checks that the fields and result type of `variant`, a constructor of `data_def`, are types
+pub(crate) fn check_variant(
+    db: &dyn TypecheckDatabase,
+    data_def: &DataDefData,
+    variant: &VariantData,
+) -> Result<(), TypeErrorKind> {
+    let mut env = vec![Kind::Type; data_def.arity];
+    env.extend(variant.ty_params.iter().cloned());
+    for ty in variant.fields.iter().chain(variant.result.iter().flatten()) {
+        check_kind(db, &env, ty, &Kind::Type)?;
+    }
+    Ok(())
+}
+
+/// The kind of the parameter of `class`, e.g. `*` for `Eq`
+pub(crate) fn class_kind(db: &dyn TypecheckDatabase, class: ClassId) -> Kind {
+    let items = db.items(class.file);
+    items[class.def].kind.clone()
+}
+
+struct KindCtxt<'db> {
+    db: &'db dyn TypecheckDatabase,
+    env: Vec<Kind>,
+}
+
+impl KindCtxt<'_> {
+    fn check(&mut self, ty: &Ty, kind: &Kind) -> Result<(), TypeErrorKind> {
+        match self.infer(ty)? {
+            Some(found) if found != *kind =>
+                Err(TypeErrorKind::KindMismatch { expected: kind.clone(), found }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The kind of `ty`, or `None` if it is (an application of) the error type, which has any
+    /// kind
+    fn infer(&mut self, ty: &Ty) -> Result<Option<Kind>, TypeErrorKind> {
+        let kind = match ty.kind() {
+            TyKind::Scalar(_) => Kind::Type,
+            TyKind::Fn(l, r) => {
+                self.check(l, &Kind::Type)?;
+                self.check(r, &Kind::Type)?;
+                Kind::Type
+            }
+            TyKind::Var(var) => {
+                let index = self.env.len() - 1 - var.index() as usize;
+                self.env[index].clone()
+            }
+            TyKind::ForAll(kind, ty) => {
+                self.under(kind.clone(), |kcx| kcx.check(ty, &Kind::Type))?;
+                Kind::Type
+            }
+            TyKind::Exists(ty) | TyKind::Mu(ty) => {
+                self.under(Kind::Type, |kcx| kcx.check(ty, &Kind::Type))?;
+                Kind::Type
+            }
+            TyKind::Lambda(kind, ty) => match self.under(kind.clone(), |kcx| kcx.infer(ty))? {
+                Some(body) => Kind::Fn(Box::new(kind.clone()), Box::new(body)),
+                None => return Ok(None),
+            },
+            TyKind::App(f, x) => match self.infer(f)? {
+                Some(Kind::Fn(param, ret)) => {
+                    self.check(x, &param)?;
+                    *ret
+                }
+                Some(Kind::Type) => return Err(TypeErrorKind::NotATyOperator),
+                None => {
+                    self.infer(x)?;
+                    return Ok(None);
+                }
+            },
+            // The parameters of data types have kind `*`
+            TyKind::Adt(adt, args) => {
+                let items = self.db.items(adt.file);
+                let arity = items[adt.def].arity;
+                if args.len() > arity {
+                    return Err(TypeErrorKind::TooManyTyArgs(*adt));
+                }
+                for arg in args {
+                    self.check(arg, &Kind::Type)?;
+                }
+                let star = || Box::new(Kind::Type);
+                (args.len()..arity).fold(Kind::Type, |kind, _| Kind::Fn(star(), Box::new(kind)))
+            }
+            TyKind::Tuple(tys) => {
+                for ty in tys {
+                    self.check(ty, &Kind::Type)?;
+                }
+                Kind::Type
+            }
+            TyKind::Record(fields) => {
+                for (_, ty) in fields {
+                    self.check(ty, &Kind::Type)?;
+                }
+                Kind::Type
+            }
+            TyKind::Constrained(class, arg, ty) => {
+                self.check(arg, &class_kind(self.db, *class))?;
+                self.check(ty, &Kind::Type)?;
+                Kind::Type
+            }
+            TyKind::Err => return Ok(None),
+        };
+        Ok(Some(kind))
+    }
+
+    fn under<T>(&mut self, kind: Kind, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.env.push(kind);
+        let res = f(self);
+        self.env.pop();
+        res
+    }
+}
@@ -1,72 +1,1005 @@
-mod subst;
+macro_rules! ty {
+    (Bool) => {{ cir::TyKind::Scalar(cir::Scalar::Bool).intern() }};
+    (Int) => {{ cir::TyKind::Scalar(cir::Scalar::Int).intern() }};
+    (Float) => {{ cir::TyKind::Scalar(cir::Scalar::Float).intern() }};
+    (Char) => {{ cir::TyKind::Scalar(cir::Scalar::Char).intern() }};
+    (String) => {{ cir::TyKind::Scalar(cir::Scalar::String).intern() }};
+    ($($tt:tt)*) => {{ cir_parse::parse_ty(stringify!($($tt)*)) }};
+}
+
+mod db;
+mod error;
+mod infer;
+mod kind;
+mod normalize;
+mod positivity;
+#[cfg(test)]
+mod test_db;
+mod variance;
 
+use cir::subst::{self, Substitute};
 use cir::{Debruijn, Ty, TyKind};
-use subst::Substitute;
+use normalize::normalize;
+use rustc_hash::FxHashMap;
 
-struct TypecheckCtxt {
+pub use self::db::{TypecheckDatabase, TypecheckDatabaseStorage};
+pub use self::error::{TypeError, TypeErrorKind};
+pub use self::positivity::PositivityError;
+pub use self::variance::Variance;
+
+struct TypecheckCtxt<'db> {
+    db: &'db dyn TypecheckDatabase,
     body: cir::BodyData,
+    /// The types of the binders whose type is not annotated, i.e. those introduced by patterns
+    /// and unannotated `let`s
+    binder_tys: FxHashMap<cir::Binder, Ty>,
+    /// The kinds of the type binders (type lambdas, `unpack`s and the type parameters bound by
+    /// patterns) enclosing the current expression, the innermost last. Their number is the depth
+    /// of the expression.
+    ty_kinds: Vec<cir::Kind>,
+    /// The depth at which each value binder is introduced, its type refers to the type variables
+    /// in scope there
+    binder_depths: FxHashMap<cir::Binder, u32>,
+    /// The type equations that hold in the enclosing `match` alternatives because they match on
+    /// constructors with signatures. Each is the level of a type variable (the depth at its
+    /// binder), the type it equals and the depth that type is valid at.
+    equations: Vec<(u32, Ty, u32)>,
+    /// The dictionaries that are parameters of the enclosing expressions because they are
+    /// checked against constrained types. Each is the class, the type it is instantiated with,
+    /// the depth that type is valid at and the binder of the dictionary.
+    dicts: Vec<(cir::ClassId, Ty, u32, cir::Binder)>,
+    errors: Vec<TypeErrorKind>,
 }
 
-macro_rules! ty {
-    (Bool) => {{ cir::TyKind::Scalar(cir::Scalar::Bool).intern() }};
-    (Int) => {{ cir::TyKind::Scalar(cir::Scalar::Int).intern() }};
-    ($($tt:tt)*) => {{ cir_parse::parse_ty(stringify!($($tt)*)) }};
-}
+impl<'db> TypecheckCtxt<'db> {
+    fn new(db: &'db dyn TypecheckDatabase, body: cir::BodyData) -> Self {
+        Self {
+            db,
+            body,
+            binder_tys: Default::default(),
+            ty_kinds: Default::default(),
+            binder_depths: Default::default(),
+            equations: Default::default(),
+            dicts: Default::default(),
+            errors: Default::default(),
+        }
+    }
+
+    fn expect_body(&mut self, expected: &Ty) {
+        self.expect_ty(self.body.expr, expected)
+    }
+
+    fn depth(&self) -> u32 {
+        self.ty_kinds.len() as u32
+    }
 
-impl TypecheckCtxt {
-    fn check_body(&mut self) -> Ty {
-        self.check_expr(self.body.expr)
+    /// Records an error, the erroneous expression has the error type so that it causes no
+    /// further errors
+    fn error(&mut self, kind: TypeErrorKind) -> Ty {
+        self.errors.push(kind);
+        TyKind::Err.intern()
+    }
+
+    /// Checks that the type `ty` written in the body has kind `kind`, see `kind::check_kind`.
+    /// Returns whether it does, an ill-kinded type must not be normalized.
+    fn check_kind(&mut self, ty: &Ty, kind: &cir::Kind) -> bool {
+        match kind::check_kind(self.db, &self.ty_kinds, ty, kind) {
+            Ok(()) => true,
+            Err(err) => {
+                self.errors.push(err);
+                false
+            }
+        }
+    }
+
+    /// Checks the annotation of a value binder (if it has one), an ill-kinded annotation is
+    /// replaced by the error type
+    fn check_binder_kind(&mut self, binder: cir::Binder) {
+        if let cir::BinderData::Val(ty) = self.binder(binder) {
+            let ty = Ty::clone(ty);
+            if !self.check_kind(&ty, &cir::Kind::Type) {
+                self.body.binders[binder] = cir::BinderData::Val(TyKind::Err.intern());
+            }
+        }
     }
 
     fn binder(&self, binder: cir::Binder) -> &cir::BinderData {
         &self.body.binders[binder]
     }
 
+    /// Whether `expr` is a lambda with a binder, or a nested lambda with a binder, that has no
+    /// type annotation, e.g. `\x. x + 1`
+    fn has_unannotated_binder(&self, expr: cir::Expr) -> bool {
+        match self.body[expr] {
+            cir::ExprData::Lambda(binder, body) =>
+                matches!(self.binder(binder), cir::BinderData::Infer)
+                    || self.has_unannotated_binder(body),
+            _ => false,
+        }
+    }
+
     fn check_binder(&self, binder: cir::Binder) -> Ty {
-        match self.binder(binder) {
+        let ty = match self.binder(binder) {
             cir::BinderData::Val(ty) => Ty::clone(ty),
-            cir::BinderData::Ty => panic!(),
+            cir::BinderData::Ty(_) => panic!(),
+            // Binders of patterns that failed to lower are never assigned a type
+            cir::BinderData::Pat | cir::BinderData::Let | cir::BinderData::Infer =>
+                self.binder_tys.get(&binder).cloned().unwrap_or_else(|| TyKind::Err.intern()),
+        };
+        let depth = self.binder_depths.get(&binder).copied().unwrap_or(self.depth());
+        subst::shift_in(&ty, self.depth() - depth)
+    }
+
+    /// Records that the value binders are introduced at the current depth
+    fn bind(&mut self, binders: impl IntoIterator<Item = cir::Binder>) {
+        let depth = self.depth();
+        self.binder_depths.extend(binders.into_iter().map(|binder| (binder, depth)));
+    }
+
+    /// Applies the equations of the enclosing `match` alternatives to `ty`
+    fn refine(&self, ty: &Ty) -> Ty {
+        self.equations.iter().fold(Ty::clone(ty), |ty, (level, eq_ty, depth)| {
+            let var = Debruijn::new(self.depth() - 1 - level);
+            subst::replace(&ty, var, &subst::shift_in(eq_ty, self.depth() - depth))
+        })
+    }
+
+    /// Checks a `match` alternative, against `expected` if the type of the `match` is known.
+    /// Matching on a constructor brings its own type parameters into scope and refines the types
+    /// in the alternative so that its result type is the scrutinee's type, e.g. `a = Int` when
+    /// matching on `IntE : Int -> Expr Int` with a scrutinee of type `Expr a`.
+    fn check_alt(&mut self, scrutinee_ty: &Ty, alt: &cir::Alt, expected: Option<&Ty>) -> Ty {
+        let (ty_kinds, binders) = match &alt.pat {
+            cir::Pat::Variant(variant, ty_binders, binders) => {
+                let items = self.db.items(variant.data_def.file);
+                let variant_data = &items[variant.data_def.def].variants[variant.variant];
+                // `bind_fields` checks there is a type binder for each type parameter
+                let kinds = variant_data
+                    .ty_params
+                    .iter()
+                    .cloned()
+                    .chain(std::iter::repeat(cir::Kind::Type));
+                (kinds.take(ty_binders.len()).collect(), binders)
+            }
+            cir::Pat::Err(ty_binders, binders) =>
+                (vec![cir::Kind::Type; ty_binders.len()], binders),
+            cir::Pat::Tuple(binders) => (vec![], binders),
+        };
+        let ty_binders = ty_kinds.len() as u32;
+        self.ty_kinds.extend(ty_kinds);
+        self.bind(binders.iter().copied());
+        let len = self.equations.len();
+        match (&alt.pat, scrutinee_ty.kind()) {
+            (cir::Pat::Variant(variant, ..), TyKind::Adt(adt, args))
+                if *adt == variant.data_def =>
+            {
+                let equations = self.bind_fields(*variant, args, ty_binders, binders);
+                let depth = self.depth();
+                self.equations.extend(
+                    equations.into_iter().map(|(var, ty)| (depth - 1 - var.index(), ty, depth)),
+                );
+            }
+            (cir::Pat::Tuple(binders), TyKind::Tuple(tys)) if binders.len() == tys.len() =>
+                self.binder_tys.extend(binders.iter().copied().zip(tys.iter().cloned())),
+            // The binders are left without a type and so have an error type
+            (_, TyKind::Err) | (cir::Pat::Err(..), _) => {}
+            _ => {
+                self.error(TypeErrorKind::PatternMismatch(Ty::clone(scrutinee_ty)));
+            }
+        }
+        let ty = match expected {
+            Some(expected) => {
+                self.expect_ty(alt.body, &subst::shift_in(expected, ty_binders));
+                Ty::clone(expected)
+            }
+            None => self.check_expr(alt.body),
+        };
+        self.equations.truncate(len);
+        self.ty_kinds.truncate(self.ty_kinds.len() - ty_binders as usize);
+        if expected.is_some() {
+            return ty;
+        }
+        match (0..ty_binders).try_fold(ty, |ty, _| subst::shift_out(&ty)) {
+            Some(ty) => ty,
+            None => self.error(TypeErrorKind::EscapingTyParam),
+        }
+    }
+
+    /// Assigns each binder of a constructor pattern the type of the corresponding field, where
+    /// `args` are the arguments of the scrutinee's type (which is outside the `ty_binders` type
+    /// binders of the pattern). Returns the equations that make the constructor's result type
+    /// equal to the scrutinee's type.
+    fn bind_fields(
+        &mut self,
+        variant: cir::VariantId,
+        args: &[Ty],
+        ty_binders: u32,
+        binders: &[cir::Binder],
+    ) -> Vec<(Debruijn, Ty)> {
+        let items = self.db.items(variant.data_def.file);
+        let data_def = &items[variant.data_def.def];
+        let variant_data = &data_def.variants[variant.variant];
+        if variant_data.ty_params.len() != ty_binders as usize {
+            self.error(TypeErrorKind::PatternTyParams);
+            return vec![];
+        }
+        // The parameters of the data definition are instantiated with the arguments of the
+        // scrutinee's type, lowering ensures they are arguments of the result type (or unused)
+        let mut ty = db::ctor_ty_with_params(self.db, variant);
+        for i in 0..data_def.arity {
+            let arg = match &variant_data.result {
+                None => args.get(i),
+                Some(result) => {
+                    let var = Debruijn::new(ty_binders + (data_def.arity - 1 - i) as u32);
+                    result
+                        .iter()
+                        .position(
+                            |arg| matches!(arg.kind(), TyKind::Var(debruijn) if *debruijn == var),
+                        )
+                        .and_then(|j| args.get(j))
+                }
+            };
+            ty = ty.skip_binder().substitute(&arg.cloned().unwrap_or_else(|| TyKind::Err.intern()));
         }
+        // The constructor's own type parameters are bound by the pattern
+        for _ in 0..ty_binders {
+            ty = ty.skip_binder();
+        }
+        // An ill-kinded constructor, the binders have the error type
+        if let TyKind::Err = ty.kind() {
+            return vec![];
+        }
+        for &binder in binders {
+            ty = match ty.kind() {
+                TyKind::Fn(field_ty, ty) => {
+                    self.binder_tys.insert(binder, Ty::clone(field_ty));
+                    Ty::clone(ty)
+                }
+                _ => {
+                    self.error(TypeErrorKind::PatternFields);
+                    return vec![];
+                }
+            };
+        }
+        let result = match ty.kind() {
+            TyKind::Adt(_, result) => result,
+            _ => {
+                self.error(TypeErrorKind::PatternFields);
+                return vec![];
+            }
+        };
+        let mut equations = vec![];
+        for (result_arg, arg) in result.iter().zip(args) {
+            if !unify(&normalize(result_arg), &subst::shift_in(arg, ty_binders), &mut equations) {
+                let scrutinee_ty = TyKind::Adt(variant.data_def, args.to_vec()).intern();
+                self.error(TypeErrorKind::CtorResultMismatch(scrutinee_ty));
+                return vec![];
+            }
+        }
+        equations
     }
 
+    fn check_bin_op(&mut self, op: cir::BinOp, l: cir::Expr, r: cir::Expr) -> Ty {
+        match op {
+            cir::BinOp::Add
+            | cir::BinOp::Sub
+            | cir::BinOp::Mul
+            | cir::BinOp::Div
+            | cir::BinOp::Rem => {
+                let ty = self.check_numeric(l);
+                self.expect_ty(r, &ty);
+                ty
+            }
+            cir::BinOp::Lt | cir::BinOp::Le | cir::BinOp::Gt | cir::BinOp::Ge => {
+                let ty = self.check_numeric(l);
+                self.expect_ty(r, &ty);
+                ty!(Bool)
+            }
+            cir::BinOp::Eq | cir::BinOp::Ne => {
+                let ty = self.check_expr(l);
+                match ty.kind() {
+                    TyKind::Scalar(_) => self.expect_ty(r, &ty),
+                    TyKind::Err => {
+                        self.check_expr(r);
+                    }
+                    _ => {
+                        self.error(TypeErrorKind::NotScalar(Ty::clone(&ty)));
+                        self.check_expr(r);
+                    }
+                }
+                ty!(Bool)
+            }
+            cir::BinOp::And | cir::BinOp::Or => {
+                self.expect_ty(l, &ty!(Bool));
+                self.expect_ty(r, &ty!(Bool));
+                ty!(Bool)
+            }
+        }
+    }
+
+    /// Checks that `expr` is an `Int` or a `Float` (or an error) and returns its type
+    fn check_numeric(&mut self, expr: cir::Expr) -> Ty {
+        let ty = self.check_expr(expr);
+        match ty.kind() {
+            TyKind::Scalar(cir::Scalar::Int | cir::Scalar::Float) | TyKind::Err => ty,
+            _ => self.error(TypeErrorKind::NotNumeric(ty)),
+        }
+    }
+
+    /// Checks that `expr` has type `expected`, expressions with an error type are accepted.
+    /// The expected type is propagated into functions and `match` alternatives, where it may be
+    /// refined by matching on a constructor with a signature.
+    fn expect_ty(&mut self, expr: cir::Expr, expected: &Ty) {
+        let expected = normalize(&self.refine(expected));
+        if let TyKind::Constrained(class, arg, ty) = expected.kind() {
+            return self.expect_constrained(expr, *class, arg, ty);
+        }
+        match (&self.body[expr], expected.kind()) {
+            (&cir::ExprData::Lambda(binder, body), TyKind::Fn(param_ty, ret_ty)) => {
+                self.check_binder_kind(binder);
+                match self.binder(binder) {
+                    cir::BinderData::Val(binder_ty)
+                        if normalize(&self.refine(binder_ty)) == *param_ty =>
+                    {
+                        self.bind([binder]);
+                        return self.expect_ty(body, ret_ty);
+                    }
+                    cir::BinderData::Infer => {
+                        self.binder_tys.insert(binder, Ty::clone(param_ty));
+                        self.bind([binder]);
+                        return self.expect_ty(body, ret_ty);
+                    }
+                    _ => {}
+                }
+            }
+            (&cir::ExprData::Lambda(binder, body), TyKind::ForAll(kind, body_ty))
+                if matches!(self.binder(binder), cir::BinderData::Ty(_)) =>
+            {
+                self.ty_kinds.push(kind.clone());
+                self.expect_ty(body, body_ty);
+                self.ty_kinds.pop();
+                return;
+            }
+            (cir::ExprData::Case(scrutinee, alts), _) => {
+                let (scrutinee, alts) = (*scrutinee, alts.clone());
+                let scrutinee_ty = self.check_expr(scrutinee);
+                for alt in &alts {
+                    self.check_alt(&scrutinee_ty, alt, Some(&expected));
+                }
+                return;
+            }
+            // The methods of an instance are checked against the types of the class's methods
+            (cir::ExprData::Record(fields), TyKind::Record(field_tys))
+                if fields.len() == field_tys.len()
+                    && fields.iter().zip(field_tys).all(|((a, _), (b, _))| a == b) =>
+            {
+                let fields = fields.clone();
+                for ((_, expr), (_, ty)) in fields.into_iter().zip(field_tys) {
+                    self.expect_ty(expr, ty);
+                }
+                return;
+            }
+            (&cir::ExprData::If(c, t, e), _) => {
+                self.expect_ty(c, &ty!(Bool));
+                self.expect_ty(t, &expected);
+                self.expect_ty(e, &expected);
+                return;
+            }
+            (&cir::ExprData::Let(binder, e1, e2), _) => {
+                self.check_let(binder, e1);
+                return self.expect_ty(e2, &expected);
+            }
+            (cir::ExprData::LetRec(bindings, e), _) => {
+                let (bindings, e) = (bindings.clone(), *e);
+                self.check_let_rec(bindings);
+                return self.expect_ty(e, &expected);
+            }
+            _ => {}
+        }
+        let ty = self.check_expr(expr);
+        if ty != expected
+            && !matches!(ty.kind(), TyKind::Err)
+            && !matches!(expected.kind(), TyKind::Err)
+        {
+            self.error(TypeErrorKind::Mismatch { expected, found: ty });
+        }
+    }
+
+    /// Checks `expr` against `C arg => ty` by making it a function of the dictionary of `C` for
+    /// `arg`, which is in scope to satisfy constraints within `expr`
+    fn expect_constrained(&mut self, expr: cir::Expr, class: cir::ClassId, arg: &Ty, ty: &Ty) {
+        let dict_ty = db::dict_ty(self.db, class, arg);
+        let binder = self.body.binders.alloc(cir::BinderData::Val(dict_ty));
+        self.bind([binder]);
+        let inner = self.move_expr(expr);
+        self.body.exprs[expr] = cir::ExprData::Lambda(binder, inner);
+        self.dicts.push((class, Ty::clone(arg), self.depth(), binder));
+        self.expect_ty(inner, ty);
+        self.dicts.pop();
+    }
+
+    /// Checks that `expr` of type `actual` can be used where `expected` is required, i.e. that
+    /// `actual` is at least as polymorphic as `expected`. Unless the types are equal, `expr` is
+    /// replaced by a coercion to `expected`, e.g. `forall a. a -> a` is coerced to `Int -> Int` by
+    /// `\x:Int. expr @Int x`.
+    fn subsume(&mut self, expr: cir::Expr, actual: &Ty, expected: &Ty) -> bool {
+        let expected = normalize(&self.refine(expected));
+        if *actual == expected {
+            return true;
+        }
+        let inner = self.move_expr(expr);
+        match self.coerce(inner, actual, &expected) {
+            Some(coerced) => {
+                self.body.exprs[expr] = self.body[coerced].clone();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Builds the coercion of `expr` from `actual` to `expected` (if `actual` is subsumed by
+    /// `expected`). Polymorphic expected types are skolemised by type lambdas, polymorphic actual
+    /// types are instantiated and functions are coerced contravariantly in their parameter by
+    /// eta-expansion.
+    fn coerce(&mut self, expr: cir::Expr, actual: &Ty, expected: &Ty) -> Option<cir::Expr> {
+        let (actual, expected) = (normalize(actual), normalize(expected));
+        if actual == expected
+            || matches!(actual.kind(), TyKind::Err)
+            || matches!(expected.kind(), TyKind::Err)
+        {
+            return Some(expr);
+        }
+        match (actual.kind(), expected.kind()) {
+            (_, TyKind::ForAll(kind, body_ty)) => {
+                let ty_binder = self.body.binders.alloc(cir::BinderData::Ty(kind.clone()));
+                self.with_value(expr, |tcx, var| {
+                    let body = tcx.coerce(var, &subst::shift_in(&actual, 1), body_ty)?;
+                    Some(tcx.alloc_expr(cir::ExprData::Lambda(ty_binder, body)))
+                })
+            }
+            (TyKind::ForAll(..), _) => {
+                let args = match instantiate(&actual, &expected) {
+                    Some(args) => args,
+                    None => {
+                        self.error(TypeErrorKind::CannotInstantiate { expected, found: actual });
+                        return Some(expr);
+                    }
+                };
+                let (mut expr, mut ty) = (expr, actual);
+                for arg in args {
+                    let ty_arg = self.alloc_expr(cir::ExprData::Type(Ty::clone(&arg)));
+                    expr = self.alloc_expr(cir::ExprData::App(expr, ty_arg));
+                    ty = ty.skip_binder().substitute(&arg);
+                }
+                self.coerce(expr, &ty, &expected)
+            }
+            (TyKind::Fn(actual_param, actual_ret), TyKind::Fn(expected_param, expected_ret)) => {
+                let binder =
+                    self.body.binders.alloc(cir::BinderData::Val(Ty::clone(expected_param)));
+                self.with_value(expr, |tcx, f| {
+                    let param = tcx.alloc_expr(cir::ExprData::Var(binder));
+                    let arg = tcx.coerce(param, expected_param, actual_param)?;
+                    let app = tcx.alloc_expr(cir::ExprData::App(f, arg));
+                    let body = tcx.coerce(app, actual_ret, expected_ret)?;
+                    Some(tcx.alloc_expr(cir::ExprData::Lambda(binder, body)))
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Binds `expr` by a `let` (unless it is a variable) so that a coercion can refer to it under
+    /// the lambdas it introduces without evaluating it more than once
+    fn with_value(
+        &mut self,
+        expr: cir::Expr,
+        f: impl FnOnce(&mut Self, cir::Expr) -> Option<cir::Expr>,
+    ) -> Option<cir::Expr> {
+        if let cir::ExprData::Var(_) = self.body[expr] {
+            return f(self, expr);
+        }
+        let binder = self.body.binders.alloc(cir::BinderData::Let);
+        let var = self.alloc_expr(cir::ExprData::Var(binder));
+        let body = f(self, var)?;
+        Some(self.alloc_expr(cir::ExprData::Let(binder, expr, body)))
+    }
+
+    /// Moves the expression `expr` to a new `Expr` so that `expr` can be replaced by an
+    /// expression containing it
+    fn move_expr(&mut self, expr: cir::Expr) -> cir::Expr {
+        let data = self.body[expr].clone();
+        self.body.exprs.alloc(data)
+    }
+
+    fn alloc_expr(&mut self, data: cir::ExprData) -> cir::Expr {
+        self.body.exprs.alloc(data)
+    }
+
+    /// Finds the dictionary of `class` for `arg`, either a parameter of an enclosing expression
+    /// or the instance whose head matches `arg` (applied to the dictionaries of its context)
+    fn resolve_dict(&mut self, class: cir::ClassId, arg: &Ty) -> cir::Expr {
+        if matches!(arg.kind(), TyKind::Err) {
+            return self.alloc_expr(cir::ExprData::Err);
+        }
+        let in_scope = self.dicts.iter().rev().find(|(dict_class, dict_arg, depth, _)| {
+            *dict_class == class
+                && normalize(&self.refine(&subst::shift_in(dict_arg, self.depth() - depth))) == *arg
+        });
+        if let Some(&(.., binder)) = in_scope {
+            return self.alloc_expr(cir::ExprData::Var(binder));
+        }
+        let mut candidates = self
+            .db
+            .class_instances(class)
+            .iter()
+            .filter_map(|&instance| Some((instance, self.match_instance(instance, arg)?)))
+            .collect::<Vec<_>>();
+        let (instance, args) = match candidates.len() {
+            1 => candidates.pop().unwrap(),
+            0 => {
+                self.error(TypeErrorKind::NoInstance { class, ty: Ty::clone(arg) });
+                return self.alloc_expr(cir::ExprData::Err);
+            }
+            _ => {
+                self.error(TypeErrorKind::OverlappingInstances { class, ty: Ty::clone(arg) });
+                return self.alloc_expr(cir::ExprData::Err);
+            }
+        };
+        let items = self.db.items(instance.file);
+        let instance_data = &items[instance.def];
+        let mut dict = self.alloc_expr(cir::ExprData::Instance(instance));
+        for arg in &args {
+            let arg = self.alloc_expr(cir::ExprData::Type(Ty::clone(arg)));
+            dict = self.alloc_expr(cir::ExprData::App(dict, arg));
+        }
+        for (class, ctx_arg) in &instance_data.context {
+            // The parameters of the instance are bound as if by enclosing `forall`s
+            let ctx_arg = instance_data
+                .params
+                .iter()
+                .rev()
+                .fold(Ty::clone(ctx_arg), |ty, kind| TyKind::ForAll(kind.clone(), ty).intern());
+            let ctx_arg = args.iter().fold(ctx_arg, |ty, arg| ty.skip_binder().substitute(arg));
+            let ctx_dict = self.resolve_dict(*class, &normalize(&ctx_arg));
+            dict = self.alloc_expr(cir::ExprData::App(dict, ctx_dict));
+        }
+        dict
+    }
+
+    /// Matches the head of `instance` against `arg`, returning the arguments for the instance's
+    /// parameters (the first is the outermost) if it is an instance for `arg`
+    fn match_instance(&self, instance: cir::InstanceId, arg: &Ty) -> Option<Vec<Ty>> {
+        let items = self.db.items(instance.file);
+        let instance = &items[instance.def];
+        let params = instance.params.len() as u32;
+        let mut equations = vec![];
+        if !unify(&normalize(&instance.head), &subst::shift_in(arg, params), &mut equations) {
+            return None;
+        }
+        // Only the parameters of the instance may be solved for, `arg` is not instantiated
+        if equations.iter().any(|(var, _)| var.index() >= params) {
+            return None;
+        }
+        (0..params)
+            .map(|i| {
+                let var = Debruijn::new(params - 1 - i);
+                let (_, ty) = equations.iter().find(|(eq_var, _)| *eq_var == var)?;
+                (0..params).try_fold(Ty::clone(ty), |ty, _| subst::shift_out(&ty))
+            })
+            .collect()
+    }
+
+    /// Replaces a reference to a method by a function of the class's dictionary that projects
+    /// the method, i.e. `\@a.\d:{ eq: a -> a -> Bool }. d.eq`
+    fn elaborate_method(&mut self, expr: cir::Expr, method: cir::MethodId) {
+        let items = self.db.items(method.class.file);
+        let class = &items[method.class.def];
+        let (name, _) = &class.methods[method.index];
+        let ty_binder = self.body.binders.alloc(cir::BinderData::Ty(class.kind.clone()));
+        let param = TyKind::Var(Debruijn::INNER).intern();
+        let dict_ty = db::dict_ty(self.db, method.class, &param);
+        let dict_binder = self.body.binders.alloc(cir::BinderData::Val(dict_ty));
+        let dict = self.alloc_expr(cir::ExprData::Var(dict_binder));
+        let field = self.alloc_expr(cir::ExprData::Field(dict, name.symbol.clone()));
+        let lambda = self.alloc_expr(cir::ExprData::Lambda(dict_binder, field));
+        self.body.exprs[expr] = cir::ExprData::Lambda(ty_binder, lambda);
+    }
+
+    fn check_let(&mut self, binder: cir::Binder, expr: cir::Expr) {
+        self.bind([binder]);
+        self.check_binder_kind(binder);
+        match self.binder(binder) {
+            cir::BinderData::Val(ty) => {
+                let ty = Ty::clone(ty);
+                self.expect_ty(expr, &ty);
+            }
+            // Types are explicit so the type of the bound expression needs no further
+            // generalization, e.g. `\@a.\x:a.x` is already `forall a. a -> a`
+            _ => {
+                let ty = self.check_expr(expr);
+                self.binder_tys.insert(binder, ty);
+            }
+        }
+    }
+
+    fn check_let_rec(&mut self, bindings: Vec<(cir::Binder, cir::Expr)>) {
+        // The declared types are used for references within the group
+        self.bind(bindings.iter().map(|&(binder, _)| binder));
+        for &(binder, _) in &bindings {
+            self.check_binder_kind(binder);
+        }
+        for (binder, expr) in bindings {
+            let ty = self.check_binder(binder);
+            self.expect_ty(expr, &ty);
+        }
+    }
+
+    /// Returns the type of `expr` in normal form (and refined by the equations of the enclosing
+    /// `match` alternatives), so types can be compared structurally. The type has no constraints,
+    /// `expr` is applied to the dictionaries that satisfy them.
     fn check_expr(&mut self, expr: cir::Expr) -> Ty {
+        let ty = self.check_expr_inner(expr);
+        let mut ty = normalize(&self.refine(&ty));
+        while let TyKind::Constrained(class, arg, inner) = ty.kind() {
+            let dict = self.resolve_dict(*class, arg);
+            let f = self.move_expr(expr);
+            self.body.exprs[expr] = cir::ExprData::App(f, dict);
+            ty = Ty::clone(inner);
+        }
+        ty
+    }
+
+    fn check_expr_inner(&mut self, expr: cir::Expr) -> Ty {
         match self.body[expr] {
             cir::ExprData::Var(binder) => self.check_binder(binder),
-            cir::ExprData::Lit(lit) => match lit {
+            // Definitions are checked against their declared type separately
+            cir::ExprData::Def(def) => self.db.value_def_ty(def),
+            cir::ExprData::Ctor(variant) => self.db.ctor_ty(variant),
+            cir::ExprData::Method(method) => {
+                self.elaborate_method(expr, method);
+                self.db.method_ty(method)
+            }
+            cir::ExprData::Instance(instance) => self.db.instance_ty(instance),
+            cir::ExprData::Lit(ref lit) => match lit {
                 cir::Lit::Bool(_) => ty!(Bool),
                 cir::Lit::Int(_) => ty!(Int),
+                cir::Lit::Float(_) => ty!(Float),
+                cir::Lit::Char(_) => ty!(Char),
+                cir::Lit::String(_) => ty!(String),
             },
-            cir::ExprData::Lambda(binder, body) => {
-                let body_ty = self.check_expr(body);
-                match self.binder(binder) {
-                    cir::BinderData::Val(binder_ty) => {
-                        let binder_ty = Ty::clone(binder_ty);
-                        TyKind::Fn(binder_ty, body_ty).intern()
+            cir::ExprData::Lambda(binder, body) => match self.binder(binder) {
+                cir::BinderData::Val(_) => {
+                    self.check_binder_kind(binder);
+                    self.bind([binder]);
+                    let binder_ty = self.check_binder(binder);
+                    let body_ty = self.check_expr(body);
+                    TyKind::Fn(binder_ty, body_ty).intern()
+                }
+                cir::BinderData::Ty(ref kind) => {
+                    let kind = kind.clone();
+                    self.ty_kinds.push(kind.clone());
+                    let body_ty = self.check_expr(body);
+                    self.ty_kinds.pop();
+                    TyKind::ForAll(kind, body_ty).intern()
+                }
+                cir::BinderData::Infer => self.error(TypeErrorKind::UnannotatedBinder),
+                cir::BinderData::Pat | cir::BinderData::Let =>
+                    unreachable!("lambda with a pattern or let binder"),
+            },
+            cir::ExprData::App(f, x) => {
+                let f_ty = self.check_expr(f);
+                match f_ty.kind() {
+                    // The type of a lambda without annotations cannot be synthesised, so it is
+                    // checked against the parameter type instead
+                    TyKind::Fn(param_ty, ret_ty) if self.has_unannotated_binder(x) => {
+                        self.expect_ty(x, param_ty);
+                        Ty::clone(ret_ty)
+                    }
+                    TyKind::Fn(param_ty, ret_ty) => {
+                        let arg_ty = self.check_expr(x);
+                        if !self.subsume(x, &arg_ty, param_ty) {
+                            let expected = Ty::clone(param_ty);
+                            self.error(TypeErrorKind::Mismatch { expected, found: arg_ty });
+                        }
+                        Ty::clone(ret_ty)
+                    }
+                    TyKind::ForAll(kind, body_ty) => match &self.body[x] {
+                        cir::ExprData::Type(ty) => {
+                            let ty = Ty::clone(ty);
+                            match self.check_kind(&ty, kind) {
+                                true => body_ty.substitute(&ty),
+                                false => TyKind::Err.intern(),
+                            }
+                        }
+                        _ => {
+                            self.check_expr(x);
+                            self.error(TypeErrorKind::ExpectedTyArg(Ty::clone(&f_ty)))
+                        }
+                    },
+                    // The argument of an erroneous function is checked for errors of its own
+                    TyKind::Err => {
+                        if !matches!(self.body[x], cir::ExprData::Type(_)) {
+                            self.check_expr(x);
+                        }
+                        f_ty
+                    }
+                    _ => {
+                        if !matches!(self.body[x], cir::ExprData::Type(_)) {
+                            self.check_expr(x);
+                        }
+                        self.error(TypeErrorKind::NotAFunction(Ty::clone(&f_ty)))
                     }
-                    cir::BinderData::Ty => TyKind::ForAll(body_ty).intern(),
                 }
             }
-            cir::ExprData::App(f, x) => match self.check_expr(f).kind() {
-                TyKind::Fn(param_ty, ret_ty) => {
-                    let arg_ty = self.check_expr(x);
-                    if &arg_ty != param_ty {
-                        todo!("type mismatch between argument and parameter");
-                    }
-                    Ty::clone(ret_ty)
+            cir::ExprData::Case(scrutinee, ref alts) => {
+                let alts = alts.clone();
+                let scrutinee_ty = self.check_expr(scrutinee);
+                let alt_tys = alts
+                    .iter()
+                    .map(|alt| self.check_alt(&scrutinee_ty, alt, None))
+                    .collect::<Vec<_>>();
+                let ty = alt_tys.first().cloned().unwrap_or_else(|| TyKind::Err.intern());
+                match alt_tys.into_iter().find(|alt_ty| *alt_ty != ty) {
+                    Some(found) => self.error(TypeErrorKind::Mismatch { expected: ty, found }),
+                    None => ty,
                 }
-                TyKind::ForAll(body_ty) => {
-                    let subst = match &self.body[x] {
-                        cir::ExprData::Type(ty) => Ty::clone(ty),
-                        _ => todo!("expected type for type lambda"),
-                    };
-                    body_ty.substitute(&subst)
+            }
+            cir::ExprData::If(c, t, e) => {
+                self.expect_ty(c, &ty!(Bool));
+                let ty = self.check_expr(t);
+                let e_ty = self.check_expr(e);
+                match e_ty == ty {
+                    true => ty,
+                    false => self.error(TypeErrorKind::Mismatch { expected: ty, found: e_ty }),
+                }
+            }
+            cir::ExprData::Let(binder, e1, e2) => {
+                self.check_let(binder, e1);
+                self.check_expr(e2)
+            }
+            cir::ExprData::LetRec(ref bindings, e) => {
+                let bindings = bindings.clone();
+                self.check_let_rec(bindings);
+                self.check_expr(e)
+            }
+            cir::ExprData::BinOp(op, l, r) => self.check_bin_op(op, l, r),
+            cir::ExprData::UnOp(op, expr) => match op {
+                cir::UnOp::Not => {
+                    self.expect_ty(expr, &ty!(Bool));
+                    ty!(Bool)
                 }
-                _ => todo!(),
+                cir::UnOp::Neg => self.check_numeric(expr),
             },
+            cir::ExprData::Prim(prim) => prim_ty(prim),
+            cir::ExprData::Tuple(ref exprs) => {
+                let exprs = exprs.clone();
+                TyKind::Tuple(exprs.into_iter().map(|expr| self.check_expr(expr)).collect())
+                    .intern()
+            }
+            cir::ExprData::Record(ref fields) => {
+                let fields = fields.clone();
+                let fields = fields.into_iter().map(|(name, expr)| (name, self.check_expr(expr)));
+                TyKind::Record(fields.collect()).intern()
+            }
+            cir::ExprData::Field(expr, ref name) => {
+                let name = name.clone();
+                let ty = self.check_expr(expr);
+                match ty.kind() {
+                    TyKind::Record(fields) => match fields.iter().find(|(field, _)| *field == name)
+                    {
+                        Some((_, field_ty)) => Ty::clone(field_ty),
+                        None => self.error(TypeErrorKind::NoField(Ty::clone(&ty), name)),
+                    },
+                    TyKind::Err => ty,
+                    _ => self.error(TypeErrorKind::NotARecord(ty)),
+                }
+            }
+            cir::ExprData::Pack(ref hidden, expr, ref ty) => {
+                let (hidden, ty) = (Ty::clone(hidden), Ty::clone(ty));
+                if !self.check_kind(&hidden, &cir::Kind::Type)
+                    || !self.check_kind(&ty, &cir::Kind::Type)
+                {
+                    self.check_expr(expr);
+                    return TyKind::Err.intern();
+                }
+                let ty = normalize(&ty);
+                match ty.kind() {
+                    TyKind::Exists(body_ty) => self.expect_ty(expr, &body_ty.substitute(&hidden)),
+                    TyKind::Err => {
+                        self.check_expr(expr);
+                    }
+                    _ => {
+                        self.check_expr(expr);
+                        return self.error(TypeErrorKind::NotExistential(ty));
+                    }
+                }
+                ty
+            }
+            cir::ExprData::Unpack(_, binder, e1, e2) => {
+                let ty = self.check_expr(e1);
+                match ty.kind() {
+                    // The type of the binder refers to the hidden type as the innermost variable
+                    TyKind::Exists(body_ty) => {
+                        self.binder_tys.insert(binder, Ty::clone(body_ty));
+                    }
+                    TyKind::Err => {}
+                    _ => {
+                        self.error(TypeErrorKind::NotExistential(Ty::clone(&ty)));
+                    }
+                }
+                self.ty_kinds.push(cir::Kind::Type);
+                self.bind([binder]);
+                let body_ty = self.check_expr(e2);
+                self.ty_kinds.pop();
+                match subst::shift_out(&body_ty) {
+                    Some(ty) => ty,
+                    None => self.error(TypeErrorKind::HiddenTyEscapes),
+                }
+            }
+            cir::ExprData::Fold(ref ty, expr) => {
+                let ty = Ty::clone(ty);
+                if !self.check_kind(&ty, &cir::Kind::Type) {
+                    self.check_expr(expr);
+                    return TyKind::Err.intern();
+                }
+                let ty = normalize(&ty);
+                match ty.kind() {
+                    TyKind::Mu(body_ty) => self.expect_ty(expr, &body_ty.substitute(&ty)),
+                    TyKind::Err => {
+                        self.check_expr(expr);
+                    }
+                    _ => {
+                        self.check_expr(expr);
+                        return self.error(TypeErrorKind::NotRecursive(ty));
+                    }
+                }
+                ty
+            }
+            cir::ExprData::Unfold(expr) => {
+                let ty = self.check_expr(expr);
+                match ty.kind() {
+                    TyKind::Mu(body_ty) => body_ty.substitute(&ty),
+                    TyKind::Err => ty,
+                    _ => self.error(TypeErrorKind::NotRecursive(ty)),
+                }
+            }
             cir::ExprData::Type(_) => unreachable!("found type in expression position"),
+            cir::ExprData::Err => TyKind::Err.intern(),
+        }
+    }
+}
+
+/// Solves `l = r` for the free type variables of both types, adding the solutions to
+/// `equations`. The solutions are kept substituted into each other so they can be applied in any
+/// order. Types under binders are only solved if they are already equal.
+fn unify(l: &Ty, r: &Ty, equations: &mut Vec<(Debruijn, Ty)>) -> bool {
+    let apply = |ty: &Ty| {
+        let ty = equations
+            .iter()
+            .fold(Ty::clone(ty), |ty, (var, eq_ty)| subst::replace(&ty, *var, eq_ty));
+        normalize(&ty)
+    };
+    let (l, r) = (apply(l), apply(r));
+    match (l.kind(), r.kind()) {
+        (TyKind::Var(a), TyKind::Var(b)) if a == b => true,
+        (&TyKind::Var(var), _) => solve(var, &r, equations),
+        (_, &TyKind::Var(var)) => solve(var, &l, equations),
+        (TyKind::Fn(l1, l2), TyKind::Fn(r1, r2)) | (TyKind::App(l1, l2), TyKind::App(r1, r2)) =>
+            unify(l1, r1, equations) && unify(l2, r2, equations),
+        (TyKind::Adt(l_adt, ls), TyKind::Adt(r_adt, rs)) =>
+            l_adt == r_adt
+                && ls.len() == rs.len()
+                && ls.iter().zip(rs.iter()).all(|(l, r)| unify(l, r, equations)),
+        (TyKind::Tuple(ls), TyKind::Tuple(rs)) =>
+            ls.len() == rs.len() && ls.iter().zip(rs.iter()).all(|(l, r)| unify(l, r, equations)),
+        (TyKind::Record(ls), TyKind::Record(rs)) =>
+            ls.len() == rs.len()
+                && ls
+                    .iter()
+                    .zip(rs.iter())
+                    .all(|((l_name, l), (r_name, r))| l_name == r_name && unify(l, r, equations)),
+        (TyKind::Constrained(l_class, l_arg, l), TyKind::Constrained(r_class, r_arg, r)) =>
+            l_class == r_class && unify(l_arg, r_arg, equations) && unify(l, r, equations),
+        (TyKind::Err, _) | (_, TyKind::Err) => true,
+        _ => l == r,
+    }
+}
+
+/// Finds the type arguments that instantiate the polymorphic type `actual` so that it is
+/// subsumed by `expected` (the first is for the outermost `forall`). Variables that do not occur
+/// in the body of `actual` are instantiated with the error type, which stands for any type.
+fn instantiate(actual: &Ty, expected: &Ty) -> Option<Vec<Ty>> {
+    let (mut body_ty, mut foralls) = (Ty::clone(actual), 0);
+    while let TyKind::ForAll(_, ty) = body_ty.kind() {
+        body_ty = Ty::clone(ty);
+        foralls += 1;
+    }
+    let mut args = vec![None; foralls];
+    match_ty(&body_ty, &subst::shift_in(expected, foralls as u32), 0, &mut args);
+    args.into_iter()
+        .enumerate()
+        .map(|(index, arg)| match arg {
+            None if !subst::occurs(&body_ty, Debruijn::new(index as u32)) =>
+                Some(TyKind::Err.intern()),
+            arg => arg,
+        })
+        .rev()
+        .collect()
+}
+
+/// Matches `pat` against `target`, solving the type variables of `pat` that are bound by the
+/// `args.len()` binders outside the `depth` binders entered so far. Unlike `unify` this ignores
+/// mismatches, as polymorphic parts of the types are related by subsumption rather than equality,
+/// and it sees through the `forall`s of `pat` that are instantiated later.
+fn match_ty(pat: &Ty, target: &Ty, depth: u32, args: &mut [Option<Ty>]) {
+    let (pat, target) = (normalize(pat), normalize(target));
+    let vars = depth + args.len() as u32;
+    let zip = |pats: &[Ty], targets: &[Ty], args: &mut [Option<Ty>]| {
+        if pats.len() == targets.len() {
+            for (pat, target) in pats.iter().zip(targets) {
+                match_ty(pat, target, depth, args);
+            }
+        }
+    };
+    match (pat.kind(), target.kind()) {
+        (TyKind::Var(var), _) if (depth..vars).contains(&var.index()) => {
+            let arg = &mut args[(var.index() - depth) as usize];
+            if arg.is_none() {
+                // The argument cannot refer to the variables bound within the types
+                *arg = (0..vars).try_fold(Ty::clone(&target), |ty, _| subst::shift_out(&ty));
+            }
+        }
+        (TyKind::Fn(p1, p2), TyKind::Fn(t1, t2)) | (TyKind::App(p1, p2), TyKind::App(t1, t2)) => {
+            match_ty(p1, t1, depth, args);
+            match_ty(p2, t2, depth, args);
+        }
+        (TyKind::Adt(p_adt, pats), TyKind::Adt(t_adt, targets)) if p_adt == t_adt =>
+            zip(pats, targets, args),
+        (TyKind::Tuple(pats), TyKind::Tuple(targets)) => zip(pats, targets, args),
+        (TyKind::Record(pats), TyKind::Record(targets))
+            if pats.len() == targets.len()
+                && pats.iter().zip(targets).all(|((p, _), (t, _))| p == t) =>
+            for ((_, pat), (_, target)) in pats.iter().zip(targets) {
+                match_ty(pat, target, depth, args);
+            },
+        (TyKind::Constrained(p_class, p_arg, p), TyKind::Constrained(t_class, t_arg, t))
+            if p_class == t_class =>
+        {
+            match_ty(p_arg, t_arg, depth, args);
+            match_ty(p, t, depth, args);
         }
+        (TyKind::ForAll(_, p), TyKind::ForAll(_, t))
+        | (TyKind::Lambda(_, p), TyKind::Lambda(_, t))
+        | (TyKind::Exists(p), TyKind::Exists(t))
+        | (TyKind::Mu(p), TyKind::Mu(t)) => match_ty(p, t, depth + 1, args),
+        (TyKind::ForAll(_, p), _) => match_ty(p, &subst::shift_in(&target, 1), depth + 1, args),
+        _ => {}
     }
 }
 
+fn solve(var: Debruijn, ty: &Ty, equations: &mut Vec<(Debruijn, Ty)>) -> bool {
+    if subst::occurs(ty, var) {
+        return false;
+    }
+    for (_, eq_ty) in equations.iter_mut() {
+        *eq_ty = subst::replace(eq_ty, var, ty);
+    }
+    equations.push((var, Ty::clone(ty)));
+    true
+}
+
+fn prim_ty(prim: cir::Prim) -> Ty {
+    let (params, ret) = match prim {
+        cir::Prim::StringLength => (vec![ty!(String)], ty!(Int)),
+        cir::Prim::StringConcat => (vec![ty!(String), ty!(String)], ty!(String)),
+        cir::Prim::CharAt => (vec![ty!(String), ty!(Int)], ty!(Char)),
+        cir::Prim::ShowInt => (vec![ty!(Int)], ty!(String)),
+        cir::Prim::ShowFloat => (vec![ty!(Float)], ty!(String)),
+        cir::Prim::ShowChar => (vec![ty!(Char)], ty!(String)),
+        cir::Prim::ShowString => (vec![ty!(String)], ty!(String)),
+    };
+    params.into_iter().rev().fold(ret, |ret, param| TyKind::Fn(param, ret).intern())
+}
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,36 @@
+use cir::subst::Substitute;
+use cir::{Ty, TyKind};
+
+/// Reduces `ty` to its normal form by applying type operators to their arguments, so that types
+/// which are equal up to beta-reduction are interned to the same `Ty`.
+/// `ty` must be well-kinded (see `kind::check_kind`), normalizing an ill-kinded type such as
+/// `(\a. a a) (\a. a a)` need not terminate.
+pub(crate) fn normalize(ty: &Ty) -> Ty {
+    let kind = match ty.kind() {
+        TyKind::Scalar(_) | TyKind::Var(_) | TyKind::Err => return Ty::clone(ty),
+        TyKind::Fn(l, r) => TyKind::Fn(normalize(l), normalize(r)),
+        TyKind::ForAll(kind, ty) => TyKind::ForAll(kind.clone(), normalize(ty)),
+        TyKind::Exists(ty) => TyKind::Exists(normalize(ty)),
+        TyKind::Mu(ty) => TyKind::Mu(normalize(ty)),
+        TyKind::Lambda(kind, ty) => TyKind::Lambda(kind.clone(), normalize(ty)),
+        TyKind::Adt(adt, args) => TyKind::Adt(*adt, args.iter().map(normalize).collect()),
+        TyKind::Tuple(tys) => TyKind::Tuple(tys.iter().map(normalize).collect()),
+        TyKind::Record(fields) =>
+            TyKind::Record(fields.iter().map(|(name, ty)| (name.clone(), normalize(ty))).collect()),
+        TyKind::Constrained(class, arg, ty) =>
+            TyKind::Constrained(*class, normalize(arg), normalize(ty)),
+        TyKind::App(f, x) => {
+            let (f, x) = (normalize(f), normalize(x));
+            match f.kind() {
+                // The result of the substitution may contain new redexes (e.g. if the bound
+                // variable is applied in the body)
+                TyKind::Lambda(_, body) => return normalize(&body.substitute(&x)),
+                TyKind::Adt(adt, args) =>
+                    TyKind::Adt(*adt, args.iter().cloned().chain(Some(x)).collect()),
+                TyKind::Err => return f,
+                _ => TyKind::App(f, x),
+            }
+        }
+    };
+    kind.intern()
+}
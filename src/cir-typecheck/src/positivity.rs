@@ -0,0 +1,219 @@
+use std::fmt;
+use std::sync::Arc;
+
+use cir::{DataDefId, FileId, Name, Span, Ty, TyKind};
+use cir_parse::ast;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::db;
+use crate::kind;
+use crate::normalize::normalize;
+use crate::TypecheckDatabase;
+
+/// A field of a variant in which the data type being defined (possibly via a mutually recursive
+/// data type) occurs in a position that is not strictly positive, e.g. `data Bad = Bad (Bad -> Int)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositivityError {
+    /// The span of the offending field
+    pub span: Span,
+    /// The data type being defined
+    pub name: Name,
+}
+
+impl fmt::Display for PositivityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` occurs in a position that is not strictly positive",
+            self.name.symbol.as_str()
+        )
+    }
+}
+
+pub(crate) fn positivity_errors(
+    db: &dyn TypecheckDatabase,
+    file: FileId,
+) -> Arc<[PositivityError]> {
+    let (items, source_file) = match (db.lower_file(file), db.parse(file)) {
+        (Ok(items), Ok(source_file)) => (items, source_file),
+        _ => return Arc::from([]),
+    };
+    let mut ast_data_defs = FxHashMap::default();
+    collect_ast_data_defs(&source_file.items, &mut ast_data_defs);
+
+    let mut errors = vec![];
+    for (def, data_def) in items.data_defs.iter() {
+        let ast_data_def = ast_data_defs[&data_def.name.span];
+        let mut pcx = PositivityCtxt::new(db, DataDefId { file, def });
+        for ((_, variant), ast_variant) in data_def.variants.iter().zip(&ast_data_def.variants) {
+            // An ill-kinded variant is a type error
+            if kind::check_variant(db, data_def, variant).is_err() {
+                continue;
+            }
+            for (field, ast_field) in variant.fields.iter().zip(&ast_variant.params) {
+                if !pcx.positive(Occurrence::Data(pcx.target), &normalize(field), 0) {
+                    errors.push(PositivityError {
+                        span: ast_field.span,
+                        name: data_def.name.clone(),
+                    });
+                }
+            }
+        }
+    }
+    Arc::from(errors)
+}
+
+/// The data definitions by the spans of their names, which are the spans of the names of the
+/// lowered data definitions
+fn collect_ast_data_defs<'a>(items: &'a [ast::Item], acc: &mut FxHashMap<Span, &'a ast::DataDef>) {
+    for item in items {
+        match &item.kind {
+            ast::ItemKind::DataDef(data_def) => {
+                acc.insert(data_def.name.span, data_def);
+            }
+            ast::ItemKind::Module(ast::Module { kind: ast::ModuleKind::Inline(items), .. }) =>
+                collect_ast_data_defs(items, acc),
+            _ => {}
+        }
+    }
+}
+
+/// What to look for in a type
+#[derive(Debug, Clone, Copy)]
+enum Occurrence {
+    Data(DataDefId),
+    /// A type parameter, the index is relative to the top of the type
+    Param(u32),
+}
+
+struct PositivityCtxt<'db> {
+    db: &'db dyn TypecheckDatabase,
+    /// The data type whose definition is being checked
+    target: DataDefId,
+    /// Data types whose fields are being checked, these are assumed to be positive so that
+    /// recursion through them terminates
+    checking_fields: FxHashSet<DataDefId>,
+    /// Like `checking_fields` but for the parameters of data types
+    checking_params: FxHashSet<(DataDefId, usize)>,
+    /// Data types whose fields are being searched for an occurrence
+    searching: FxHashSet<DataDefId>,
+}
+
+impl<'db> PositivityCtxt<'db> {
+    fn new(db: &'db dyn TypecheckDatabase, target: DataDefId) -> Self {
+        Self {
+            db,
+            target,
+            checking_fields: Default::default(),
+            checking_params: Default::default(),
+            searching: Default::default(),
+        }
+    }
+
+    fn fields(&self, data_def: DataDefId) -> Vec<Ty> {
+        db::data_def_fields(self.db, data_def)
+    }
+
+    /// Whether `occurrence` occurs in `ty` (under `binders` type binders), including within the
+    /// definitions of the data types that `ty` refers to
+    fn occurs(&mut self, occurrence: Occurrence, ty: &Ty, binders: u32) -> bool {
+        match ty.kind() {
+            TyKind::Scalar(_) | TyKind::Err => false,
+            TyKind::Var(var) =>
+                matches!(occurrence, Occurrence::Param(index) if var.index() == index + binders),
+            TyKind::Fn(l, r) | TyKind::App(l, r) | TyKind::Constrained(_, l, r) =>
+                self.occurs(occurrence, l, binders) || self.occurs(occurrence, r, binders),
+            TyKind::ForAll(_, ty) | TyKind::Exists(ty) | TyKind::Mu(ty) | TyKind::Lambda(_, ty) =>
+                self.occurs(occurrence, ty, binders + 1),
+            TyKind::Tuple(tys) => tys.iter().any(|ty| self.occurs(occurrence, ty, binders)),
+            TyKind::Record(fields) =>
+                fields.iter().any(|(_, ty)| self.occurs(occurrence, ty, binders)),
+            TyKind::Adt(adt, args) => {
+                if args.iter().any(|arg| self.occurs(occurrence, arg, binders)) {
+                    return true;
+                }
+                match occurrence {
+                    Occurrence::Data(data_def) if data_def == *adt => true,
+                    // The parameters of a data type are not in scope in other data types
+                    Occurrence::Param(_) => false,
+                    Occurrence::Data(_) => {
+                        if !self.searching.insert(*adt) {
+                            return false;
+                        }
+                        let occurs =
+                            self.fields(*adt).iter().any(|field| self.occurs(occurrence, field, 0));
+                        self.searching.remove(adt);
+                        occurs
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether every occurrence of `occurrence` in `ty` is strictly positive, i.e. is not to the
+    /// left of an arrow or an argument of a type variable
+    fn positive(&mut self, occurrence: Occurrence, ty: &Ty, binders: u32) -> bool {
+        match ty.kind() {
+            TyKind::Scalar(_) | TyKind::Var(_) | TyKind::Err => true,
+            // The dictionary of a constraint is a parameter like that of a function
+            TyKind::Fn(l, r) | TyKind::Constrained(_, l, r) =>
+                !self.occurs(occurrence, l, binders) && self.positive(occurrence, r, binders),
+            // The applied type operator is a type variable and so could use its argument anywhere
+            TyKind::App(f, x) =>
+                !self.occurs(occurrence, f, binders) && !self.occurs(occurrence, x, binders),
+            TyKind::ForAll(_, ty) | TyKind::Exists(ty) | TyKind::Mu(ty) | TyKind::Lambda(_, ty) =>
+                self.positive(occurrence, ty, binders + 1),
+            TyKind::Tuple(tys) => tys.iter().all(|ty| self.positive(occurrence, ty, binders)),
+            TyKind::Record(fields) =>
+                fields.iter().all(|(_, ty)| self.positive(occurrence, ty, binders)),
+            TyKind::Adt(adt, args) => match occurrence {
+                Occurrence::Data(data_def) if data_def == *adt =>
+                    !args.iter().any(|arg| self.occurs(occurrence, arg, binders)),
+                _ => {
+                    let args_positive = args.iter().enumerate().all(|(i, arg)| {
+                        !self.occurs(occurrence, arg, binders)
+                            || self.param_positive(*adt, i)
+                                && self.positive(occurrence, arg, binders)
+                    });
+                    args_positive
+                        && match occurrence {
+                            Occurrence::Data(_) => self.fields_positive(*adt, occurrence),
+                            Occurrence::Param(_) => true,
+                        }
+                }
+            },
+        }
+    }
+
+    /// Whether the occurrences of `occurrence` in the definition of `data_def` (a data type that
+    /// may be mutually recursive with the target) are strictly positive
+    fn fields_positive(&mut self, data_def: DataDefId, occurrence: Occurrence) -> bool {
+        if !self.checking_fields.insert(data_def) {
+            return true;
+        }
+        let positive =
+            self.fields(data_def).iter().all(|field| self.positive(occurrence, field, 0));
+        self.checking_fields.remove(&data_def);
+        positive
+    }
+
+    /// Whether the `i`th parameter of `data_def` occurs only strictly positively in its definition
+    fn param_positive(&mut self, data_def: DataDefId, i: usize) -> bool {
+        let items = self.db.items(data_def.file);
+        let data = &items[data_def.def];
+        // The parameters are indices constrained by the constructors' result types rather than
+        // the types of fields
+        if data.variants.iter().any(|(_, variant)| variant.result.is_some()) {
+            return false;
+        }
+        if !self.checking_params.insert((data_def, i)) {
+            return true;
+        }
+        // The first parameter is bound by the outermost binder
+        let occurrence = Occurrence::Param((data.arity - 1 - i) as u32);
+        let positive =
+            self.fields(data_def).iter().all(|field| self.positive(occurrence, field, 0));
+        self.checking_params.remove(&(data_def, i));
+        positive
+    }
+}
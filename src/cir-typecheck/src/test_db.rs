@@ -0,0 +1,71 @@
+use std::sync::Mutex;
+
+use cir::db::{self, SourceDatabaseStorage};
+use cir::{DataDefId, FileId, SourceMap, Ty, ValueDefId};
+use cir_parse::{LowerDatabase, LowerDatabaseStorage};
+
+use crate::{TypecheckDatabase, TypecheckDatabaseStorage};
+
+#[salsa::database(SourceDatabaseStorage, LowerDatabaseStorage, TypecheckDatabaseStorage)]
+#[derive(Default)]
+pub(crate) struct TestDatabase {
+    storage: salsa::Storage<Self>,
+    executed: Mutex<Vec<String>>,
+}
+
+impl salsa::Database for TestDatabase {
+    fn salsa_event(&self, event: salsa::Event) {
+        if let salsa::EventKind::WillExecute { database_key } = event.kind {
+            self.executed.lock().unwrap().push(format!("{:?}", database_key.debug(self)));
+        }
+    }
+}
+
+impl TestDatabase {
+    pub(crate) fn value_def(&self, file: FileId, name: &str) -> ValueDefId {
+        let items = self.items(file);
+        let (def, _) = items.value_defs.iter().find(|(_, def)| def.name.symbol == name).unwrap();
+        ValueDefId { file, def }
+    }
+
+    pub(crate) fn data_def(&self, file: FileId, name: &str) -> DataDefId {
+        let items = self.items(file);
+        let (def, _) = items.data_defs.iter().find(|(_, def)| def.name.symbol == name).unwrap();
+        DataDefId { file, def }
+    }
+
+    /// The type of `def`, which must typecheck without errors
+    pub(crate) fn checked_ty(&self, def: ValueDefId) -> Ty {
+        let (_, errors) = self.elaborated_body_with_errors(def);
+        assert!(errors.is_empty(), "{:?}", errors);
+        self.type_of_value_def(def)
+    }
+
+    pub(crate) fn type_error_messages(&self, file: FileId) -> Vec<String> {
+        self.type_errors(file).iter().map(|err| err.display(self).to_string()).collect()
+    }
+
+    pub(crate) fn with_files(files: &[(&str, &str)]) -> (Self, Vec<FileId>) {
+        Self::new(files, false)
+    }
+
+    /// Like `with_files` but with the prelude loaded, the returned files exclude the prelude
+    pub(crate) fn with_prelude(files: &[(&str, &str)]) -> (Self, Vec<FileId>) {
+        Self::new(files, true)
+    }
+
+    fn new(files: &[(&str, &str)], prelude: bool) -> (Self, Vec<FileId>) {
+        let mut db = Self::default();
+        let mut source_map = SourceMap::default();
+        if prelude {
+            cir_parse::add_prelude(&mut source_map);
+        }
+        let files = files.iter().map(|&(name, text)| source_map.add(name, text)).collect();
+        db::set_source_map(&mut db, &source_map);
+        (db, files)
+    }
+
+    pub(crate) fn take_executed(&self) -> Vec<String> {
+        std::mem::take(&mut self.executed.lock().unwrap())
+    }
+}
@@ -1,11 +1,26 @@
+use cir::subst::Substitute;
 use cir::Ty;
 
-use crate::subst::Substitute;
+use crate::test_db::TestDatabase;
 use crate::TypecheckCtxt;
 
 fn check_expr(s: &str) -> Ty {
+    let db = TestDatabase::default();
     let body = cir_parse::parse_body(s);
-    TypecheckCtxt { body }.check_body()
+    let expr = body.expr;
+    let mut tcx = TypecheckCtxt::new(&db, body);
+    let ty = tcx.check_expr(expr);
+    assert!(tcx.errors.is_empty(), "{:?}", tcx.errors);
+    ty
+}
+
+fn expr_errors(s: &str) -> Vec<String> {
+    let db = TestDatabase::default();
+    let body = cir_parse::parse_body(s);
+    let expr = body.expr;
+    let mut tcx = TypecheckCtxt::new(&db, body);
+    tcx.check_expr(expr);
+    tcx.errors.iter().map(|err| err.display(&db).to_string()).collect()
 }
 
 #[test]
@@ -47,12 +62,159 @@ fn test_typeck_type_application() {
     assert_eq!(check_expr("(\\@a.\\@b.\\x:a.\\y:b.x) @Int @Bool"), ty!(Int -> Bool -> Int));
 }
 
+#[test]
+fn test_typeck_binder_under_type_lambda() {
+    // The type of `x` refers to `a`, which is one binder further out under `\@b`
+    assert_eq!(check_expr("\\@a.\\x:a.\\@b.x"), ty!(forall a. a -> forall b. a));
+    assert_eq!(
+        check_expr("\\@a.\\x:a.\\@b.\\y:b. (x, y)"),
+        ty!(forall a. a -> forall b. b -> (a, b))
+    );
+}
+
 #[test]
 fn test_typeck_partial_type_application() {
     assert_eq!(check_expr("(\\@a.\\@b.\\x:a.\\y:b.x) @Int"), ty!(forall b. Int -> b -> Int));
     // Check the names of forall binders are not meaningful for equality
     assert_eq!(check_expr("(\\@a.\\@b.\\x:a.\\y:b.x) @Int"), ty!(forall a. Int -> a -> Int));
 }
+
+#[test]
+fn test_typeck_operators() {
+    assert_eq!(check_expr("1 + 2 * 3 - 4 / 5 % 6"), ty!(Int));
+    assert_eq!(check_expr("\\x:Int. -x"), ty!(Int -> Int));
+    assert_eq!(check_expr("\\x:Int. x < 1 && x != 0 || !(x >= 5)"), ty!(Int -> Bool));
+    assert_eq!(check_expr("true == false"), ty!(Bool));
+}
+
+#[test]
+fn test_typeck_float() {
+    assert_eq!(check_expr("1.5 * -2.0e3 + 1e-3 % 2.0"), ty!(Float));
+    assert_eq!(check_expr("\\x:Float. -x"), ty!(Float -> Float));
+    assert_eq!(check_expr("\\x:Float. x < 0.5 || x == 1.0"), ty!(Float -> Bool));
+}
+
+#[test]
+fn test_typeck_string_and_char() {
+    assert_eq!(check_expr(r#""a" == "b""#), ty!(Bool));
+    assert_eq!(check_expr(r"\c:Char. c != 'x'"), ty!(Char -> Bool));
+    assert_eq!(check_expr(r#"stringLength (stringConcat "ab" "c")"#), ty!(Int));
+    assert_eq!(check_expr(r#"charAt "abc""#), ty!(Int -> Char));
+}
+
+#[test]
+fn test_typeck_tuple() {
+    assert_eq!(check_expr("()"), ty!(()));
+    assert_eq!(check_expr("(1, (true, ()))"), ty!((Int, (Bool, ()))));
+    assert_eq!(
+        check_expr("\\@a.\\@b.\\p:(a, b). match p { (x, y) -> (y, x) }"),
+        ty!(forall a. forall b. (a, b) -> (b, a))
+    );
+    assert_eq!(
+        check_expr("(\\@a.\\x:a. (x, x)) @(Int, Bool)"),
+        ty!((Int, Bool) -> ((Int, Bool), (Int, Bool)))
+    );
+}
+
+#[test]
+fn test_typeck_record() {
+    assert_eq!(check_expr("{ y = true, x = 1 }"), ty!({ x: Int, y: Bool }));
+    assert_eq!(check_expr("\\r:{ x: Int, y: Bool }. r.y"), ty!({ y: Bool, x: Int } -> Bool));
+    assert_eq!(check_expr("{ p = { q = 1 } }.p.q"), ty!(Int));
+    assert_eq!(
+        check_expr("(\\@a.\\x:a. { fst = x, snd = x }) @Int"),
+        ty!(Int -> { snd: Int, fst: Int })
+    );
+}
+
+#[test]
+fn test_typeck_existential() {
+    let counter = "pack @Int (0, \\n:Int. n + 1, \\n:Int. n) as exists c. (c, c -> c, c -> Int)";
+    assert_eq!(check_expr(counter), ty!(exists c. (c, c -> c, c -> Int)));
+    assert_eq!(
+        check_expr(&format!(
+            "unpack @c counter = {} in match counter {{ (zero, inc, get) -> get (inc zero) }}",
+            counter
+        )),
+        ty!(Int)
+    );
+    // Variables bound outside of the `unpack` can be used in its body
+    assert_eq!(
+        check_expr(
+            "\\@a.\\x:a.\\p:(exists b. (b, b -> a)). unpack @b q = p in match q { (y, f) -> (x, f y) }"
+        ),
+        ty!(forall a. a -> (exists b. (b, b -> a)) -> (a, a))
+    );
+    // The annotation is normalized to an existential type
+    assert_eq!(
+        check_expr("pack @Int (0, 1) as (\\(f : * -> *). exists a. f a) (\\b. (b, b))"),
+        ty!(exists a. (a, a))
+    );
+}
+
+#[test]
+fn test_typeck_existential_escape() {
+    assert_eq!(
+        expr_errors("unpack @a x = pack @Int 0 as exists a. a in x"),
+        ["the hidden type of `unpack` escapes its scope"]
+    );
+}
+
+#[test]
+fn test_typeck_type_operators() {
+    assert_eq!(
+        check_expr("\\@(f : * -> *).\\x:f Int. x"),
+        ty!(forall (f : * -> *). f Int -> f Int)
+    );
+    // Type operators are applied before types are compared
+    assert_eq!(check_expr("\\x:(\\a. a -> a) Int. x"), ty!((Int -> Int) -> Int -> Int));
+    assert_eq!(check_expr("(\\@(f : * -> *).\\x:f Int. x) @(\\a. (a, a)) (1, 2)"), ty!((Int, Int)));
+    // The applied type operator may itself return a type operator
+    assert_eq!(check_expr("\\x:(\\a. \\b. (b, a)) Int Bool. x"), ty!((Bool, Int) -> (Bool, Int)));
+    assert_ne!(ty!(forall (f : * -> *). Int), ty!(forall a. Int));
+}
+
+#[test]
+fn test_typeck_recursive_type() {
+    assert_eq!(
+        check_expr("\\xs:(mu l. (Int, l)). unfold xs"),
+        ty!((mu l. (Int, l)) -> (Int, mu l. (Int, l)))
+    );
+    assert_eq!(
+        check_expr("\\xs:(mu l. (Int, l)). fold @(mu l. (Int, l)) (1, xs)"),
+        ty!((mu l. (Int, l)) -> mu l. (Int, l))
+    );
+    // Recursive types are iso-recursive, a type is not equal to its unfolding
+    assert_ne!(
+        check_expr("\\xs:(mu l. (Int, l)). unfold xs"),
+        ty!((mu l. (Int, l)) -> mu l. (Int, l))
+    );
+    assert_eq!(
+        check_expr("\\@a.\\xs:(mu l. (a, l)). unfold xs"),
+        ty!(forall a. (mu l. (a, l)) -> (a, mu l. (a, l)))
+    );
+}
+
+#[test]
+fn test_typeck_if() {
+    assert_eq!(check_expr("\\x:Int. if x < 0 then -x else x"), ty!(Int -> Int));
+    assert_eq!(
+        check_expr("\\b:Bool. if b then \\x:Int. x else \\y:Int. 0"),
+        ty!(Bool -> Int -> Int)
+    );
+}
+
+#[test]
+fn test_typeck_let() {
+    assert_eq!(check_expr("let x = 1 in x + 1"), ty!(Int));
+    assert_eq!(check_expr("let x: Bool = true in \\y:Int. x"), ty!(Int -> Bool));
+    // The binder is not in scope in its own definition
+    assert_eq!(check_expr("\\x:Bool. let x = 1 in x"), ty!(Bool -> Int));
+    assert_eq!(check_expr("\\x:Int. let x = x < 0 in x"), ty!(Int -> Bool));
+    // Let-bound type abstractions remain polymorphic
+    assert_eq!(check_expr("let id = \\@a.\\x:a.x in id @Bool (id @Int 1 == 1)"), ty!(Bool));
+}
+
 #[test]
 fn test_typeck_simple_app() {
     assert_eq!(check_expr("(\\x:Int.x) 5"), ty!(Int));
@@ -61,6 +223,9 @@ fn test_typeck_simple_app() {
 #[test]
 fn test_typeck_higher_order_app() {
     assert_eq!(check_expr("(\\p:Int -> Bool.\\x:Int.p x) (\\x:Int.false) 0"), ty!(Bool));
+    // Arguments without annotations are checked against the parameter type
+    assert_eq!(check_expr("(\\f:Int -> Int. f 1) (\\x. x + 1)"), ty!(Int));
+    assert_eq!(check_expr("(\\f:Int -> Int -> Int. f 1 2) (\\x:Int. \\y. x + y)"), ty!(Int));
 }
 
 #[test]
@@ -77,6 +242,45 @@ fn test_higher_rank_app() {
     assert_eq!(check_expr("(\\@a.\\f:(forall b. b -> a).\\x:a.x) @Int (\\@b.\\y:b.0) 0"), ty!(Int));
 }
 
+#[test]
+fn test_subsumption_app() {
+    // A polymorphic argument is instantiated
+    assert_eq!(check_expr("(\\f:Int -> Int. f 1) (\\@a.\\x:a.x)"), ty!(Int));
+    // Including under the parameters of the expected function type
+    assert_eq!(
+        check_expr("(\\f:Int -> (forall b. b -> Int). f 1 @Bool true) (\\@a.\\x:a.\\@b.\\y:b.x)"),
+        ty!(Int)
+    );
+    // A more polymorphic argument type is required by subsumption of the parameter
+    assert_eq!(
+        check_expr("(\\h:((forall a. a -> a) -> Int). h (\\@a.\\x:a.x)) (\\g:Int -> Int. g 1)"),
+        ty!(Int)
+    );
+    // The expected type is skolemised
+    assert_eq!(
+        check_expr("(\\f:(forall b. b -> b -> b). f @Int 1 2) (\\@a.\\x:a.\\@c.\\y:c.y)"),
+        ty!(Int)
+    );
+    // A variable that does not occur can be instantiated with any type
+    assert_eq!(check_expr("(\\f:Int -> Int. f 1) (\\@a.\\x:Int.x)"), ty!(Int));
+}
+
+#[test]
+fn test_subsumption_app_less_polymorphic() {
+    assert_eq!(
+        expr_errors("(\\f:(forall a. a -> a). 0) (\\x:Int.x)"),
+        ["type mismatch: expected `forall a. a -> a`, found `Int -> Int`"]
+    );
+}
+
+#[test]
+fn test_subsumption_app_contravariant() {
+    assert_eq!(
+        expr_errors("(\\h:(Int -> Int) -> Int. h (\\x:Int.x)) (\\g:(forall a. a -> a). g @Int 1)"),
+        ["type mismatch: expected `(Int -> Int) -> Int`, found `(forall a. a -> a) -> Int`"]
+    );
+}
+
 #[test]
 fn test_subst() {
     use cir_parse::parse_ty;
@@ -93,3 +297,23 @@ fn test_subst() {
     let ty = parse_ty("forall a. (forall b. a -> b) -> a");
     assert_eq!(ty.skip_binder().substitute(&ty!(Int)), ty!((forall b. (Int -> b)) -> Int));
 }
+
+#[test]
+fn test_normalize() {
+    use crate::normalize::normalize;
+    use cir_parse::parse_ty;
+    let ty = parse_ty("forall (f : * -> *). (\\g. \\a. g (g a)) f Int");
+    assert_eq!(normalize(&ty), parse_ty("forall (f : * -> *). f (f Int)"));
+    // Normalizing under a binder must not capture the free variables of the argument
+    let ty = parse_ty("forall a. (\\b. forall a. b -> a) a");
+    assert_eq!(normalize(&ty), ty!(forall a. forall c. a -> c));
+}
+
+#[test]
+fn test_subst_shifts_free_variables() {
+    use cir_parse::parse_ty;
+    // Instantiating `b` with the variable `a` bound outside must not capture it under `forall c`
+    let ty = parse_ty("forall a. forall b. forall c. b -> c").skip_binder().skip_binder();
+    let a = ty!(forall a. a).skip_binder();
+    assert_eq!(ty.substitute(&a), ty!(forall a. forall c. a -> c).skip_binder());
+}
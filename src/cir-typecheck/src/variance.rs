@@ -0,0 +1,194 @@
+use std::fmt;
+use std::sync::Arc;
+
+use cir::{DataDefId, Ty, TyKind};
+use rustc_hash::FxHashMap;
+
+use crate::db;
+use crate::TypecheckDatabase;
+
+/// How the type of a value changes as a type parameter changes, e.g. `List` is covariant as a
+/// `List Int` holds `Int`s while `Int -> Bool` consumes `Int`s so `Pred` in
+/// `data Pred a = Pred (a -> Bool)` is contravariant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Variance {
+    /// The parameter only occurs in positive positions, i.e. to the right of an even number of
+    /// arrows
+    Covariant,
+    /// The parameter only occurs in negative positions
+    Contravariant,
+    /// The parameter occurs in both positive and negative positions (or as the argument of a
+    /// type variable)
+    Invariant,
+    /// The parameter does not occur at all, i.e. it is a phantom parameter
+    Bivariant,
+}
+
+impl Variance {
+    /// The variance of the occurrences in both `self` and `other`
+    fn join(self, other: Self) -> Self {
+        match (self, other) {
+            (Variance::Bivariant, variance) | (variance, Variance::Bivariant) => variance,
+            (a, b) if a == b => a,
+            _ => Variance::Invariant,
+        }
+    }
+
+    /// The variance of an occurrence with variance `other` within a position of variance `self`
+    fn compose(self, other: Self) -> Self {
+        match (self, other) {
+            (Variance::Bivariant, _) | (_, Variance::Bivariant) => Variance::Bivariant,
+            (Variance::Covariant, variance) => variance,
+            (Variance::Contravariant, variance) => variance.flip(),
+            (Variance::Invariant, _) => Variance::Invariant,
+        }
+    }
+
+    fn flip(self) -> Self {
+        match self {
+            Variance::Covariant => Variance::Contravariant,
+            Variance::Contravariant => Variance::Covariant,
+            variance => variance,
+        }
+    }
+}
+
+/// `+`, `-`, `=` and `*` respectively, e.g. `List` is displayed as `+`
+impl fmt::Display for Variance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Variance::Covariant => "+",
+            Variance::Contravariant => "-",
+            Variance::Invariant => "=",
+            Variance::Bivariant => "*",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+pub(crate) fn variances(db: &dyn TypecheckDatabase, data_def: DataDefId) -> Arc<[Variance]> {
+    let mut vcx = VarianceCtxt { db, fields: Default::default(), variances: Default::default() };
+    vcx.collect(data_def);
+    vcx.solve();
+    Arc::from(vcx.variances.remove(&data_def).unwrap())
+}
+
+/// Infers the variances of the parameters of a set of (possibly mutually recursive) data types
+struct VarianceCtxt<'db> {
+    db: &'db dyn TypecheckDatabase,
+    /// The (normalized) fields of every variant of each data type whose variances are solved
+    /// for, the parameters are bound as in `VariantData::fields`
+    fields: FxHashMap<DataDefId, Vec<Ty>>,
+    variances: FxHashMap<DataDefId, Vec<Variance>>,
+}
+
+impl VarianceCtxt<'_> {
+    /// Adds `data_def` and the data types its fields refer to (transitively), initially assuming
+    /// every parameter is bivariant. The parameters of data types whose constructors' signatures
+    /// refine them are invariant.
+    fn collect(&mut self, data_def: DataDefId) {
+        if self.variances.contains_key(&data_def) {
+            return;
+        }
+        let items = self.db.items(data_def.file);
+        let data = &items[data_def.def];
+        let fields = db::data_def_fields(self.db, data_def);
+        let mut adts = vec![];
+        for field in &fields {
+            collect_adts(field, &mut adts);
+        }
+        if data.variants.iter().any(|(_, variant)| variant.result.is_some()) {
+            self.variances.insert(data_def, vec![Variance::Invariant; data.arity]);
+        } else {
+            self.variances.insert(data_def, vec![Variance::Bivariant; data.arity]);
+            self.fields.insert(data_def, fields);
+        }
+        for adt in adts {
+            self.collect(adt);
+        }
+    }
+
+    /// Recomputes the variances until they no longer change, they only ever increase (towards
+    /// invariant) so this terminates
+    fn solve(&mut self) {
+        let data_defs = self.fields.keys().copied().collect::<Vec<_>>();
+        loop {
+            let mut changed = false;
+            for &data_def in &data_defs {
+                let arity = self.variances[&data_def].len();
+                let variances = (0..arity)
+                    .map(|i| {
+                        // The first parameter is bound by the outermost binder
+                        let index = (arity - 1 - i) as u32;
+                        self.fields[&data_def].iter().fold(Variance::Bivariant, |acc, field| {
+                            acc.join(self.variance_in(field, index, Variance::Covariant))
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                if variances != self.variances[&data_def] {
+                    self.variances.insert(data_def, variances);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// The variance of the occurrences of the type variable `index` in `ty`, which is in a
+    /// position of variance `position`
+    fn variance_in(&self, ty: &Ty, index: u32, position: Variance) -> Variance {
+        match ty.kind() {
+            TyKind::Scalar(_) | TyKind::Err => Variance::Bivariant,
+            TyKind::Var(var) if var.index() == index => position,
+            TyKind::Var(_) => Variance::Bivariant,
+            TyKind::Fn(l, r) => {
+                let l = self.variance_in(l, index, position.compose(Variance::Contravariant));
+                l.join(self.variance_in(r, index, position))
+            }
+            // Nothing is known about how a type variable uses its argument
+            TyKind::App(f, x) => {
+                let f = self.variance_in(f, index, position);
+                f.join(self.variance_in(x, index, position.compose(Variance::Invariant)))
+            }
+            // The methods of the class may use its argument anywhere
+            TyKind::Constrained(_, arg, ty) => {
+                let arg = self.variance_in(arg, index, position.compose(Variance::Invariant));
+                arg.join(self.variance_in(ty, index, position))
+            }
+            TyKind::ForAll(_, ty) | TyKind::Exists(ty) | TyKind::Mu(ty) | TyKind::Lambda(_, ty) =>
+                self.variance_in(ty, index + 1, position),
+            TyKind::Tuple(tys) => tys.iter().fold(Variance::Bivariant, |acc, ty| {
+                acc.join(self.variance_in(ty, index, position))
+            }),
+            TyKind::Record(fields) => fields.iter().fold(Variance::Bivariant, |acc, (_, ty)| {
+                acc.join(self.variance_in(ty, index, position))
+            }),
+            TyKind::Adt(adt, args) => args.iter().zip(&self.variances[adt]).fold(
+                Variance::Bivariant,
+                |acc, (arg, &param)| {
+                    acc.join(self.variance_in(arg, index, position.compose(param)))
+                },
+            ),
+        }
+    }
+}
+
+fn collect_adts(ty: &Ty, acc: &mut Vec<DataDefId>) {
+    match ty.kind() {
+        TyKind::Scalar(_) | TyKind::Var(_) | TyKind::Err => {}
+        TyKind::Fn(l, r) | TyKind::App(l, r) | TyKind::Constrained(_, l, r) => {
+            collect_adts(l, acc);
+            collect_adts(r, acc);
+        }
+        TyKind::ForAll(_, ty) | TyKind::Exists(ty) | TyKind::Mu(ty) | TyKind::Lambda(_, ty) =>
+            collect_adts(ty, acc),
+        TyKind::Tuple(tys) => tys.iter().for_each(|ty| collect_adts(ty, acc)),
+        TyKind::Record(fields) => fields.iter().for_each(|(_, ty)| collect_adts(ty, acc)),
+        TyKind::Adt(adt, args) => {
+            acc.push(*adt);
+            args.iter().for_each(|arg| collect_adts(arg, acc));
+        }
+    }
+}
@@ -1 +1,27 @@
+use std::sync::Arc;
 
+use std::path::Path;
+
+use crate::{FileId, SourceMap};
+
+#[salsa::query_group(SourceDatabaseStorage)]
+pub trait SourceDatabase {
+    #[salsa::input]
+    fn file_text(&self, file: FileId) -> Arc<str>;
+
+    #[salsa::input]
+    fn file_path(&self, file: FileId) -> Arc<Path>;
+
+    /// All files that make up the program
+    #[salsa::input]
+    fn files(&self) -> Arc<Vec<FileId>>;
+}
+
+/// Sets the text of every file in `source_map` as database inputs
+pub fn set_source_map(db: &mut dyn SourceDatabase, source_map: &SourceMap) {
+    for file in source_map.files() {
+        db.set_file_text(file, Arc::clone(source_map.source(file)));
+        db.set_file_path(file, Arc::from(Path::new(source_map.name(file))));
+    }
+    db.set_files(Arc::new(source_map.files().collect()));
+}
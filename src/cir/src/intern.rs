@@ -6,6 +6,7 @@ use std::sync::Arc;
 
 use dashmap::{DashSet, SharedValue};
 use rustc_hash::FxHasher;
+use smol_str::SmolStr;
 
 use crate::TyData;
 
@@ -92,3 +93,10 @@ impl Intern for TyData {
         &INTERNER
     }
 }
+
+impl Intern for SmolStr {
+    fn interner() -> &'static Interner<Self> {
+        static INTERNER: Interner<SmolStr> = Interner::new();
+        &INTERNER
+    }
+}
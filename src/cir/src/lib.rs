@@ -4,44 +4,219 @@ use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::ops::Index;
 
-use codespan::Span;
 use la_arena::{Arena, Idx};
 use smallvec::{smallvec, SmallVec};
-use smol_str::SmolStr;
+pub use smol_str::SmolStr;
 
 pub use self::intern::{Intern, Interned};
+pub use self::source_map::{FileId, SourceMap, Span};
 
-mod db;
+pub mod db;
 mod intern;
+mod source_map;
+pub mod subst;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Default, PartialEq, Eq)]
 pub struct Items {
     pub items: Vec<Item>,
+    pub value_defs: Arena<ValueDefData>,
+    pub data_defs: Arena<DataDefData>,
+    pub type_synonyms: Arena<TypeSynonymData>,
+    pub classes: Arena<ClassData>,
+    pub instances: Arena<InstanceData>,
+    pub bodies: Arena<BodyData>,
+}
+
+impl Index<ValueDef> for Items {
+    type Output = ValueDefData;
+
+    fn index(&self, index: ValueDef) -> &Self::Output {
+        &self.value_defs[index]
+    }
+}
+
+impl Index<DataDef> for Items {
+    type Output = DataDefData;
+
+    fn index(&self, index: DataDef) -> &Self::Output {
+        &self.data_defs[index]
+    }
+}
+
+impl Index<TypeSynonym> for Items {
+    type Output = TypeSynonymData;
+
+    fn index(&self, index: TypeSynonym) -> &Self::Output {
+        &self.type_synonyms[index]
+    }
+}
+
+impl Index<Class> for Items {
+    type Output = ClassData;
+
+    fn index(&self, index: Class) -> &Self::Output {
+        &self.classes[index]
+    }
+}
+
+impl Index<Instance> for Items {
+    type Output = InstanceData;
+
+    fn index(&self, index: Instance) -> &Self::Output {
+        &self.instances[index]
+    }
+}
+
+impl Index<Body> for Items {
+    type Output = BodyData;
+
+    fn index(&self, index: Body) -> &Self::Output {
+        &self.bodies[index]
+    }
 }
 
 pub type ValueDef = Idx<ValueDefData>;
 pub type DataDef = Idx<DataDefData>;
+pub type Variant = Idx<VariantData>;
+pub type TypeSynonym = Idx<TypeSynonymData>;
+pub type Class = Idx<ClassData>;
+pub type Instance = Idx<InstanceData>;
+
+/// A `ValueDef` qualified by the file it was lowered from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ValueDefId {
+    pub file: FileId,
+    pub def: ValueDef,
+}
+
+/// A `DataDef` qualified by the file it was lowered from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DataDefId {
+    pub file: FileId,
+    pub def: DataDef,
+}
+
+/// A `TypeSynonym` qualified by the file it was lowered from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TypeSynonymId {
+    pub file: FileId,
+    pub def: TypeSynonym,
+}
+
+/// A `Class` qualified by the file it was lowered from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClassId {
+    pub file: FileId,
+    pub def: Class,
+}
+
+/// An `Instance` qualified by the file it was lowered from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InstanceId {
+    pub file: FileId,
+    pub def: Instance,
+}
+
+/// The `index`th method of a class
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MethodId {
+    pub class: ClassId,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VariantId {
+    pub data_def: DataDefId,
+    pub variant: Variant,
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Item {
     ValueDef(ValueDef),
     DataDef(DataDef),
+    TypeSynonym(TypeSynonym),
+    Class(Class),
+    Instance(Instance),
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct DataDefData {}
+/// Variants may refer to the data type being defined and to data types defined later, as all
+/// data definitions are collected by the `DefMap` before any are lowered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataDefData {
+    pub name: Name,
+    /// The number of type parameters
+    pub arity: usize,
+    pub variants: Arena<VariantData>,
+}
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct ValueDefData {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantData {
     pub name: Name,
+    /// The kinds of the constructor's own type parameters, e.g. `b` and `c` in
+    /// `Pair : forall b c. Expr b -> Expr c -> Expr (b, c)`. Only constructors declared with a
+    /// signature have these.
+    pub ty_params: Vec<Kind>,
+    /// The types of the fields, type parameters of the data definition and then those of the
+    /// constructor are bound as if by enclosing `forall`s (the first parameter is the outermost)
+    pub fields: Vec<Ty>,
+    /// The arguments of the constructed type if it is not the data type applied to its
+    /// parameters, e.g. `[Int]` for `IntE : Int -> Expr Int`. The arguments are bound as in
+    /// `fields`.
+    pub result: Option<Vec<Ty>>,
+}
+
+/// `type Pair a b = forall r. (a -> b -> r) -> r`, uses of the synonym are expanded during
+/// lowering so it never appears in a `Ty`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeSynonymData {
+    pub name: Name,
+    /// The number of type parameters
+    pub arity: usize,
+    /// The expanded type, the parameters are bound as in `VariantData::fields`
     pub ty: Ty,
+}
+
+/// `class Eq a where eq : a -> a -> Bool`, a value of the class's dictionary type is a record of
+/// its methods (see `TyKind::Constrained`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassData {
+    pub name: Name,
+    /// The kind of the class's parameter
+    pub kind: Kind,
+    /// The types of the methods, the parameter is bound as if by an enclosing `forall`
+    pub methods: Vec<(Name, Ty)>,
+}
+
+/// `instance forall a. Eq a => Eq (List a) where eq = ...`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceData {
+    /// The span of the instance, or of the class in `deriving (...)` for a derived instance
+    pub span: Span,
+    pub class: ClassId,
+    /// The kinds of the instance's type parameters, e.g. `a` above
+    pub params: Vec<Kind>,
+    /// The instances the instance depends on, e.g. `Eq a` above. The parameters are bound in
+    /// these and in `head` as in `VariantData::fields`.
+    pub context: Vec<(ClassId, Ty)>,
+    /// The type the class is instantiated with, e.g. `List a` above
+    pub head: Ty,
+    /// The dictionary, a type lambda for each parameter around a record of the methods.
+    /// Typechecking adds a dictionary parameter for each constraint of the context.
+    pub body: Body,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueDefData {
+    pub name: Name,
+    /// The declared type, `None` if the type is inferred
+    pub ty: Option<Ty>,
     pub body: Body,
 }
 
 #[derive(Debug, Clone, Eq)]
 pub struct Name {
     pub span: Span,
-    pub symbol: SmolStr,
+    pub symbol: Symbol,
 }
 
 impl Hash for Name {
@@ -58,7 +233,38 @@ impl PartialEq for Name {
 
 impl Name {
     pub fn new(span: Span, symbol: impl AsRef<str>) -> Self {
-        Self { span, symbol: SmolStr::new(symbol) }
+        Self { span, symbol: Symbol::new(symbol) }
+    }
+}
+
+/// An interned identifier, equality and hashing are by pointer
+pub type Symbol = Interned<SmolStr>;
+
+impl Symbol {
+    pub fn new(s: impl AsRef<str>) -> Self {
+        Self::intern(SmolStr::new(s))
+    }
+
+    pub fn as_str(&self) -> &str {
+        SmolStr::as_str(self)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(s: &str) -> Self {
+        Self::new(s)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
     }
 }
 
@@ -67,10 +273,180 @@ pub type Expr = Idx<ExprData>;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ExprData {
     Var(Binder),
+    /// A reference to a top-level definition
+    Def(ValueDefId),
+    /// A reference to a data constructor
+    Ctor(VariantId),
+    /// A reference to a method of a class, typechecking replaces it by a field access on the
+    /// dictionary of the class, so it does not occur in elaborated bodies
+    Method(MethodId),
+    /// The dictionary of an instance, only introduced by typechecking
+    Instance(InstanceId),
     Lit(Lit),
     Lambda(Binder, Expr),
     App(Expr, Expr),
+    Case(Expr, Vec<Alt>),
+    /// `if c then t else e`, only the chosen branch is evaluated
+    If(Expr, Expr, Expr),
+    /// `let x = e1 in e2`, the binder is only in scope in `e2`
+    Let(Binder, Expr, Expr),
+    /// `let rec f = e1 and g = e2 in e`, the binders are in scope in every bound expression and
+    /// the body
+    LetRec(Vec<(Binder, Expr)>, Expr),
+    /// A primitive binary operation on scalars
+    BinOp(BinOp, Expr, Expr),
+    /// A primitive unary operation on scalars
+    UnOp(UnOp, Expr),
+    /// A reference to a primitive function
+    Prim(Prim),
+    /// `(e1, e2, ...)`, the empty tuple `()` is the unit value
+    Tuple(Vec<Expr>),
+    /// `{ x = e1, y = e2 }`, the fields are sorted by name
+    Record(Vec<(Symbol, Expr)>),
+    /// `e.x`
+    Field(Expr, Symbol),
+    /// `pack @T e as exists a. U`, hides the type `T` of `e : U[a := T]`
+    Pack(Ty, Expr, Ty),
+    /// `unpack @a x = e1 in e2`, the type binder and then the value binder (of type `U` where
+    /// `e1 : exists a. U`) are in scope in `e2`
+    Unpack(Binder, Binder, Expr, Expr),
+    /// `fold @(mu a. T) e` where `e : T[a := mu a. T]`
+    Fold(Ty, Expr),
+    /// `unfold e` where `e : mu a. T`, the inverse of `fold`
+    Unfold(Expr),
     Type(Ty),
+    /// An expression that failed to lower (e.g. an unresolved name)
+    Err,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    /// `+`, integer addition wraps on overflow
+    Add,
+    /// `-`, integer subtraction wraps on overflow
+    Sub,
+    /// `*`, integer multiplication wraps on overflow
+    Mul,
+    /// `/`, integer division rounds towards zero
+    Div,
+    /// `%`, has the sign of the dividend
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `&&`, short-circuiting
+    And,
+    /// `||`, short-circuiting
+    Or,
+}
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnOp {
+    /// `!`
+    Not,
+    /// `-`, integer negation wraps on overflow
+    Neg,
+}
+
+/// A built-in function, in scope everywhere unless shadowed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Prim {
+    /// `stringLength : String -> Int`, the number of characters in the string
+    StringLength,
+    /// `stringConcat : String -> String -> String`
+    StringConcat,
+    /// `charAt : String -> Int -> Char`, the character at the given (zero-based) index
+    CharAt,
+    /// `showInt : Int -> String`
+    ShowInt,
+    /// `showFloat : Float -> String`, always with a fractional part or exponent, e.g. `3.0`
+    ShowFloat,
+    /// `showChar : Char -> String`, quoted with the same escapes as literals
+    ShowChar,
+    /// `showString : String -> String`, quoted with the same escapes as literals
+    ShowString,
+}
+
+impl Prim {
+    pub const ALL: [Prim; 7] = [
+        Prim::StringLength,
+        Prim::StringConcat,
+        Prim::CharAt,
+        Prim::ShowInt,
+        Prim::ShowFloat,
+        Prim::ShowChar,
+        Prim::ShowString,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Prim::StringLength => "stringLength",
+            Prim::StringConcat => "stringConcat",
+            Prim::CharAt => "charAt",
+            Prim::ShowInt => "showInt",
+            Prim::ShowFloat => "showFloat",
+            Prim::ShowChar => "showChar",
+            Prim::ShowString => "showString",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|prim| prim.name() == name)
+    }
+
+    /// The number of arguments the primitive takes
+    pub fn arity(self) -> usize {
+        match self {
+            Prim::StringLength
+            | Prim::ShowInt
+            | Prim::ShowFloat
+            | Prim::ShowChar
+            | Prim::ShowString => 1,
+            Prim::StringConcat | Prim::CharAt => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alt {
+    pub pat: Pat,
+    pub body: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pat {
+    /// A constructor applied to a type binder for each of its own type parameters (see
+    /// `VariantData::ty_params`) and a binder for each of its fields
+    Variant(VariantId, Vec<Binder>, Vec<Binder>),
+    /// A binder for each component of a tuple, e.g. `(x, y)`
+    Tuple(Vec<Binder>),
+    /// A pattern that failed to lower, its type binders and binders are in scope in the body of
+    /// the `Alt` but the binders have an error type
+    Err(Vec<Binder>, Vec<Binder>),
 }
 
 pub type Binder = Idx<BinderData>;
@@ -78,16 +454,48 @@ pub type Binder = Idx<BinderData>;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BinderData {
     Val(Ty),
-    Ty,
+    Ty(Kind),
+    /// A binder introduced by a pattern (or `unpack`), its type is determined by the scrutinee
+    Pat,
+    /// A binder introduced by a `let` without a type annotation, its type is that of the bound
+    /// expression
+    Let,
+    /// A lambda binder without a type annotation, its type is inferred in a definition without a
+    /// signature and otherwise given by the expected function type
+    Infer,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Lit {
     Bool(bool),
     Int(i64),
+    Float(Float),
+    Char(char),
+    String(SmolStr),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// A float stored as its bits so that it can be compared and hashed.
+/// Equality is bitwise, unlike the IEEE equality used when evaluating `==`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Float(u64);
+
+impl Float {
+    pub fn new(f: f64) -> Self {
+        Self(f.to_bits())
+    }
+
+    pub fn value(self) -> f64 {
+        f64::from_bits(self.0)
+    }
+}
+
+impl fmt::Debug for Float {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.value())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Path {
     pub segments: SmallVec<[PathSegment; 1]>,
 }
@@ -96,11 +504,56 @@ impl Path {
     pub fn single(name: Name) -> Self {
         Self { segments: smallvec![PathSegment { name }] }
     }
+
+    pub fn names(&self) -> impl Iterator<Item = &Name> {
+        self.segments.iter().map(|segment| &segment.name)
+    }
+
+    /// The qualifying segments, i.e. all but the last segment
+    pub fn qualifier(&self) -> impl Iterator<Item = &Name> {
+        self.segments[..self.segments.len() - 1].iter().map(|segment| &segment.name)
+    }
+
+    pub fn last(&self) -> &Name {
+        &self.segments.last().expect("empty path").name
+    }
+
+    pub fn as_single(&self) -> Option<&Name> {
+        match &self.segments[..] {
+            [segment] => Some(&segment.name),
+            _ => None,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        let first = self.segments.first().expect("empty path").name.span;
+        first.merge(self.last().span)
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl FromIterator<Name> for Path {
+    fn from_iter<T: IntoIterator<Item = Name>>(iter: T) -> Self {
+        let segments = iter.into_iter().map(|name| PathSegment { name }).collect::<SmallVec<_>>();
+        assert!(!segments.is_empty(), "empty path");
+        Self { segments }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", segment.name.symbol.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PathSegment {
-    name: Name,
+    pub name: Name,
 }
 
 pub type Body = Idx<BodyData>;
@@ -152,7 +605,7 @@ impl TyData {
 
     pub fn skip_binder(&self) -> Ty {
         match self.kind() {
-            TyKind::ForAll(ty) => Ty::clone(ty),
+            TyKind::ForAll(_, ty) => Ty::clone(ty),
             _ => panic!("expected forall type"),
         }
     }
@@ -163,7 +616,32 @@ pub enum TyKind {
     Scalar(Scalar),
     Fn(Ty, Ty),
     Var(Debruijn),
-    ForAll(Ty),
+    ForAll(Kind, Ty),
+    /// `exists a. T`, the bound variable (of kind `*`) is represented as in `ForAll`
+    Exists(Ty),
+    /// The iso-recursive type `mu a. T`, the bound variable (of kind `*`) is represented as in
+    /// `ForAll` and stands for the type itself. It is not equal to its unfolding
+    /// `T[a := mu a. T]`, the two are converted by `fold` and `unfold`.
+    Mu(Ty),
+    /// A type operator `\a. T`, the bound variable is represented as in `ForAll`
+    Lambda(Kind, Ty),
+    /// The application of a type operator to an argument, e.g. `f Int` where `f : * -> *`.
+    /// Applications of data types are represented by `Adt` instead.
+    App(Ty, Ty),
+    /// A data type applied to type arguments, there are fewer arguments than parameters if the
+    /// data type is used as a type operator (e.g. `List` in `f List`)
+    Adt(DataDefId, Vec<Ty>),
+    /// `(T1, T2, ...)`, the empty tuple `()` is the unit type. There are no 1-tuples.
+    Tuple(Vec<Ty>),
+    /// `{ x: T1, y: T2 }`, the fields are sorted by name so that structurally equal record types
+    /// are interned to the same `Ty`
+    Record(Vec<(Symbol, Ty)>),
+    /// `C T => U`, a `U` that requires the dictionary of the instance of the class `C` for `T`.
+    /// Typechecking elaborates it to a function from the dictionary, whose type is the record of
+    /// the class's methods instantiated with `T`.
+    Constrained(ClassId, Ty, Ty),
+    /// The type of an expression that failed to lower or typecheck
+    Err,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -180,9 +658,23 @@ impl Debruijn {
         self <= other
     }
 
+    pub fn index(self) -> u32 {
+        self.0
+    }
+
     #[must_use]
     pub fn shifted_in(self) -> Self {
-        Self(self.0 + 1)
+        self.shifted_in_by(1)
+    }
+
+    #[must_use]
+    pub fn shifted_in_by(self, amount: u32) -> Self {
+        Self(self.0 + amount)
+    }
+
+    #[must_use]
+    pub fn shifted_out(self) -> Self {
+        Self(self.0 - 1)
     }
 }
 
@@ -192,7 +684,43 @@ impl fmt::Debug for TyKind {
             TyKind::Scalar(scalar) => write!(f, "{:?}", scalar),
             TyKind::Fn(l, r) => write!(f, "({:?} -> {:?})", l, r),
             TyKind::Var(var) => write!(f, "{:?}", var),
-            TyKind::ForAll(ty) => write!(f, "∀{:?}", ty),
+            TyKind::ForAll(Kind::Type, ty) => write!(f, "∀{:?}", ty),
+            TyKind::ForAll(kind, ty) => write!(f, "∀({:?}){:?}", kind, ty),
+            TyKind::Exists(ty) => write!(f, "∃{:?}", ty),
+            TyKind::Mu(ty) => write!(f, "μ{:?}", ty),
+            TyKind::Lambda(Kind::Type, ty) => write!(f, "λ{:?}", ty),
+            TyKind::Lambda(kind, ty) => write!(f, "λ({:?}){:?}", kind, ty),
+            TyKind::App(l, r) => write!(f, "({:?} {:?})", l, r),
+            TyKind::Adt(adt, args) if args.is_empty() => write!(f, "{:?}", adt),
+            TyKind::Adt(adt, args) => {
+                write!(f, "({:?}", adt)?;
+                for arg in args {
+                    write!(f, " {:?}", arg)?;
+                }
+                write!(f, ")")
+            }
+            TyKind::Tuple(tys) => {
+                write!(f, "(")?;
+                for (i, ty) in tys.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", ty)?;
+                }
+                write!(f, ")")
+            }
+            TyKind::Record(fields) => {
+                write!(f, "{{")?;
+                for (i, (name, ty)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, " {}: {:?}", name.as_str(), ty)?;
+                }
+                write!(f, " }}")
+            }
+            TyKind::Constrained(class, arg, ty) => write!(f, "({:?} {:?} => {:?})", class, arg, ty),
+            TyKind::Err => write!(f, "{{error}}"),
         }
     }
 }
@@ -203,9 +731,40 @@ impl TyKind {
     }
 }
 
+/// The kind of a type variable, `*` is the kind of the types of values
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// `*`
+    Type,
+    /// `k1 -> k2`, the kind of type operators
+    Fn(Box<Kind>, Box<Kind>),
+}
+
+impl fmt::Debug for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Type => write!(f, "*"),
+            Kind::Fn(l, r) if matches!(**l, Kind::Fn(..)) => write!(f, "({:?}) -> {:?}", l, r),
+            Kind::Fn(l, r) => write!(f, "{:?} -> {:?}", l, r),
+        }
+    }
+}
+
+/// Visibility only restricts which modules can name an item. A public item may mention private
+/// types, e.g. `pub let x: T` where `T` is private is allowed, and other modules can then use `x`
+/// without being able to name its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    Public,
+    /// Only visible within the defining module and its descendants
+    Private,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Scalar {
     Bool,
     Int,
     Float,
+    Char,
+    String,
 }
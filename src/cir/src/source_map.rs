@@ -0,0 +1,105 @@
+use std::ffi::{OsStr, OsString};
+use std::ops::Range;
+use std::sync::Arc;
+
+use codespan::{ByteIndex, Files};
+use codespan_reporting::files::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileId(pub u32);
+
+/// A byte range within a particular file of a `SourceMap`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub file: FileId,
+    pub start: ByteIndex,
+    pub end: ByteIndex,
+}
+
+impl Span {
+    pub fn new(file: FileId, start: impl Into<ByteIndex>, end: impl Into<ByteIndex>) -> Self {
+        Self { file, start: start.into(), end: end.into() }
+    }
+
+    pub fn merge(self, other: Self) -> Self {
+        assert_eq!(self.file, other.file, "cannot merge spans from different files");
+        Self { file: self.file, start: self.start.min(other.start), end: self.end.max(other.end) }
+    }
+
+    pub fn range(self) -> Range<usize> {
+        self.start.to_usize()..self.end.to_usize()
+    }
+}
+
+/// The source text of every file in the program
+#[derive(Debug, Default, Clone)]
+pub struct SourceMap {
+    files: Files<Arc<str>>,
+    ids: Vec<codespan::FileId>,
+}
+
+impl SourceMap {
+    pub fn add(&mut self, name: impl Into<OsString>, source: impl Into<Arc<str>>) -> FileId {
+        let file = FileId(self.ids.len() as u32);
+        self.ids.push(self.files.add(name, source.into()));
+        file
+    }
+
+    pub fn update(&mut self, file: FileId, source: impl Into<Arc<str>>) {
+        self.files.update(self.codespan_id(file), source.into())
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = FileId> {
+        (0..self.ids.len() as u32).map(FileId)
+    }
+
+    pub fn name(&self, file: FileId) -> &OsStr {
+        self.files.name(self.codespan_id(file))
+    }
+
+    pub fn source(&self, file: FileId) -> &Arc<str> {
+        self.files.source(self.codespan_id(file))
+    }
+
+    pub fn snippet(&self, span: Span) -> &str {
+        &self.source(span.file)[span.range()]
+    }
+
+    fn codespan_id(&self, file: FileId) -> codespan::FileId {
+        self.ids[file.0 as usize]
+    }
+
+    fn try_codespan_id(&self, file: FileId) -> Result<codespan::FileId, Error> {
+        self.ids.get(file.0 as usize).copied().ok_or(Error::FileMissing)
+    }
+}
+
+impl<'a> codespan_reporting::files::Files<'a> for SourceMap {
+    type FileId = FileId;
+    type Name = String;
+    type Source = &'a str;
+
+    fn name(&'a self, file: FileId) -> Result<String, Error> {
+        codespan_reporting::files::Files::name(&self.files, self.try_codespan_id(file)?)
+    }
+
+    fn source(&'a self, file: FileId) -> Result<&'a str, Error> {
+        codespan_reporting::files::Files::source(&self.files, self.try_codespan_id(file)?)
+    }
+
+    fn line_index(&'a self, file: FileId, byte_index: usize) -> Result<usize, Error> {
+        codespan_reporting::files::Files::line_index(
+            &self.files,
+            self.try_codespan_id(file)?,
+            byte_index,
+        )
+    }
+
+    fn line_range(&'a self, file: FileId, line_index: usize) -> Result<Range<usize>, Error> {
+        codespan_reporting::files::Files::line_range(
+            &self.files,
+            self.try_codespan_id(file)?,
+            line_index,
+        )
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{Debruijn, Ty, TyKind};
+
+pub type Subst = Ty;
+
+pub trait Substitute {
+    /// Substitutes `subst` for the innermost bound variable, i.e. instantiates the body of a
+    /// `forall` whose binder has been removed
+    fn substitute(&self, subst: &Subst) -> Self;
+}
+
+impl Substitute for Ty {
+    fn substitute(&self, subst: &Subst) -> Self {
+        substitute_ty(self, subst, Debruijn::INNER)
+    }
+}
+
+fn substitute_ty(ty: &Ty, subst: &Subst, cutoff: Debruijn) -> Ty {
+    match ty.kind() {
+        TyKind::Scalar(_) | TyKind::Err => Ty::clone(ty),
+        TyKind::Adt(adt, args) =>
+            TyKind::Adt(*adt, args.iter().map(|arg| substitute_ty(arg, subst, cutoff)).collect())
+                .intern(),
+        TyKind::Tuple(tys) =>
+            TyKind::Tuple(tys.iter().map(|ty| substitute_ty(ty, subst, cutoff)).collect()).intern(),
+        TyKind::Record(fields) => TyKind::Record(
+            fields
+                .iter()
+                .map(|(name, ty)| (name.clone(), substitute_ty(ty, subst, cutoff)))
+                .collect(),
+        )
+        .intern(),
+        TyKind::Fn(f, x) =>
+            TyKind::Fn(substitute_ty(f, subst, cutoff), substitute_ty(x, subst, cutoff)).intern(),
+        // `subst` is moved under `cutoff` binders so its free variables must be shifted in
+        TyKind::Var(debruijn) if *debruijn == cutoff =>
+            shift_ty(subst, cutoff.index(), Debruijn::INNER),
+        // Variables bound outside the removed binder now refer to one binder less
+        TyKind::Var(debruijn) if *debruijn > cutoff => TyKind::Var(debruijn.shifted_out()).intern(),
+        TyKind::Var(_) => Ty::clone(ty),
+        TyKind::ForAll(kind, ty) =>
+            TyKind::ForAll(kind.clone(), substitute_ty(ty, subst, cutoff.shifted_in())).intern(),
+        TyKind::Exists(ty) =>
+            TyKind::Exists(substitute_ty(ty, subst, cutoff.shifted_in())).intern(),
+        TyKind::Mu(ty) => TyKind::Mu(substitute_ty(ty, subst, cutoff.shifted_in())).intern(),
+        TyKind::Lambda(kind, ty) =>
+            TyKind::Lambda(kind.clone(), substitute_ty(ty, subst, cutoff.shifted_in())).intern(),
+        TyKind::App(f, x) =>
+            TyKind::App(substitute_ty(f, subst, cutoff), substitute_ty(x, subst, cutoff)).intern(),
+        TyKind::Constrained(class, arg, ty) => TyKind::Constrained(
+            *class,
+            substitute_ty(arg, subst, cutoff),
+            substitute_ty(ty, subst, cutoff),
+        )
+        .intern(),
+    }
+}
+
+/// Whether the free variable `var` occurs in `ty`
+pub fn occurs(ty: &Ty, var: Debruijn) -> bool {
+    occurs_ty(ty, var, 0)
+}
+
+fn occurs_ty(ty: &Ty, var: Debruijn, binders: u32) -> bool {
+    match ty.kind() {
+        TyKind::Scalar(_) | TyKind::Err => false,
+        TyKind::Var(debruijn) => debruijn.index() == var.index() + binders,
+        TyKind::Fn(l, r) | TyKind::App(l, r) | TyKind::Constrained(_, l, r) =>
+            occurs_ty(l, var, binders) || occurs_ty(r, var, binders),
+        TyKind::ForAll(_, ty) | TyKind::Exists(ty) | TyKind::Mu(ty) | TyKind::Lambda(_, ty) =>
+            occurs_ty(ty, var, binders + 1),
+        TyKind::Adt(_, tys) | TyKind::Tuple(tys) =>
+            tys.iter().any(|ty| occurs_ty(ty, var, binders)),
+        TyKind::Record(fields) => fields.iter().any(|(_, ty)| occurs_ty(ty, var, binders)),
+    }
+}
+
+/// Replaces the free variable `var` of `ty` with `with`, unlike `substitute` no binder is removed
+/// so the other variables are unchanged
+pub fn replace(ty: &Ty, var: Debruijn, with: &Ty) -> Ty {
+    replace_ty(ty, var, with, 0)
+}
+
+fn replace_ty(ty: &Ty, var: Debruijn, with: &Ty, binders: u32) -> Ty {
+    match ty.kind() {
+        TyKind::Scalar(_) | TyKind::Err => Ty::clone(ty),
+        TyKind::Adt(adt, args) =>
+            TyKind::Adt(*adt, args.iter().map(|arg| replace_ty(arg, var, with, binders)).collect())
+                .intern(),
+        TyKind::Tuple(tys) =>
+            TyKind::Tuple(tys.iter().map(|ty| replace_ty(ty, var, with, binders)).collect())
+                .intern(),
+        TyKind::Record(fields) => TyKind::Record(
+            fields
+                .iter()
+                .map(|(name, ty)| (name.clone(), replace_ty(ty, var, with, binders)))
+                .collect(),
+        )
+        .intern(),
+        TyKind::Fn(f, x) =>
+            TyKind::Fn(replace_ty(f, var, with, binders), replace_ty(x, var, with, binders))
+                .intern(),
+        TyKind::Var(debruijn) if debruijn.index() == var.index() + binders =>
+            shift_in(with, binders),
+        TyKind::Var(_) => Ty::clone(ty),
+        TyKind::ForAll(kind, ty) =>
+            TyKind::ForAll(kind.clone(), replace_ty(ty, var, with, binders + 1)).intern(),
+        TyKind::Exists(ty) => TyKind::Exists(replace_ty(ty, var, with, binders + 1)).intern(),
+        TyKind::Mu(ty) => TyKind::Mu(replace_ty(ty, var, with, binders + 1)).intern(),
+        TyKind::Lambda(kind, ty) =>
+            TyKind::Lambda(kind.clone(), replace_ty(ty, var, with, binders + 1)).intern(),
+        TyKind::App(f, x) =>
+            TyKind::App(replace_ty(f, var, with, binders), replace_ty(x, var, with, binders))
+                .intern(),
+        TyKind::Constrained(class, arg, ty) => TyKind::Constrained(
+            *class,
+            replace_ty(arg, var, with, binders),
+            replace_ty(ty, var, with, binders),
+        )
+        .intern(),
+    }
+}
+
+/// Shifts the free variables of `ty` in by `amount`, e.g. to use the type of a binder under
+/// `amount` more type binders than where it was introduced
+pub fn shift_in(ty: &Ty, amount: u32) -> Ty {
+    shift_ty(ty, amount, Debruijn::INNER)
+}
+
+/// Shifts the free variables of `ty` out by one as the innermost binder is removed, or returns
+/// `None` if `ty` refers to the innermost bound variable (which would escape its scope)
+pub fn shift_out(ty: &Ty) -> Option<Ty> {
+    shift_out_ty(ty, Debruijn::INNER)
+}
+
+fn shift_out_ty(ty: &Ty, cutoff: Debruijn) -> Option<Ty> {
+    let kind = match ty.kind() {
+        TyKind::Scalar(_) | TyKind::Err => return Some(Ty::clone(ty)),
+        TyKind::Adt(adt, args) => TyKind::Adt(
+            *adt,
+            args.iter().map(|arg| shift_out_ty(arg, cutoff)).collect::<Option<_>>()?,
+        ),
+        TyKind::Tuple(tys) =>
+            TyKind::Tuple(tys.iter().map(|ty| shift_out_ty(ty, cutoff)).collect::<Option<_>>()?),
+        TyKind::Record(fields) => TyKind::Record(
+            fields
+                .iter()
+                .map(|(name, ty)| Some((name.clone(), shift_out_ty(ty, cutoff)?)))
+                .collect::<Option<_>>()?,
+        ),
+        TyKind::Fn(f, x) => TyKind::Fn(shift_out_ty(f, cutoff)?, shift_out_ty(x, cutoff)?),
+        TyKind::Var(debruijn) if *debruijn == cutoff => return None,
+        TyKind::Var(debruijn) if *debruijn > cutoff => TyKind::Var(debruijn.shifted_out()),
+        TyKind::Var(_) => return Some(Ty::clone(ty)),
+        TyKind::ForAll(kind, ty) =>
+            TyKind::ForAll(kind.clone(), shift_out_ty(ty, cutoff.shifted_in())?),
+        TyKind::Exists(ty) => TyKind::Exists(shift_out_ty(ty, cutoff.shifted_in())?),
+        TyKind::Mu(ty) => TyKind::Mu(shift_out_ty(ty, cutoff.shifted_in())?),
+        TyKind::Lambda(kind, ty) =>
+            TyKind::Lambda(kind.clone(), shift_out_ty(ty, cutoff.shifted_in())?),
+        TyKind::App(f, x) => TyKind::App(shift_out_ty(f, cutoff)?, shift_out_ty(x, cutoff)?),
+        TyKind::Constrained(class, arg, ty) =>
+            TyKind::Constrained(*class, shift_out_ty(arg, cutoff)?, shift_out_ty(ty, cutoff)?),
+    };
+    Some(kind.intern())
+}
+
+/// Shifts the variables of `ty` that are free (bound outside of `ty`) in by `amount`
+fn shift_ty(ty: &Ty, amount: u32, cutoff: Debruijn) -> Ty {
+    if amount == 0 {
+        return Ty::clone(ty);
+    }
+    match ty.kind() {
+        TyKind::Scalar(_) | TyKind::Err => Ty::clone(ty),
+        TyKind::Adt(adt, args) =>
+            TyKind::Adt(*adt, args.iter().map(|arg| shift_ty(arg, amount, cutoff)).collect())
+                .intern(),
+        TyKind::Tuple(tys) =>
+            TyKind::Tuple(tys.iter().map(|ty| shift_ty(ty, amount, cutoff)).collect()).intern(),
+        TyKind::Record(fields) => TyKind::Record(
+            fields.iter().map(|(name, ty)| (name.clone(), shift_ty(ty, amount, cutoff))).collect(),
+        )
+        .intern(),
+        TyKind::Fn(f, x) =>
+            TyKind::Fn(shift_ty(f, amount, cutoff), shift_ty(x, amount, cutoff)).intern(),
+        TyKind::Var(debruijn) if *debruijn >= cutoff =>
+            TyKind::Var(debruijn.shifted_in_by(amount)).intern(),
+        TyKind::Var(_) => Ty::clone(ty),
+        TyKind::ForAll(kind, ty) =>
+            TyKind::ForAll(kind.clone(), shift_ty(ty, amount, cutoff.shifted_in())).intern(),
+        TyKind::Exists(ty) => TyKind::Exists(shift_ty(ty, amount, cutoff.shifted_in())).intern(),
+        TyKind::Mu(ty) => TyKind::Mu(shift_ty(ty, amount, cutoff.shifted_in())).intern(),
+        TyKind::Lambda(kind, ty) =>
+            TyKind::Lambda(kind.clone(), shift_ty(ty, amount, cutoff.shifted_in())).intern(),
+        TyKind::App(f, x) =>
+            TyKind::App(shift_ty(f, amount, cutoff), shift_ty(x, amount, cutoff)).intern(),
+        TyKind::Constrained(class, arg, ty) =>
+            TyKind::Constrained(*class, shift_ty(arg, amount, cutoff), shift_ty(ty, amount, cutoff))
+                .intern(),
+    }
+}